@@ -0,0 +1,158 @@
+//! Counting progress with only `core`, for `no_std`.
+
+use core::time::Duration;
+
+/// A source of the time for a `ProgressCounter`, in ticks at a fixed rate, e.g. a hardware timer
+/// on a microcontroller. Only the difference between readings is used, so it can start anywhere,
+/// but it mustn't go backwards.
+///
+/// With the `std` feature, any `Clock` can be used, with `ClockTicks`.
+pub trait TickSource {
+    /// How many ticks there are in a second
+    fn ticks_per_sec(&self) -> u64;
+
+    /// The time now, in ticks
+    fn now(&self) -> u64;
+}
+
+impl<T: TickSource + ?Sized> TickSource for &T {
+    fn ticks_per_sec(&self) -> u64 {
+        (**self).ticks_per_sec()
+    }
+
+    fn now(&self) -> u64 {
+        (**self).now()
+    }
+}
+
+/// Counts how much has been done, out of a total (if known), for the fraction, rate & ETA. It
+/// only needs `core`, so it can be used without the `std` feature, e.g. in firmware which erases
+/// flash sectors, or reads sensor samples. The time comes from a `TickSource`.
+///
+/// It's much simpler than a `ProgressRecorderIter`: there are no averages, and no output.
+///
+/// ```
+/// # use iter_progress::{ProgressCounter, TickSource};
+/// # use std::cell::Cell;
+/// # use std::time::Duration;
+/// /// A timer counting milliseconds
+/// struct Timer<'a>(&'a Cell<u64>);
+///
+/// impl<'a> TickSource for Timer<'a> {
+///     fn ticks_per_sec(&self) -> u64 {
+///         1_000
+///     }
+///     fn now(&self) -> u64 {
+///         self.0.get()
+///     }
+/// }
+///
+/// let millis = Cell::new(0);
+/// let sectors = [0u32; 16];
+/// let mut counter = ProgressCounter::new(Timer(&millis), sectors.len());
+/// for _sector in sectors.iter().take(4) {
+///     // erase it…
+///     millis.set(millis.get() + 500);
+///     counter.inc();
+/// }
+/// assert_eq!(counter.fraction(), Some(0.25));
+/// assert_eq!(counter.rate(), 2.);
+/// assert_eq!(counter.eta(), Some(Duration::from_secs(6)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProgressCounter<T> {
+    ticks: T,
+
+    /// When it started, in ticks
+    started: u64,
+
+    done: usize,
+    total: Option<usize>,
+}
+
+impl<T: TickSource> ProgressCounter<T> {
+    /// Start counting now, with the time from `ticks`. `total` is how much there is to do, if
+    /// known.
+    pub fn new(ticks: T, total: impl Into<Option<usize>>) -> ProgressCounter<T> {
+        ProgressCounter {
+            started: ticks.now(),
+            ticks,
+            done: 0,
+            total: total.into(),
+        }
+    }
+
+    /// One more has been done
+    pub fn inc(&mut self) {
+        self.add(1);
+    }
+
+    /// `n` more have been done
+    pub fn add(&mut self, n: usize) {
+        self.done = self.done.saturating_add(n);
+    }
+
+    /// Set how much there is to do (`None` if not known), e.g. once it's been worked out.
+    pub fn set_total(&mut self, total: impl Into<Option<usize>>) {
+        self.total = total.into();
+    }
+
+    /// How much has been done
+    pub fn num_done(&self) -> usize {
+        self.done
+    }
+
+    /// How much there is to do, if known
+    pub fn total(&self) -> Option<usize> {
+        self.total
+    }
+
+    /// How far through this is, from 0 to 1, if the total is known. A total of 0 means there's
+    /// nothing to do, so it's 1. It's never more than 1, even if more than the total is done.
+    pub fn fraction(&self) -> Option<f64> {
+        let total = self.total?;
+        Some(if total == 0 {
+            1.
+        } else {
+            (self.done as f64 / total as f64).min(1.)
+        })
+    }
+
+    /// The percentage done, from 0 to 100, if the total is known
+    pub fn percent(&self) -> Option<f64> {
+        self.fraction().map(|fraction| fraction * 100.)
+    }
+
+    /// How long since it started
+    pub fn duration_since_start(&self) -> Duration {
+        let ticks = self.ticks.now().saturating_sub(self.started);
+        let per_sec = self.ticks.ticks_per_sec().max(1);
+        let nanos = u128::from(ticks % per_sec) * 1_000_000_000 / u128::from(per_sec);
+        Duration::new(ticks / per_sec, nanos as u32)
+    }
+
+    /// How many are done per second, since the start. 0 if no time has passed yet.
+    pub fn rate(&self) -> f64 {
+        let secs = self.duration_since_start().as_secs_f64();
+        if secs > 0. {
+            self.done as f64 / secs
+        } else {
+            0.
+        }
+    }
+
+    /// How long until it's all done, at the rate so far. `None` if the total isn't known,
+    /// nothing has been done yet, or it's too long for a `Duration`.
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.total?.saturating_sub(self.done);
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.rate();
+        if rate > 0. {
+            Duration::try_from_secs_f64(remaining as f64 / rate).ok()
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,4948 @@
+//! The parts of the crate which need the standard library, i.e. all but `ProgressCounter`. With
+//! the `std` feature, everything public here is re-exported from the crate root.
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::iter::{FusedIterator, Iterator};
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+// The standard library's clocks don't work in browsers, so use the browser's there
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::{Instant, SystemTime};
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use web_time::{Instant, SystemTime};
+
+#[cfg(feature = "tokio")]
+mod async_io;
+mod bytes;
+mod cancel;
+mod checkpoint;
+mod clock;
+mod events;
+mod handle;
+mod heartbeat;
+#[cfg(feature = "latency-histogram")]
+mod histogram;
+#[cfg(feature = "log")]
+mod logging;
+mod multi;
+mod nested;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod pause;
+#[cfg(feature = "prometheus")]
+mod prom;
+#[cfg(feature = "serde")]
+mod ser;
+#[cfg(all(unix, feature = "signals"))]
+mod signals;
+#[cfg(feature = "sparkline")]
+mod sparkline;
+#[cfg(feature = "futures")]
+mod stream;
+mod style;
+mod template;
+#[cfg(test)]
+mod tests;
+#[cfg(feature = "tracing")]
+mod trace;
+mod tree;
+mod trigger;
+mod units;
+mod watchdog;
+#[cfg(feature = "ratatui")]
+mod widget;
+
+#[cfg(feature = "tokio")]
+pub use self::async_io::{ProgressAsyncReader, ProgressAsyncWriter};
+pub use self::bytes::{ProgressLines, ProgressReader, ProgressWriter};
+pub use self::cancel::CancelToken;
+pub use self::checkpoint::Checkpoint;
+use self::clock::SharedClock;
+pub use self::clock::{Clock, ClockTicks, MockClock, SystemClock};
+pub use self::events::ProgressEvent;
+pub use self::handle::ProgressHandle;
+#[cfg(feature = "latency-histogram")]
+pub use self::histogram::LatencyHistogram;
+pub use self::multi::MultiProgress;
+pub use self::nested::NestedProgress;
+#[cfg(feature = "rayon")]
+pub use self::parallel::{OptionalParallelProgress, ParallelProgress, ParallelProgressableIter};
+pub use self::pause::PauseHandle;
+#[cfg(feature = "futures")]
+pub use self::stream::{ProgressStream, ProgressableStream};
+pub use self::style::{BarStyle, Color};
+pub use self::template::Template;
+pub use self::tree::ProgressTree;
+pub use self::trigger::Trigger;
+pub use self::units::{format_byte_rate, format_bytes, format_count, ByteUnits};
+#[cfg(feature = "ratatui")]
+pub use self::widget::ProgressWidget;
+use super::TickSource;
+
+/// Every step of the underlying iterator, one of these is generated. It contains all the
+/// information of how this iterator is progresing. Use the methods to access data on it.
+///
+/// With the `serde` feature, it can be serialised (e.g. to JSON) with the main stats.
+///
+/// Records can be compared. They're equal if all their stats are, regardless of where their
+/// output goes.
+#[derive(Debug, Clone)]
+pub struct ProgressRecord {
+    /// How many elements before this
+    num: usize,
+
+    /// How many items have been seen, including this one. The same as `num`, unless counting in
+    /// other units, or resumed with `.reset_to(…)`
+    num_items: usize,
+
+    /// How long since we started iterating.
+    iterating_for: Duration,
+
+    /// Value of underlying iterator's `.size_hint()`
+    size_hint: (usize, Option<usize>),
+
+    /// Whether `num` is a count of items (so the `size_hint` can be used for the fraction)
+    counting_items: bool,
+
+    /// If `.assumed_size(...)` was set on `ProgressableIter`, return that.
+    assumed_size: Option<usize>,
+
+    /// The underlying iterator's upper bound when we started, plus what was done by then, if
+    /// counting items
+    initial_total: Option<usize>,
+
+    /// If we have overridden the calculated fraction
+    assumed_fraction: Option<f64>,
+
+    /// The timestamp of when the previous record was created. Will be None if this is first.
+    previous_record_tm: Option<Instant>,
+
+    /// When the iteration started
+    started_iterating: Instant,
+
+    /// When this record was generated
+    tm: Instant,
+
+    /// The rolling average duration, if calculated
+    rolling_average_duration: Option<Duration>,
+
+    /// The exponential average duration, if calculated
+    exp_average_duration: Option<Duration>,
+
+    /// Running mean & variance of the durations between records
+    item_durations: RunningStats,
+
+    /// Change in the recent rate since the previous record, per second
+    acceleration: Option<f64>,
+
+    /// Rate over the window set with `.recent_window_hybrid(…)`, if set
+    recent_window_rate: Option<f64>,
+
+    /// The lowest & highest `.recent_rate()` so far
+    recent_rate_range: Option<(f64, f64)>,
+
+    /// Whether `.bar_unicode()` can use Unicode characters
+    unicode: bool,
+
+    /// If set, the format of `.message()`
+    template: Option<Template>,
+
+    /// If set, before every message
+    label: Option<Arc<str>>,
+
+    /// The outer iterator's fraction, if this is an inner one, with `.with_parent(…)`
+    parent_fraction: Option<f64>,
+
+    /// Whether output can redraw the current line, or should print plain lines
+    interactive: bool,
+
+    /// How many times an `Err` has been retried, when using `.progress_retry(…)`
+    num_retries: usize,
+
+    /// How many items failed, even after retrying, when using `.progress_retry(…)`
+    num_failed: usize,
+
+    /// Rate from a previous run, to compare against
+    baseline_rate: Option<f64>,
+
+    /// When this must be finished by
+    deadline: Option<SystemTime>,
+
+    /// Historical rates of each (equally sized) phase, for the ETA
+    phase_rates: Option<Arc<[f64]>>,
+
+    /// How `.eta()` is calculated
+    eta_strategy: EtaStrategy,
+
+    /// Where the `print_…` methods write to
+    output: OutputSink,
+
+    /// When `.should_print_every_n_sec(…)` last said to print, in nanoseconds of
+    /// `.duration_since_start()`. Shared with the recorder, and all its records
+    last_printed: Arc<AtomicU64>,
+
+    /// The recorder's clock, for the time now
+    clock: SharedClock,
+}
+
+impl PartialEq for ProgressRecord {
+    fn eq(&self, other: &Self) -> bool {
+        // Everything but the `output` & `last_printed`
+        self.num == other.num
+            && self.num_items == other.num_items
+            && self.iterating_for == other.iterating_for
+            && self.size_hint == other.size_hint
+            && self.counting_items == other.counting_items
+            && self.assumed_size == other.assumed_size
+            && self.initial_total == other.initial_total
+            && self.assumed_fraction == other.assumed_fraction
+            && self.previous_record_tm == other.previous_record_tm
+            && self.started_iterating == other.started_iterating
+            && self.tm == other.tm
+            && self.rolling_average_duration == other.rolling_average_duration
+            && self.exp_average_duration == other.exp_average_duration
+            && self.item_durations == other.item_durations
+            && self.acceleration == other.acceleration
+            && self.recent_window_rate == other.recent_window_rate
+            && self.recent_rate_range == other.recent_rate_range
+            && self.unicode == other.unicode
+            && self.template == other.template
+            && self.label == other.label
+            && self.parent_fraction == other.parent_fraction
+            && self.interactive == other.interactive
+            && self.num_retries == other.num_retries
+            && self.num_failed == other.num_failed
+            && self.baseline_rate == other.baseline_rate
+            && self.deadline == other.deadline
+            && self.phase_rates == other.phase_rates
+            && self.eta_strategy == other.eta_strategy
+    }
+}
+
+/// The progress message, i.e. `.message()`, so a record can go straight into `println!`, a log
+/// line, or an error context.
+///
+/// ```
+/// # use iter_progress::ProgressableIter;
+/// let (state, _) = (0..10).progress().with_template("{count}").next().unwrap();
+/// assert_eq!(format!("Processing: {}", state), "Processing: 1 / 10");
+/// ```
+impl std::fmt::Display for ProgressRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+/// How `ProgressRecord::eta` is calculated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EtaStrategy {
+    /// Assume the rate since the start will continue
+    #[default]
+    Naive,
+
+    /// Use `ProgressRecord::eta_adaptive`, i.e. blend the rate since the start with the recent
+    /// rate
+    Adaptive,
+}
+
+/// How to combine the item count & duration limits of `.recent_window_hybrid(…)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowPolicy {
+    /// The window is whichever is smaller: the last N items, or the last D seconds. Good for
+    /// bursty sources, where N items could span a long time. The window always includes at
+    /// least the previous record, so there is always a rate.
+    Smaller,
+
+    /// The window is whichever is larger: the last N items, or the last D seconds. Good for
+    /// sparse sources, where D seconds could have very few items. For fast sources, the window
+    /// can hold many items, and hence use a lot of memory.
+    Larger,
+}
+
+/// Timestamps of recent records, for `.recent_window_hybrid(…)`
+#[derive(Debug, Clone)]
+struct RecentWindow {
+    max_items: usize,
+    max_duration: Duration,
+    policy: WindowPolicy,
+
+    /// `(timestamp, done)` for every record in the window, oldest first
+    samples: VecDeque<(Instant, usize)>,
+}
+
+impl RecentWindow {
+    /// Add this record, drop anything which is now outside the window, and return the rate over
+    /// the window
+    fn add(&mut self, now: Instant, done: usize) -> Option<f64> {
+        self.samples.push_back((now, done));
+        loop {
+            let should_drop = match self.policy {
+                WindowPolicy::Smaller => {
+                    self.samples.len() > 2
+                        && (self.samples.len() - 1 > self.max_items
+                            || now - self.samples[0].0 > self.max_duration)
+                }
+                WindowPolicy::Larger => {
+                    // Only drop the oldest if the window would still be big enough without it
+                    self.samples.len() > 2
+                        && self.samples.len() - 2 >= self.max_items
+                        && now - self.samples[1].0 >= self.max_duration
+                }
+            };
+            if !should_drop {
+                break;
+            }
+            self.samples.pop_front();
+        }
+
+        let (oldest_tm, oldest_done) = self.samples[0];
+        if self.samples.len() < 2 || now <= oldest_tm {
+            None
+        } else {
+            Some((done - oldest_done) as f64 / (now - oldest_tm).as_secs_f64())
+        }
+    }
+}
+
+/// Timestamps of the records over the last `max_duration`, for `.rate_over(…)`
+#[derive(Debug, Clone)]
+struct TrailingRates {
+    max_duration: Duration,
+
+    /// `(timestamp, done)` for every record, oldest first. The oldest is the last one from before
+    /// `max_duration` ago, so a window of `max_duration` is fully covered.
+    samples: VecDeque<(Instant, usize)>,
+}
+
+impl TrailingRates {
+    fn new(max_duration: Duration) -> Self {
+        TrailingRates {
+            max_duration,
+            samples: VecDeque::new(),
+        }
+    }
+
+    fn add(&mut self, now: Instant, done: usize) {
+        self.samples.push_back((now, done));
+        while self.samples.len() > 2 && now - self.samples[1].0 >= self.max_duration {
+            self.samples.pop_front();
+        }
+    }
+
+    /// The rate from the last record from at least `window` before the latest one (or the
+    /// oldest, if there isn't one), to the latest one
+    fn rate_over(&self, window: Duration) -> Option<f64> {
+        let &(latest_tm, latest_done) = self.samples.back()?;
+        let &(oldest_tm, oldest_done) = self
+            .samples
+            .iter()
+            .rev()
+            .find(|(tm, _)| latest_tm - *tm >= window)
+            .unwrap_or(&self.samples[0]);
+        if latest_tm <= oldest_tm {
+            None
+        } else {
+            Some((latest_done - oldest_done) as f64 / (latest_tm - oldest_tm).as_secs_f64())
+        }
+    }
+}
+
+/// Reads the clock only every so many items, and interpolates the time for the items in
+/// between, for `.sample_time_every(…)`
+#[derive(Debug, Clone)]
+struct TimeSampling {
+    every: usize,
+
+    /// `(count, timestamp)` of the previous & latest times the clock was read
+    previous: Option<(usize, Instant)>,
+    latest: Option<(usize, Instant)>,
+
+    /// The time last returned, so time never goes backwards
+    last_returned: Option<Instant>,
+}
+
+impl TimeSampling {
+    fn new(every: usize) -> TimeSampling {
+        TimeSampling {
+            every: every.max(1),
+            previous: None,
+            latest: None,
+            last_returned: None,
+        }
+    }
+
+    /// The time for item `count`
+    fn now(&mut self, count: usize, clock: &dyn Clock) -> Instant {
+        let now = match (self.previous, self.latest) {
+            (previous, Some((latest_count, latest_tm)))
+                if count > latest_count && count - latest_count < self.every =>
+            {
+                match previous {
+                    // Carry on at the rate between the last 2 readings
+                    Some((previous_count, previous_tm)) => {
+                        latest_tm
+                            + (latest_tm - previous_tm).mul_f64(
+                                (count - latest_count) as f64
+                                    / (latest_count - previous_count) as f64,
+                            )
+                    }
+                    None => latest_tm,
+                }
+            }
+            _ => {
+                let now = clock.now();
+                self.previous = self.latest;
+                self.latest = Some((count, now));
+                now
+            }
+        };
+        let now = self.last_returned.map_or(now, |last| now.max(last));
+        self.last_returned = Some(now);
+        now
+    }
+}
+
+/// `num` with a `,` between every 3 digits, e.g. `1,234,567`
+fn thousands_separated(num: usize) -> String {
+    let digits = num.to_string();
+    let mut res = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            res.push(',');
+        }
+        res.push(digit);
+    }
+    res
+}
+
+/// Format a duration for people, e.g. `"1h 02m 03s"`, `"2m 03s"`, or `"3s"`. Useful with
+/// `ProgressRecord::eta` & `ProgressRecord::duration_since_start`.
+///
+/// ```
+/// # use iter_progress::format_duration;
+/// # use std::time::Duration;
+/// assert_eq!(format_duration(Duration::from_secs(133)), "2m 13s");
+/// assert_eq!(format_duration(Duration::from_secs(3_840)), "1h 04m 00s");
+/// ```
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, mins, secs) = (secs / 3600, (secs / 60) % 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, mins, secs)
+    } else if mins > 0 {
+        format!("{}m {:02}s", mins, secs)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Format the time of day of `time` in UTC, e.g. `"14:32 UTC"`. (The standard library can't
+/// tell what the local time zone is.)
+///
+/// ```
+/// # use iter_progress::format_time_of_day;
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// let time = UNIX_EPOCH + Duration::from_secs(2 * 86_400 + 14 * 3_600 + 32 * 60 + 59);
+/// assert_eq!(format_time_of_day(time), "14:32 UTC");
+/// ```
+pub fn format_time_of_day(time: SystemTime) -> String {
+    let secs = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since) => since.as_secs(),
+        Err(_) => 0,
+    };
+    format!("{:02}:{:02} UTC", (secs / 3600) % 24, (secs / 60) % 60)
+}
+
+/// A bar `width` characters wide, filled with `#` for fraction `f`
+fn ascii_bar(f: f64, width: usize) -> String {
+    let done = ((f.clamp(0., 1.) * width as f64) as usize).min(width);
+    format!("{}{}", "#".repeat(done), " ".repeat(width - done))
+}
+
+/// A bar `width` characters wide, filled with Unicode block elements for fraction `f`
+fn unicode_bar(f: f64, width: usize) -> String {
+    const PARTIAL: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let eighths = ((f.clamp(0., 1.) * (width * 8) as f64) as usize).min(width * 8);
+    let full = eighths / 8;
+    let mut bar = "█".repeat(full);
+    if full < width {
+        bar.push(PARTIAL[eighths % 8]);
+        bar.push_str(&" ".repeat(width - full - 1));
+    }
+    bar
+}
+
+/// Running mean & variance, calculated with Welford's algorithm, so it's constant memory, and
+/// the min & max.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct RunningStats {
+    n: usize,
+    mean: f64,
+    m2: f64,
+    min: f64,
+    max: f64,
+}
+
+impl RunningStats {
+    fn add(&mut self, value: f64) {
+        if self.n == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.n += 1;
+        let delta = value - self.mean;
+        self.mean += delta / (self.n as f64);
+        self.m2 += delta * (value - self.mean);
+    }
+
+    fn mean(&self) -> Option<f64> {
+        if self.n == 0 {
+            None
+        } else {
+            Some(self.mean)
+        }
+    }
+
+    fn stddev(&self) -> Option<f64> {
+        if self.n == 0 {
+            None
+        } else {
+            Some((self.m2 / (self.n as f64)).sqrt())
+        }
+    }
+
+    fn min_max(&self) -> Option<(f64, f64)> {
+        if self.n == 0 {
+            None
+        } else {
+            Some((self.min, self.max))
+        }
+    }
+}
+
+impl ProgressRecord {
+    /// Duration since iteration started
+    pub fn duration_since_start(&self) -> Duration {
+        self.iterating_for
+    }
+
+    /// Number of items we've generated so far. Will be 0 for the first element
+    ///
+    /// ```rust
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..1_000).progress();
+    /// let (state, num) = progressor.next().unwrap();
+    /// assert_eq!(state.num_done(), 1);
+    /// ```
+    ///
+    ///
+    /// ```rust
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..1_000).progress().skip(10);
+    /// let (state, num) = progressor.next().unwrap();
+    /// assert_eq!(state.num_done(), 11);
+    /// ```
+    pub fn num_done(&self) -> usize {
+        self.num
+    }
+
+    /// The underlying iterator's `.size_hint()` when this record was generated, i.e. the
+    /// bounds on how many items were still to come.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let (state, _) = (0..10).filter(|n| n % 2 == 0).progress().next().unwrap();
+    /// assert_eq!(state.size_hint(), (0, Some(9)));
+    /// ```
+    pub fn size_hint(&self) -> (usize, Option<usize>) {
+        self.size_hint
+    }
+
+    /// The `Instant` for when the previous record was generated. None if there was no previous
+    /// record.
+    ///
+    /// This can be useful for calculating fine-grained rates
+    pub fn previous_record_tm(&self) -> Option<Instant> {
+        self.previous_record_tm
+    }
+
+    /// How long between the previous record and this one. None if this is the first record.
+    ///
+    /// Useful for spotting individual slow items.
+    pub fn since_previous(&self) -> Option<Duration> {
+        self.previous_record_tm.map(|previous| self.tm - previous)
+    }
+
+    /// How long this item took, i.e. the time since the previous item, or since iterating
+    /// started for the first. Like `.since_previous()`, but there's always a value. (When only
+    /// generating a record every so often, it's the time since the previous record.)
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # use std::time::Duration;
+    /// for (state, _row) in (0..10).progress() {
+    ///     if state.time_since_last_item() > Duration::from_secs(1) {
+    ///         eprintln!("Item {} was slow", state.num_done());
+    ///     }
+    /// }
+    /// ```
+    pub fn time_since_last_item(&self) -> Duration {
+        self.since_previous()
+            .unwrap_or_else(|| self.tm.saturating_duration_since(self.started_iterating))
+    }
+
+    /// How many seconds it has been, now, since this record's item was produced. The time now
+    /// is from the recorder's clock (see `OptionalProgressRecorderIter::with_clock`).
+    ///
+    /// If the latest record is kept somewhere else (e.g. for a monitoring thread), this shows
+    /// when the iterator has stalled. To have a callback called when the underlying iterator
+    /// stalls, use `OptionalProgressRecorderIter::on_item_timeout`.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let (state, _) = (0..10).progress().next().unwrap();
+    /// if state.seconds_since_last_item() > 60. {
+    ///     eprintln!("Stalled!");
+    /// }
+    /// ```
+    pub fn seconds_since_last_item(&self) -> f64 {
+        self.clock
+            .now()
+            .saturating_duration_since(self.tm)
+            .as_secs_f64()
+    }
+
+    /// Return the time `Instant` that this iterator started
+    pub fn started_iterating(&self) -> Instant {
+        self.started_iterating
+    }
+
+    /// Number of items per second, calculated from the start. 0 if no time has passed yet.
+    pub fn rate(&self) -> f64 {
+        let secs = self.duration_since_start().as_secs_f64();
+        if secs > 0. {
+            (self.num_done() as f64) / secs
+        } else {
+            0.
+        }
+    }
+
+    /// Number of items per `unit` of time, calculated from the start, like `.rate()`.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # use std::time::Duration;
+    /// let (state, _) = (0..10).progress().next().unwrap();
+    /// let per_5_min = state.rate_per(Duration::from_secs(5 * 60));
+    /// ```
+    pub fn rate_per(&self, unit: Duration) -> f64 {
+        self.rate() * unit.as_secs_f64()
+    }
+
+    /// How far through the iterator as a fraction, if known.
+    /// First looks at the `assumed_fraction` if you have overridden that.
+    /// Uses the underlying iterator's `.size_hint()` method if that is an exact value, falling
+    /// back to any assumed size (set with `.assume_size(...)`), then the upper bound of the
+    /// `.size_hint()` when iterating started. Otherwise returns `None`.
+    ///
+    /// ```
+    /// use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..1_000).progress().skip(120);
+    /// let (state, num) = progressor.next().unwrap();
+    /// assert_eq!(num, 120);
+    /// assert_eq!(state.fraction(), Some(0.121));
+    /// ```
+    ///
+    /// The upper bound is captured once, so for e.g. a `.filter(…)`, the fraction doesn't jump
+    /// about as the live bounds shrink. It's 1 once the iterator is exhausted.
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..100).filter(|n| n % 2 == 1).progress();
+    /// let (state, _) = progressor.nth(9).unwrap();
+    /// assert_eq!(state.fraction(), Some(0.1));
+    /// let (state, _) = progressor.last().unwrap();
+    /// assert_eq!(state.fraction(), Some(1.));
+    /// ```
+    ///
+    /// Returns `None` if we cannot know, e.g. for an infinite iterator
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..).progress().skip(120);
+    /// let (state, num) = progressor.next().unwrap();
+    /// assert_eq!(state.fraction(), None);
+    /// ```
+    ///
+    /// An assumed size can be wrong, so when using one, this is only 1 (i.e. 100%) once the
+    /// underlying iterator is known to be exhausted. Until then, it stays below 1, even after
+    /// going past the assumed size.
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..20).filter(|_| true).progress().assume_size(10);
+    /// let (state, _) = progressor.nth(9).unwrap();
+    /// assert!(state.fraction().unwrap() < 1.);
+    /// let (state, _) = progressor.last().unwrap();
+    /// assert_eq!(state.fraction(), Some(1.));
+    /// ```
+    pub fn fraction(&self) -> Option<f64> {
+        if self.assumed_fraction.is_some() {
+            return self.assumed_fraction;
+        }
+
+        let total = self.total()?;
+        let done = self.num_used();
+        if self.size_is_exact() {
+            Some((done as f64) / (total as f64))
+        } else if self.size_hint.1 == Some(0) {
+            // We've finished
+            Some(((done as f64) / (total as f64)).min(1.))
+        } else {
+            // There's more to come, so don't say we're finished
+            Some((done as f64) / (total.max(done + 1) as f64))
+        }
+    }
+
+    /// The lowest & highest the fraction could be, going by the bounds of the underlying
+    /// iterator's `.size_hint()`, e.g. so a UI can show "between 40% and 55% done". When the
+    /// size is exact (or assumed), both are the same as `.fraction()`. `None` if there's no upper
+    /// bound, or when counting something other than items.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..100).filter(|n| n % 2 == 0).chain(0..20).progress();
+    /// let (state, _) = progressor.nth(29).unwrap();
+    /// // 30 done, and between 20 & 20 + 41 to come
+    /// assert_eq!(state.fraction_bounds(), Some((30. / 91., 30. / 50.)));
+    ///
+    /// let (state, _) = (0..10).progress().nth(4).unwrap();
+    /// assert_eq!(state.fraction_bounds(), Some((0.5, 0.5)));
+    /// ```
+    pub fn fraction_bounds(&self) -> Option<(f64, f64)> {
+        if self.assumed_fraction.is_some() || self.size_is_exact() || self.assumed_size.is_some() {
+            return self.fraction().map(|fraction| (fraction, fraction));
+        }
+        if !self.counting_items {
+            return None;
+        }
+        let done = self.num_used();
+        let (lower, upper) = self.size_hint;
+        let upper = upper?;
+        if done == 0 {
+            return Some((0., 0.));
+        }
+        Some((
+            done as f64 / (done + upper) as f64,
+            done as f64 / (done + lower) as f64,
+        ))
+    }
+
+    /// Whether the underlying iterator's `.size_hint()` is exact, and used for the total
+    fn size_is_exact(&self) -> bool {
+        self.counting_items && self.size_hint.1 == Some(self.size_hint.0)
+    }
+
+    /// The total number of items, if known. Uses the underlying iterator's `.size_hint()` if
+    /// that is an exact value, falling back to any assumed size, then the initial upper bound.
+    fn total(&self) -> Option<usize> {
+        if self.size_is_exact() {
+            // use that directly
+            Some(self.size_hint.0 + self.num_used())
+        } else if self.assumed_size.is_some() {
+            self.assumed_size
+        } else {
+            self.initial_total
+        }
+    }
+
+    /// How many are still to do, if the total is known (see `.fraction()`). In the same units as
+    /// `.num_done()`. 0 if more than the total has been done.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let (state, _) = (0..100).progress().nth(9).unwrap();
+    /// assert_eq!(state.remaining(), Some(90));
+    ///
+    /// let (state, _) = (0..).progress().nth(9).unwrap();
+    /// assert_eq!(state.remaining(), None);
+    /// ```
+    pub fn remaining(&self) -> Option<usize> {
+        self.total()
+            .map(|total| total.saturating_sub(self.num_used()))
+    }
+
+    /// How many of the underlying iterator's items have been used up: those done, and, with
+    /// `.progress_retry(…)`, every `Err`, since they count towards the total too
+    fn num_used(&self) -> usize {
+        self.num + self.num_retries + self.num_failed
+    }
+
+    /// How many items are done, out of the total (if known), with thousands separators, e.g.
+    /// `"1,234 / 5,000"`, or `"1,234"` if the total isn't known.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..5_000).progress().skip(1_233);
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.count_of_total(), "1,234 / 5,000");
+    ///
+    /// let mut progressor = (0..).progress().skip(1_233);
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.count_of_total(), "1,234");
+    /// ```
+    pub fn count_of_total(&self) -> String {
+        match self.total() {
+            Some(total) => format!(
+                "{} / {}",
+                thousands_separated(self.num_done()),
+                thousands_separated(total)
+            ),
+            None => thousands_separated(self.num_done()),
+        }
+    }
+
+    /// Like `.count_of_total()`, when counting bytes (e.g. with `ProgressReader`), formatted
+    /// with `format_bytes`, e.g. `"1.2 GiB / 4.0 GiB"`, or `"1.2 GiB"` if the total isn't known.
+    ///
+    /// ```
+    /// # use iter_progress::{ByteUnits, ProgressReader};
+    /// # use std::io::Read;
+    /// let data = vec![0u8; 4_096];
+    /// let mut reader = ProgressReader::new(&data[..], data.len());
+    /// reader.read_exact(&mut [0u8; 1_536]).unwrap();
+    /// let state = reader.record().unwrap();
+    /// assert_eq!(state.bytes_of_total(ByteUnits::Binary), "1.5 KiB / 4.0 KiB");
+    /// ```
+    pub fn bytes_of_total(&self, units: ByteUnits) -> String {
+        let done = format_bytes(self.num_done() as f64, units);
+        match self.total() {
+            Some(total) => format!("{} / {}", done, format_bytes(total as f64, units)),
+            None => done,
+        }
+    }
+
+    /// The rate, when counting bytes, formatted with `format_byte_rate`, e.g. `"12.4 MiB/s"`.
+    pub fn byte_rate(&self, units: ByteUnits) -> String {
+        format_byte_rate(self.rate(), units)
+    }
+
+    /// Like `.count_of_total()`, but abbreviated with `format_count`, e.g. `"1.2M / 5.0M"`.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..5_000_000).progress().skip(1_233_999);
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.short_count_of_total(1), "1.2M / 5.0M");
+    /// ```
+    pub fn short_count_of_total(&self, precision: usize) -> String {
+        let done = format_count(self.num_done() as f64, precision);
+        match self.total() {
+            Some(total) => format!("{} / {}", done, format_count(total as f64, precision)),
+            None => done,
+        }
+    }
+
+    /// The rate, abbreviated with `format_count`, e.g. `"45.3k/s"`.
+    pub fn short_rate(&self, precision: usize) -> String {
+        format!("{}/s", format_count(self.rate(), precision))
+    }
+
+    /// Assume that this is actually at this fraction through
+    /// If the underlying Iterator doesn't provide a useful `size_hint`, but you "know" the real
+    /// fraction (e.g. if reading from a file), you can override the value for this
+    /// `ProgressRecord`. This new value is used for rate & time calculations.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..).progress();
+    /// let (mut state, _num) = progressor.next().unwrap();
+    /// assert_eq!(state.fraction(), None);     // No fraction possible
+    /// // Be we know we're 12% the way through
+    /// state.assume_fraction(0.12);
+    /// assert_eq!(state.fraction(), Some(0.12));
+    /// ```
+    pub fn assume_fraction(&mut self, f: impl Into<f64>) {
+        self.assumed_fraction = Some(f.into())
+    }
+
+    /// Percentage progress through the iterator, if known.
+    ///
+    /// ```
+    /// use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..1_000).progress().skip(120);
+    /// let (state, num) = progressor.next().unwrap();
+    /// assert_eq!(state.percent(), Some(12.1));
+    /// ```
+    ///
+    /// Returns `None` if we cannot know, e.g. for an infinite iterator
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..).progress().skip(120);
+    /// let (state, num) = progressor.next().unwrap();
+    /// assert_eq!(state.percent(), None);
+    /// ```
+    pub fn percent(&self) -> Option<f64> {
+        self.fraction().map(|f| f * 100.)
+    }
+
+    /// Print out `msg`, but only if there has been `n` seconds since last printout. (like
+    /// `print!()`, so newline not included) It's written to the `OutputSink`, stdout by default.
+    ///
+    /// No matter how many items there are, this prints at most once every `n` seconds.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// for (state, _) in (0..1_000).progress() {
+    ///     state.print_every_n_sec(1., "Still going\n");
+    /// }
+    /// ```
+    pub fn print_every_n_sec<T: std::fmt::Display>(&self, n: f32, msg: T) {
+        if self.should_print_every_n_sec(n) {
+            self.output.write(format_args!("{}", msg));
+        }
+    }
+
+    /// Like `.print_every_n_sec(…)`, but the message is only made (by calling `msg`) if it's
+    /// printed, so formatting doesn't slow down the other iterations.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// for (state, num) in (0..1_000).progress() {
+    ///     state.print_every_n_sec_with(1., || format!("Up to {}\n", num));
+    /// }
+    /// ```
+    pub fn print_every_n_sec_with<T, F>(&self, n: f32, msg: F)
+    where
+        T: std::fmt::Display,
+        F: FnOnce() -> T,
+    {
+        if self.should_print_every_n_sec(n) {
+            self.output.write(format_args!("{}", msg()));
+        }
+    }
+
+    /// Should we print now, if printing at most once every `n` seconds? This is what
+    /// `.print_every_n_sec(n, …)` uses.
+    ///
+    /// The time of the last print is kept in the recorder, so when this returns true, it's
+    /// counted as printing, and it won't return true again (for any record from the same
+    /// recorder) until `n` seconds later. Unlike `.should_do_every_n_sec(n)`, which is true when
+    /// crossing into the next `n` second period, two prints are never closer than `n` seconds.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// for (state, num) in (0..1_000).progress() {
+    ///     if state.should_print_every_n_sec(1.) {
+    ///         println!("Up to {}", num);
+    ///     }
+    /// }
+    /// ```
+    pub fn should_print_every_n_sec(&self, n: impl Into<f32>) -> bool {
+        // Too long for a `Duration` (e.g. infinite) means never
+        let n = match Duration::try_from_secs_f64(f64::from(n.into()).max(0.)) {
+            Ok(n) => n,
+            Err(_) => return false,
+        };
+        let now = self.iterating_for.as_nanos() as u64;
+        let last = self.last_printed.load(Ordering::Relaxed);
+        if Duration::from_nanos(now.saturating_sub(last)) < n {
+            return false;
+        }
+        // If another record has printed since `last`, it's not time yet
+        self.last_printed
+            .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Print a one line summary of the progress (bar, percent, count, rate & ETA, or the time
+    /// elapsed if the ETA isn't known), every `n` seconds.
+    ///
+    /// It's written to the `OutputSink`, stdout by default. When the output is interactive (see
+    /// `OutputMode`), the line is redrawn in place (with `\r`), so print a newline after
+    /// iterating. Otherwise (e.g. in CI logs), a new line is
+    /// printed at most every 10 seconds (or `n` if that's longer), so logs aren't flooded.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # let my_big_vec = vec![false; 100];
+    /// for (state, val) in my_big_vec.iter().progress() {
+    ///     state.print_progress_every_n_sec(0.5);
+    /// }
+    /// println!();
+    /// ```
+    pub fn print_progress_every_n_sec(&self, n: impl Into<f32>) {
+        let n = n.into();
+        if self.interactive {
+            if self.should_do_every_n_sec(n) {
+                self.output.write(format_args!("\r{}", self.message()));
+            }
+        } else if self.should_do_every_n_sec(n.max(PLAIN_OUTPUT_MIN_SECS)) {
+            self.output.write(format_args!("{}\n", self.message()));
+        }
+    }
+
+    /// The progress message, from the `Template` set with `.with_template(…)`, or by default, a
+    /// one line summary with the percent, count, rate & ETA (or the time elapsed if the ETA
+    /// isn't known), after the label, if there is one. This is what
+    /// `.print_progress_every_n_sec(…)` prints, and what the record is displayed as.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let (state, _) = (0..10).progress().next().unwrap();
+    /// assert!(state.message().contains(" 10.0% 1 / 10 items, "));
+    /// ```
+    pub fn message(&self) -> String {
+        self.labelled(match self.template {
+            Some(ref template) => template.render(self),
+            None => self.status_line(),
+        })
+    }
+
+    /// The label set with `.with_label(…)`, if any, e.g. `"parsing"`.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// For an inner iterator (with `.with_parent(…)`), how far through the outer iterator is,
+    /// including this inner iterator's progress. See `NestedProgress::fraction`.
+    pub fn parent_fraction(&self) -> Option<f64> {
+        self.parent_fraction
+    }
+
+    /// `line`, after the label (if any), e.g. `"parsing: 12 items, …"`
+    fn labelled(&self, line: String) -> String {
+        match self.label {
+            Some(ref label) => format!("{}: {}", label, line),
+            None => line,
+        }
+    }
+
+    /// The line drawn by `.progress_bar()`, e.g.
+    /// `"[██████▌             ]  32.5% 325 / 1,000, 12.3 items/sec, ETA 55s"`. If the fraction
+    /// isn't known, there's a spinner instead (when interactive), e.g.
+    /// `"⠹ 325 items, 12.3 items/sec, 26s elapsed"`
+    fn bar_line(&self, style: &BarStyle) -> String {
+        self.labelled(match self.template {
+            Some(ref template) => template.render(self),
+            None => self.default_bar_line(style),
+        })
+    }
+
+    /// The line drawn by `.progress_bar()`, without a `Template` or label
+    fn default_bar_line(&self, style: &BarStyle) -> String {
+        match self.bar_unicode(20) {
+            Some(bar) => {
+                let filled = bar.trim_end_matches(' ');
+                let unfilled = style
+                    .unfilled_char
+                    .to_string()
+                    .repeat(bar.len() - filled.len());
+                let percent = format!("{:5.1}%", self.percent().unwrap_or_default());
+                let mut line = format!(
+                    "{}{}{}{} {} {}, {}",
+                    style.brackets.0,
+                    style.paint(filled, style.filled_color, false, self.interactive),
+                    style.paint(&unfilled, style.unfilled_color, false, self.interactive),
+                    style.brackets.1,
+                    style.paint(&percent, None, style.bold_percent, self.interactive),
+                    self.bar_count(style),
+                    self.bar_rate(style)
+                );
+                if let Some(eta) = self.eta() {
+                    line.push_str(&format!(", ETA {}", format_duration(eta)));
+                }
+                if style.completion_time {
+                    if let Some(when) = self.estimated_completion_time() {
+                        line.push_str(&format!(", done at {}", format_time_of_day(when)));
+                    }
+                }
+                line
+            }
+            None => {
+                let spinner = if self.interactive {
+                    format!("{} ", self.spinner())
+                } else {
+                    String::new()
+                };
+                let count = match style.byte_units {
+                    Some(_) => self.bar_count(style),
+                    None => format!("{} items", self.bar_count(style)),
+                };
+                format!(
+                    "{}{}, {}, {} elapsed",
+                    spinner,
+                    count,
+                    self.bar_rate(style),
+                    format_duration(self.duration_since_start())
+                )
+            }
+        }
+    }
+
+    /// The count for `.progress_bar()`, in bytes if the style says so
+    fn bar_count(&self, style: &BarStyle) -> String {
+        match (style.byte_units, style.short_counts) {
+            (Some(units), _) => self.bytes_of_total(units),
+            (None, Some(precision)) => self.short_count_of_total(precision),
+            (None, None) => self.count_of_total(),
+        }
+    }
+
+    /// The rate for `.progress_bar()`, in bytes if the style says so
+    fn bar_rate(&self, style: &BarStyle) -> String {
+        match (style.byte_units, style.short_counts) {
+            (Some(units), _) => self.byte_rate(units),
+            (None, Some(precision)) => {
+                format!("{} items/sec", format_count(self.rate(), precision))
+            }
+            (None, None) => format!("{:.1} items/sec", self.rate()),
+        }
+    }
+
+    /// A frame of a spinner animation, which changes every 0.1 sec
+    fn spinner(&self) -> char {
+        let frames: &[char] = if self.unicode {
+            &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏']
+        } else {
+            &['|', '/', '-', '\\']
+        };
+        let frame = self.duration_since_start().as_millis() / 100;
+        frames[(frame % frames.len() as u128) as usize]
+    }
+
+    /// One line summary of the progress, with the ETA if known, or the time elapsed
+    fn status_line(&self) -> String {
+        let mut line = match self.percent() {
+            Some(percent) => format!(
+                "[{}] {:5.1}% {} items, {:.1} items/sec",
+                self.bar_unicode(20).unwrap_or_default(),
+                percent,
+                self.count_of_total(),
+                self.rate()
+            ),
+            None => format!(
+                "{} items, {:.1} items/sec",
+                self.count_of_total(),
+                self.rate()
+            ),
+        };
+        match self.eta() {
+            Some(eta) => line.push_str(&format!(", ETA {}", format_duration(eta))),
+            None => line.push_str(&format!(
+                ", {} elapsed",
+                format_duration(self.duration_since_start())
+            )),
+        }
+        line
+    }
+
+    /// One line summary of everything done, for after iterating, e.g.
+    /// `"1,000 items in 12s, 83.3 items/sec"`
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let (state, _) = (0..10).progress().last().unwrap();
+    /// assert!(state.summary_line().starts_with("10 items in "));
+    /// ```
+    pub fn summary_line(&self) -> String {
+        format!(
+            "{} items in {}, {:.1} items/sec",
+            thousands_separated(self.num_done()),
+            format_duration(self.duration_since_start()),
+            self.rate()
+        )
+    }
+
+    /// The progress as a JSON object, e.g.
+    /// `{"done":1234,"total":10000,"rate":512.3,"eta_secs":17}`. `total` & `eta_secs` are `null`
+    /// when not known. There's no trailing newline.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let (state, _) = (0..10).progress().next().unwrap();
+    /// assert!(state.json_line().starts_with(r#"{"done":1,"total":10,"rate":"#));
+    /// ```
+    pub fn json_line(&self) -> String {
+        fn or_null(val: Option<impl std::fmt::Display>) -> String {
+            val.map_or_else(|| "null".to_string(), |val| val.to_string())
+        }
+        let rate = self.rate();
+        let rate = if rate.is_finite() {
+            Some(format!("{:.1}", rate))
+        } else {
+            None
+        };
+        format!(
+            r#"{{"done":{},"total":{},"rate":{},"eta_secs":{}}}"#,
+            self.num_done(),
+            or_null(self.total()),
+            or_null(rate),
+            or_null(self.eta().map(|eta| eta.as_secs()))
+        )
+    }
+
+    /// The record as a JSON object, for other programs to parse, e.g.
+    /// `{"done":1,"total":4,"elapsed_secs":10.0,"rate":0.1,"fraction":0.25,"eta_secs":30.0}`.
+    /// These fields are always there, in this order, and are `null` when not known. Durations are
+    /// in (fractional) seconds. It's the same as the `serde` serialisation, without needing
+    /// `serde`. There's no trailing newline.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let (state, _) = (0..4).progress().next().unwrap();
+    /// assert!(state.to_json().starts_with(r#"{"done":1,"total":4,"elapsed_secs":"#));
+    /// ```
+    pub fn to_json(&self) -> String {
+        fn num(val: Option<f64>) -> String {
+            match val {
+                Some(val) if val.is_finite() => format!("{:?}", val),
+                _ => "null".to_string(),
+            }
+        }
+        format!(
+            concat!(
+                r#"{{"done":{},"total":{},"elapsed_secs":{},"rate":{},"#,
+                r#""fraction":{},"eta_secs":{}}}"#
+            ),
+            self.num_done(),
+            self.total()
+                .map_or_else(|| "null".to_string(), |total| total.to_string()),
+            num(Some(self.duration_since_start().as_secs_f64())),
+            num(Some(self.rate())),
+            num(self.fraction()),
+            num(self.eta().map(|eta| eta.as_secs_f64()))
+        )
+    }
+
+    /// The text for the terminal title, e.g. `"47% (470 / 1,000)"`, or `"470 items"`
+    fn terminal_title(&self) -> String {
+        match self.percent() {
+            Some(percent) => format!("{:.0}% ({})", percent, self.count_of_total()),
+            None => format!("{} items", self.count_of_total()),
+        }
+    }
+
+    /// Call this function, but only every n sec (as close as possible).
+    /// Could be a print statement.
+    pub fn do_every_n_sec<F: FnOnce(&Self)>(&self, n: impl Into<f32>, f: F) {
+        if self.should_do_every_n_sec(n) {
+            f(self);
+        }
+    }
+
+    /// If we want to do every `n` sec, should we do it now?
+    pub fn should_do_every_n_sec(&self, n: impl Into<f32>) -> bool {
+        let n = f64::from(n.into());
+        let secs_since_start = self.duration_since_start().as_secs_f64();
+
+        match self.previous_record_tm() {
+            None => {
+                // This iteration is the first time, so we should print if more than `n` seconds
+                // have gone past
+                secs_since_start > n
+            }
+            Some(last_time) => {
+                let last_time_offset = self
+                    .duration_since_start()
+                    .saturating_sub(self.tm - last_time);
+                let current_step = secs_since_start / n;
+                let last_step = last_time_offset.as_secs_f64() / n;
+
+                current_step.trunc() > last_step.trunc()
+            }
+        }
+    }
+
+    /// If we want to do every `n` items, should we do it now?
+    ///
+    /// This counts items, even when `.num_done()` is in other units, e.g. with
+    /// `.progress_weighted(…)`.
+    pub fn should_do_every_n_items(&self, n: usize) -> bool {
+        self.num_items > 0 && (self.num_items - 1) % n == 0
+    }
+
+    /// Print out `msg`, but only if there has been `n` items. It's written to the `OutputSink`,
+    /// stdout by default.
+    /// Often you want to print out a debug message every 1,000 items or so. This function does
+    /// that.
+    pub fn print_every_n_items<T: std::fmt::Display>(&self, n: usize, msg: T) {
+        if self.should_do_every_n_items(n) {
+            self.output.write(format_args!("{}", msg));
+        }
+    }
+
+    /// Like `.print_every_n_items(…)`, but the message is only made (by calling `msg`) if it's
+    /// printed, so formatting doesn't slow down the other iterations.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// for (state, num) in (0..1_000).progress() {
+    ///     state.print_every_n_items_with(100, || format!("Up to {}\n", num));
+    /// }
+    /// ```
+    pub fn print_every_n_items_with<T, F>(&self, n: usize, msg: F)
+    where
+        T: std::fmt::Display,
+        F: FnOnce() -> T,
+    {
+        if self.should_do_every_n_items(n) {
+            self.output.write(format_args!("{}", msg()));
+        }
+    }
+
+    /// Do thing but only every `n` items.
+    /// Could be a print statement.
+    ///
+    /// takes 2 arguments, `n` and the function (`f`) which takes a `&ProgressState`. `f` will only
+    /// be called every `n` items that pass through the iterator. It can be any closure, e.g. one
+    /// which mutates or moves what it captures, and any formatting in it only happens then.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// for (state, _) in (0..150).progress() {
+    ///    state.do_every_n_items(5, |state| {
+    ///        println!("Current progress: {}%", state.percent().unwrap());
+    ///    });
+    /// }
+    /// ```
+    ///
+    /// e.g. to flush a batch:
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut batch = Vec::new();
+    /// let mut flushed = Vec::new();
+    /// for (state, num) in (0..10).progress() {
+    ///     batch.push(num);
+    ///     state.do_every_n_items(4, |_| flushed.push(std::mem::take(&mut batch)));
+    /// }
+    /// assert_eq!(flushed, vec![vec![0], vec![1, 2, 3, 4], vec![5, 6, 7, 8]]);
+    /// ```
+    pub fn do_every_n_items<F: FnOnce(&Self)>(&self, n: usize, f: F) {
+        if self.should_do_every_n_items(n) {
+            f(self);
+        }
+    }
+
+    /// Rolling average time to process each item this iterator is processing if it is recording
+    /// that. None if it's not being recorded, or it's too soon to know (e.g. for the first item).
+    pub fn rolling_average_duration(&self) -> &Option<Duration> {
+        &self.rolling_average_duration
+    }
+
+    /// Rolling average number of items per second this iterator is processing if it is recording
+    /// that. None if it's not being recorded, or it's too soon to know (e.g. for the first item).
+    pub fn rolling_average_rate(&self) -> Option<f64> {
+        self.rolling_average_duration.map(|d| 1. / d.as_secs_f64())
+    }
+
+    /// Exponential average time to process each item this iterator is processing if it is recording
+    /// that. None if it's not being recorded, or it's too soon to know (e.g. for the first item).
+    pub fn exp_average_duration(&self) -> &Option<Duration> {
+        &self.exp_average_duration
+    }
+
+    /// Exponential average number of items per second this iterator is processing if it is recording
+    /// that. None if it's not being recorded, or it's too soon to know (e.g. for the first item).
+    pub fn exp_average_rate(&self) -> Option<f64> {
+        self.exp_average_duration.map(|d| 1. / d.as_secs_f64())
+    }
+
+    /// The exponentially weighted moving average (EWMA) rate, in items per second, i.e.
+    /// `.exp_average_rate()`. It's smoother than the recent rate on bursty iterators. Set the
+    /// smoothing factor (alpha) with `.with_exp_average(alpha)`. None if that isn't set, or it's
+    /// too soon to know.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # let my_big_vec = vec![false; 100];
+    /// for (state, _) in my_big_vec.iter().progress().with_exp_average(0.1) {
+    ///     if let Some(rate) = state.rate_ewma() {
+    ///         // Show the smoothed rate
+    ///     }
+    /// }
+    /// ```
+    pub fn rate_ewma(&self) -> Option<f64> {
+        self.exp_average_rate()
+    }
+
+    /// The current rate, from the window set with `.recent_window_hybrid(…)` if that's set, else
+    /// the rolling average if that's being recorded, else from the exponential average. None if
+    /// none are being recorded, or it's too soon to know.
+    pub fn recent_rate(&self) -> Option<f64> {
+        self.recent_window_rate
+            .or_else(|| self.rolling_average_rate())
+            .or_else(|| self.exp_average_rate())
+    }
+
+    /// How fast `.recent_rate()` is changing, in items per second per second, i.e. is
+    /// throughput speeding up (positive) or slowing down (negative). None until there have been 2
+    /// records with a `.recent_rate()`.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # let my_big_vec = vec![false; 100];
+    /// for (state, val) in my_big_vec.iter().progress().with_rolling_average(10) {
+    ///     if state.acceleration().map_or(false, |a| a < 0.) {
+    ///         // slowing down
+    ///     }
+    /// }
+    /// ```
+    pub fn acceleration(&self) -> Option<f64> {
+        self.acceleration
+    }
+
+    /// The highest `.recent_rate()` seen so far, in items per second. None if there's been no
+    /// recent rate, e.g. no rolling average or recent window is set.
+    ///
+    /// With `.trough_rate()`, this gives the range of the throughput, including on the final
+    /// summary from `.finish()`.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let (summary, _) = (0..100).progress().recent_window_items(10).last().unwrap();
+    /// assert!(summary.trough_rate() <= summary.peak_rate());
+    /// ```
+    pub fn peak_rate(&self) -> Option<f64> {
+        self.recent_rate_range.map(|(_, peak)| peak)
+    }
+
+    /// The lowest `.recent_rate()` seen so far, in items per second. None if there's been no
+    /// recent rate. See `.peak_rate()`.
+    pub fn trough_rate(&self) -> Option<f64> {
+        self.recent_rate_range.map(|(trough, _)| trough)
+    }
+
+    /// A progress bar, `width` characters wide, using `#` for the done part. None if the fraction
+    /// isn't known.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..4).progress();
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.bar(8), Some("##      ".to_string()));
+    /// ```
+    pub fn bar(&self, width: usize) -> Option<String> {
+        self.fraction().map(|f| ascii_bar(f, width))
+    }
+
+    /// A progress bar, `width` characters wide, using Unicode block elements, so that it advances
+    /// in eighths of a character. This looks smoother than `.bar()`, especially at small widths.
+    /// Falls back to `.bar()` if Unicode has been turned off (with `.with_unicode(false)`). None if
+    /// the fraction isn't known.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..8).progress();
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.bar_unicode(4), Some("▌   ".to_string()));
+    /// ```
+    pub fn bar_unicode(&self, width: usize) -> Option<String> {
+        if !self.unicode {
+            return self.bar(width);
+        }
+        self.fraction().map(|f| unicode_bar(f, width))
+    }
+
+    /// How much faster this is than the baseline rate (set with `.with_baseline_rate(…)`), i.e.
+    /// `rate() / baseline`. 1.5 means 50% faster, 0.5 means half as fast. None if there is no
+    /// baseline, or it is zero.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # let my_big_vec = vec![false; 100];
+    /// for (state, val) in my_big_vec.iter().progress().with_baseline_rate(1_000.) {
+    ///     state.do_every_n_items(10, |state| {
+    ///         println!("{:.1}x the speed of last run", state.speedup().unwrap());
+    ///     });
+    /// }
+    /// ```
+    pub fn speedup(&self) -> Option<f64> {
+        match self.baseline_rate {
+            Some(baseline) if baseline != 0. => Some(self.rate() / baseline),
+            _ => None,
+        }
+    }
+
+    /// How many times an `Err` has been retried so far. Only used with `.progress_retry(…)`,
+    /// otherwise 0.
+    pub fn num_retries(&self) -> usize {
+        self.num_retries
+    }
+
+    /// How many items failed permanently (i.e. were still `Err` after all the retries) so far.
+    /// Only used with `.progress_retry(…)`, otherwise 0.
+    pub fn num_failed(&self) -> usize {
+        self.num_failed
+    }
+
+    /// Mean time between records (i.e. between items, for `.progress()`). None until at least
+    /// two items have been seen.
+    pub fn item_duration_mean(&self) -> Option<Duration> {
+        self.item_durations.mean().map(Duration::from_secs_f64)
+    }
+
+    /// Standard deviation of the time between records (i.e. between items, for `.progress()`).
+    /// A large value, compared to `.item_duration_mean()`, means throughput is spiky rather than
+    /// steady. None until at least two items have been seen.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..10).progress();
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.item_duration_stddev(), None);
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert!(state.item_duration_stddev().is_some());
+    /// ```
+    pub fn item_duration_stddev(&self) -> Option<Duration> {
+        self.item_durations.stddev().map(Duration::from_secs_f64)
+    }
+
+    /// The shortest time between records (i.e. between items, for `.progress()`) so far. None
+    /// until at least two items have been seen.
+    ///
+    /// With `.item_duration_max()`, `.item_duration_mean()` & `.item_duration_stddev()`, this
+    /// gives an overview of the latency of each item, e.g. in the final summary from
+    /// `.finish()`.
+    pub fn item_duration_min(&self) -> Option<Duration> {
+        self.item_durations
+            .min_max()
+            .map(|(min, _)| Duration::from_secs_f64(min))
+    }
+
+    /// The longest time between records (i.e. between items, for `.progress()`) so far. None
+    /// until at least two items have been seen.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let (summary, _) = (0..10).progress().last().unwrap();
+    /// assert!(summary.item_duration_min() <= summary.item_duration_max());
+    /// ```
+    pub fn item_duration_max(&self) -> Option<Duration> {
+        self.item_durations
+            .min_max()
+            .map(|(_, max)| Duration::from_secs_f64(max))
+    }
+
+    /// If the total size is know (i.e. we know the `.fraction()`), calculate the estimated time
+    /// to arrival, i.e. how long before this is finished.
+    ///
+    /// If phase rates were given with `.with_phase_rates(…)`, and the total is known, the
+    /// remaining items of each phase are assumed to go at that phase's rate (saturating at
+    /// `Duration::MAX` if that's too long for a `Duration`). Otherwise it depends
+    /// on the `EtaStrategy` (set with `.with_eta_strategy(…)`). By default, the current overall
+    /// rate is assumed for all the remaining items.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # let my_big_vec = vec![false; 100];
+    /// for (state, val) in my_big_vec.iter().progress() {
+    ///     state.do_every_n_sec(1., |state| {
+    ///         if let Some(eta) = state.eta() {
+    ///             println!("{}% done, finished in {} sec", state.percent().unwrap(), eta.as_secs());
+    ///         }
+    ///     });
+    /// }
+    /// ```
+    ///
+    /// It's None if the total isn't known
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let (state, _) = (0..).progress().next().unwrap();
+    /// assert_eq!(state.eta(), None);
+    /// ```
+    pub fn eta(&self) -> Option<Duration> {
+        if let Some(eta) = self.phase_eta() {
+            return Some(eta);
+        }
+        match self.eta_strategy {
+            EtaStrategy::Naive => {
+                // Nothing done yet means it's not known. Past the end (from an assumed fraction)
+                // means we're finished
+                let elapsed = self.duration_since_start();
+                self.fraction()
+                    .filter(|&f| f > 0.)
+                    .and_then(|f| {
+                        Duration::try_from_secs_f64(elapsed.as_secs_f64() / f.min(1.)).ok()
+                    })
+                    .map(|total| total.saturating_sub(elapsed))
+            }
+            EtaStrategy::Adaptive => self.eta_adaptive(),
+        }
+    }
+
+    /// The ETA from a blend of the rate since the start, and the `.recent_rate()`. Early on, it
+    /// leans towards the rate since the start, so it's stable, and as it gets closer to the end,
+    /// it leans towards the recent rate, so it responds to changes. The weight of the recent
+    /// rate is the `.fraction()` done.
+    ///
+    /// None if the fraction isn't known, or it's too soon to know the rate. If there's no recent
+    /// rate being recorded, it's the same as the naive `.eta()`.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # let my_big_vec = vec![false; 100];
+    /// for (state, val) in my_big_vec.iter().progress().with_rolling_average(10) {
+    ///     state.do_every_n_sec(1., |state| {
+    ///         println!("ETA: {:?}", state.eta_adaptive());
+    ///     });
+    /// }
+    /// ```
+    pub fn eta_adaptive(&self) -> Option<Duration> {
+        let fraction = self.fraction()?;
+        let rate = match self.recent_rate() {
+            Some(recent) => {
+                let weight = fraction.clamp(0., 1.);
+                (1. - weight) * self.rate() + weight * recent
+            }
+            None => self.rate(),
+        };
+        let done = self.num_used() as f64;
+        let remaining = match self.total() {
+            Some(total) => (total as f64 - done).max(0.),
+            None => done * (1. - fraction) / fraction,
+        };
+        if rate.is_finite() && rate > 0. {
+            Duration::try_from_secs_f64(remaining / rate).ok()
+        } else {
+            None
+        }
+    }
+
+    /// The ETA from the phase rates, if there are any, and the total is known. `Duration::MAX`
+    /// if it's too long for a `Duration`, rather than falling back to a much shorter ETA.
+    fn phase_eta(&self) -> Option<Duration> {
+        let rates = self.phase_rates.as_ref()?;
+        let total = self.total()? as f64;
+        let done = self.num_used() as f64;
+        let phase_size = total / rates.len() as f64;
+        let mut secs = 0.;
+        for (i, &rate) in rates.iter().enumerate() {
+            let start = phase_size * i as f64;
+            let end = phase_size * (i + 1) as f64;
+            let remaining = end - done.max(start);
+            if remaining > 0. {
+                if rate <= 0. {
+                    return None;
+                }
+                secs += remaining / rate;
+            }
+        }
+        Some(Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX))
+    }
+
+    /// If the total size is know (i.e. we know the `.fraction()`), calculate how long, in total,
+    /// this iterator would run for. i.e. how long it's run plus how much longer it has left
+    /// (saturating at `Duration::MAX`)
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..10).progress();
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(
+    ///     state.estimated_total_time(),
+    ///     Some(state.duration_since_start() + state.eta().unwrap())
+    /// );
+    /// ```
+    pub fn estimated_total_time(&self) -> Option<Duration> {
+        self.eta()
+            .map(|eta| self.duration_since_start().saturating_add(eta))
+    }
+
+    /// The `Instant` this is estimated to finish at, i.e. when this record was generated plus
+    /// the `.eta()`. None if that's too far in the future for an `Instant`.
+    pub fn eta_instant(&self) -> Option<Instant> {
+        self.eta().and_then(|eta| self.tm.checked_add(eta))
+    }
+
+    /// The wall-clock time this is estimated to finish at, i.e. `.eta_instant()` as a
+    /// `SystemTime`. Use `format_time_of_day` to show it. How long that is from now is measured
+    /// by the recorder's clock (see `OptionalProgressRecorderIter::with_clock`). None if the
+    /// ETA isn't known, or it's too far in the future for a `SystemTime`.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// use std::time::SystemTime;
+    /// let (state, _) = (0..10).progress().next().unwrap();
+    /// assert!(state.estimated_completion_time().unwrap() >= SystemTime::now());
+    /// ```
+    pub fn estimated_completion_time(&self) -> Option<SystemTime> {
+        let eta_instant = self.eta_instant()?;
+        let (now, system_now) = (self.clock.now(), SystemTime::now());
+        match eta_instant.checked_duration_since(now) {
+            Some(until) => system_now.checked_add(until),
+            None => system_now.checked_sub(now.duration_since(eta_instant)),
+        }
+    }
+
+    /// Whether this is estimated to finish before the deadline set with `.with_deadline_at(…)`.
+    /// None if there's no deadline, or the ETA isn't known.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// use std::time::{Duration, SystemTime};
+    /// let deadline = SystemTime::now() + Duration::from_secs(3600);
+    /// # let my_big_vec = vec![false; 100];
+    /// for (state, val) in my_big_vec.iter().progress().with_deadline_at(deadline) {
+    ///     if state.on_track() == Some(false) {
+    ///         // We won't make it. Shed some work?
+    ///     }
+    /// }
+    /// ```
+    pub fn on_track(&self) -> Option<bool> {
+        let deadline = self.deadline?;
+        self.eta()?;
+        // The ETA is known, so no completion time means it's too far in the future
+        Some(
+            self.estimated_completion_time()
+                .is_some_and(|finish| finish <= deadline),
+        )
+    }
+}
+
+pub struct OptionalProgressRecorderIter<I> {
+    /// The iterator that we are iteating on
+    iter: I,
+
+    /// How many items have been seen
+    count: usize,
+
+    /// How much has been done, in the units of `Counting` (or `Accumulating`). Same as `count`
+    /// if not counting like that.
+    done: usize,
+
+    /// If set, how much the items count towards `done` (rather than 1 each), added by the
+    /// `Counting` iterator as items are yielded
+    counted: Option<Arc<AtomicUsize>>,
+
+    generate_every_count: usize,
+
+    /// If set, only generate records when the fraction crosses a multiple of this, and the
+    /// last multiple crossed
+    milestones: Option<(f64, usize)>,
+
+    /// When did we start iterating
+    started_iterating: Instant,
+
+    /// Where the time comes from
+    clock: Arc<dyn Clock>,
+
+    /// If set, only read the clock every so many items
+    time_sampling: Option<TimeSampling>,
+
+    /// Added to the time since `started_iterating`, when resuming with `.reset_to(…)`
+    elapsed_offset: Duration,
+
+    previous_record_tm: Option<Instant>,
+
+    /// Set with `.pause_handle()`, and how long it had been paused for when we started, and at
+    /// the previous record
+    pause: Option<PauseHandle>,
+    paused_at_start: Duration,
+    paused_at_previous: Duration,
+
+    /// How many `Err`s have been retried, and how many items failed, with `.progress_retry(…)`
+    num_retries: usize,
+    num_failed: usize,
+
+    /// Set with `.handle()`, updated with every record
+    handle: Option<ProgressHandle>,
+
+    /// For `ProgressRecord::should_print_every_n_sec`, shared with the records
+    last_printed: Arc<AtomicU64>,
+
+    /// Set with `.nested()`, for inner iterators to report to
+    nested: Option<NestedProgress>,
+
+    /// Set with `.with_parent(…)`, if this is an inner iterator
+    parent: Option<NestedProgress>,
+
+    rolling_average: Option<(usize, Vec<f64>)>,
+    exp_average: Option<(f64, Option<Duration>)>,
+    recent_window: Option<RecentWindow>,
+    item_durations: RunningStats,
+
+    /// When the previous record with a recent rate was generated, and that rate
+    previous_recent_rate: Option<(Instant, f64)>,
+
+    /// The lowest & highest recent rate so far
+    recent_rate_range: Option<(f64, f64)>,
+
+    /// If set, timestamps for `.rate_over(…)`
+    trailing_rates: Option<TrailingRates>,
+
+    assumed_size: Option<usize>,
+
+    /// The underlying iterator's upper bound (if any) when we started, plus `done` then
+    initial_total: Option<usize>,
+
+    unicode: bool,
+    interactive: bool,
+    output_mode: OutputMode,
+    output: OutputSink,
+
+    /// Whether `output` was set, so `.progress_bar()` draws there, rather than on stderr
+    output_set: bool,
+
+    baseline_rate: Option<f64>,
+    deadline: Option<SystemTime>,
+    phase_rates: Option<Arc<[f64]>>,
+    eta_strategy: EtaStrategy,
+
+    /// The style for `.progress_bar()`, if not the default
+    bar_style: Option<BarStyle>,
+
+    /// Samples of `(duration_since_start, count)`, taken every time a record is generated
+    history: Option<History>,
+
+    /// Stop generating records after this many
+    record_limit: Option<usize>,
+
+    /// How many records have been generated
+    num_records: usize,
+
+    /// If set, the format of `ProgressRecord::message`
+    template: Option<Template>,
+
+    /// If set, before every message
+    label: Option<Arc<str>>,
+
+    /// If set, a callback for `.do_every(…)`
+    periodic: Option<trigger::Periodic>,
+
+    /// If set, write a CSV row for every n records
+    csv_log: Option<CsvLog>,
+
+    /// If set, write a JSON line to this at most every duration
+    json_lines: Option<(OutputSink, Duration)>,
+
+    /// If set, send `ProgressEvent`s
+    events: Option<events::EventSender>,
+
+    /// If set, save a `Checkpoint` at most every so often
+    checkpoints: Option<checkpoint::CheckpointWriter>,
+
+    /// If set, update the terminal title every so many records
+    terminal_title: Option<TerminalTitle>,
+
+    /// If set, calls a callback when the underlying iterator takes too long for an item
+    watchdog: Option<watchdog::Watchdog>,
+
+    /// If set, stop when this is cancelled, and whether the summary has been printed
+    cancel: Option<(CancelToken, bool)>,
+
+    /// If set, how often to write a heartbeat line, and the thread writing them, once started
+    heartbeat: Option<(Duration, Option<heartbeat::Heartbeat>)>,
+
+    /// If set, every time between records is added to this
+    #[cfg(feature = "latency-histogram")]
+    latency_histogram: Option<LatencyHistogram>,
+
+    /// If set, writes a line when sent SIGUSR1 or SIGINFO
+    #[cfg(all(unix, feature = "signals"))]
+    signal_printer: Option<signals::SignalPrinter>,
+
+    /// If set, update OpenTelemetry metrics every n records
+    #[cfg(feature = "opentelemetry")]
+    otel: Option<otel::OtelExporter>,
+
+    /// If set, update Prometheus metrics every n records
+    #[cfg(feature = "prometheus")]
+    prometheus: Option<prom::PrometheusExporter>,
+
+    /// If set, log progress with the `log` crate
+    #[cfg(feature = "log")]
+    logger: Option<logging::ProgressLogger>,
+
+    /// If set, report progress with the `tracing` crate
+    #[cfg(feature = "tracing")]
+    tracer: Option<trace::ProgressTracer>,
+
+    _fake_now: Option<Instant>,
+}
+
+/// A store of `(duration_since_start, count)` samples. When it has a limit, reservoir sampling is
+/// used to keep a representative subset of the whole run, rather than only the most recent
+/// samples.
+struct History {
+    limit: Option<usize>,
+
+    /// The samples kept, each with when it was offered (to put them back in order), in no
+    /// particular order once the limit is reached
+    samples: Vec<(usize, (Duration, usize))>,
+
+    /// How many samples have been offered
+    seen: usize,
+
+    /// State for a xorshift PRNG, used to pick which samples to keep
+    rng: u64,
+}
+
+/// Appends `elapsed_secs,count,rate,percent` rows to a CSV file
+struct CsvLog {
+    writer: BufWriter<File>,
+    every: CsvInterval,
+}
+
+/// How often a CSV row is written
+enum CsvInterval {
+    /// Every n records generated
+    Records(usize),
+
+    /// At most every duration
+    Time(Duration),
+}
+
+impl CsvLog {
+    fn open(path: impl AsRef<std::path::Path>, every: CsvInterval) -> io::Result<CsvLog> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        if is_empty {
+            writeln!(writer, "elapsed_secs,count,rate,percent")?;
+        }
+        let every = match every {
+            CsvInterval::Records(n) => CsvInterval::Records(n.max(1)),
+            every => every,
+        };
+        Ok(CsvLog { writer, every })
+    }
+
+    /// Whether a row should be written for `record`, the `num_records`th record
+    fn is_due(&self, num_records: usize, record: &ProgressRecord) -> bool {
+        match self.every {
+            CsvInterval::Records(n) => num_records % n == 0,
+            CsvInterval::Time(d) => record.should_do_every_n_sec(d.as_secs_f32()),
+        }
+    }
+
+    fn write_row(&mut self, record: &ProgressRecord) -> io::Result<()> {
+        write!(
+            self.writer,
+            "{:.3},{},{:.3},",
+            record.duration_since_start().as_secs_f64(),
+            record.num_done(),
+            record.rate()
+        )?;
+        if let Some(percent) = record.percent() {
+            write!(self.writer, "{:.2}", percent)?;
+        }
+        writeln!(self.writer)?;
+        self.writer.flush()
+    }
+}
+
+impl History {
+    fn new(limit: Option<usize>) -> History {
+        History {
+            limit,
+            samples: Vec::new(),
+            seen: 0,
+            rng: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    /// Reservoir sampling: once full, the `n`th sample replaces a random one with probability
+    /// `limit / n`, so every sample so far is equally likely to be kept.
+    fn add(&mut self, sample: (Duration, usize)) {
+        self.seen += 1;
+        match self.limit {
+            Some(limit) if self.samples.len() >= limit => {
+                let idx = (self.next_random() % (self.seen as u64)) as usize;
+                if idx < limit {
+                    self.samples[idx] = (self.seen, sample);
+                }
+            }
+            _ => self.samples.push((self.seen, sample)),
+        }
+    }
+
+    /// The samples kept, in the order they were added
+    fn in_order(&self) -> Vec<(Duration, usize)> {
+        let mut samples = self.samples.clone();
+        samples.sort_by_key(|&(seen, _)| seen);
+        samples.into_iter().map(|(_, sample)| sample).collect()
+    }
+}
+
+/// Wraps an iterator and keeps track of state used for `ProgressRecord`'s
+pub struct ProgressRecorderIter<I>(OptionalProgressRecorderIter<I>);
+
+impl<I> AsRef<OptionalProgressRecorderIter<I>> for ProgressRecorderIter<I> {
+    fn as_ref(&self) -> &OptionalProgressRecorderIter<I> {
+        &self.0
+    }
+}
+
+impl<I> AsMut<OptionalProgressRecorderIter<I>> for ProgressRecorderIter<I> {
+    fn as_mut(&mut self) -> &mut OptionalProgressRecorderIter<I> {
+        &mut self.0
+    }
+}
+
+impl<I: Iterator> Deref for ProgressRecorderIter<I> {
+    type Target = OptionalProgressRecorderIter<I>;
+    fn deref(&self) -> &OptionalProgressRecorderIter<I> {
+        &self.0
+    }
+}
+
+impl<I: Iterator> DerefMut for ProgressRecorderIter<I> {
+    fn deref_mut(&mut self) -> &mut OptionalProgressRecorderIter<I> {
+        &mut self.0
+    }
+}
+
+impl<I: Iterator> ProgressRecorderIter<I> {
+    /// Create a new `ProgressRecorderIter` from another iterator.
+    pub fn new(iter: I) -> ProgressRecorderIter<I> {
+        ProgressRecorderIter(OptionalProgressRecorderIter::new(iter, 1))
+    }
+
+    pub fn assume_size(self, size: impl Into<Option<usize>>) -> Self {
+        let mut new = self;
+        new.0.assumed_size = size.into();
+        new
+    }
+
+    /// Keep a history of `(duration_since_start, count)` samples. See
+    /// `OptionalProgressRecorderIter::with_history`.
+    pub fn with_history(self, limit: impl Into<Option<usize>>) -> Self {
+        ProgressRecorderIter(self.0.with_history(limit))
+    }
+
+    /// Set a deadline. See `OptionalProgressRecorderIter::with_deadline_at`.
+    pub fn with_deadline_at(self, when: impl Into<Option<SystemTime>>) -> Self {
+        ProgressRecorderIter(self.0.with_deadline_at(when))
+    }
+
+    /// Store the last `ProgressRecord` in `slot` when this is finished with, i.e. when it's
+    /// exhausted or dropped (e.g. after a `break`), so it can be used for a summary after a
+    /// `for` loop. `slot` is left alone if there were no items.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut summary = None;
+    /// for (_state, num) in (0..100).progress().finish_into(&mut summary) {
+    ///     if num == 9 {
+    ///         break;
+    ///     }
+    /// }
+    /// assert_eq!(summary.unwrap().num_done(), 10);
+    /// ```
+    pub fn finish_into(self, slot: &mut Option<ProgressRecord>) -> FinishInto<'_, I> {
+        FinishInto {
+            inner: self,
+            slot,
+            last: None,
+        }
+    }
+
+    /// How the ETA is calculated. See `OptionalProgressRecorderIter::with_eta_strategy`.
+    pub fn with_eta_strategy(self, strategy: EtaStrategy) -> Self {
+        ProgressRecorderIter(self.0.with_eta_strategy(strategy))
+    }
+
+    /// Print a summary line (see `.finish()`) when this is dropped, even if it wasn't iterated to
+    /// the end (e.g. after a `break`, or an error returned with `?`), so runs which stopped early
+    /// still report how far they got. The line starts with `"Done"` if it was exhausted, or
+    /// `"Stopped"` if not. When cancelled with a `CancelToken`, there's only the one
+    /// `"Cancelled"` line.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// fn first_big(nums: &[u32]) -> Option<u32> {
+    ///     for (_state, &num) in nums.iter().progress().report_on_drop() {
+    ///         if num > 100 {
+    ///             return Some(num); // prints "Stopped: 3 items in …"
+    ///         }
+    ///     }
+    ///     None
+    /// }
+    /// assert_eq!(first_big(&[1, 10, 1000, 5]), Some(1000));
+    /// ```
+    pub fn report_on_drop(self) -> ReportOnDrop<I> {
+        ReportOnDrop {
+            inner: self,
+            exhausted: false,
+        }
+    }
+
+    /// Call `f` with each `ProgressRecord`, and yield the items unchanged. See
+    /// `ProgressableIter::inspect_progress`.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let evens = (0..)
+    ///     .take_while(|&num| num < 100)
+    ///     .progress()
+    ///     .assume_size(100)
+    ///     .inspect_progress(|state| println!("{:?}% done", state.percent()))
+    ///     .filter(|num| num % 2 == 0)
+    ///     .count();
+    /// assert_eq!(evens, 50);
+    /// ```
+    pub fn inspect_progress<F>(self, f: F) -> InspectProgress<I, F>
+    where
+        F: FnMut(&ProgressRecord),
+    {
+        InspectProgress { inner: self, f }
+    }
+
+    /// Draw a progress bar on stderr (or the `OutputSink`, if set), and yield the items
+    /// unchanged. See `ProgressableIter::progress_bar`.
+    ///
+    /// ```
+    /// # use iter_progress::{OutputMode, ProgressableIter};
+    /// let lines = (0..)
+    ///     .take_while(|&num| num < 100)
+    ///     .progress()
+    ///     .assume_size(100)
+    ///     .with_output_mode(OutputMode::Plain)
+    ///     .progress_bar();
+    /// for line in lines {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn progress_bar(self) -> ProgressBar<I> {
+        let mut res = self;
+        let style = res.0.bar_style.take().unwrap_or_default();
+        let output = if res.0.output_set {
+            res.0.output.clone()
+        } else {
+            OutputSink::Stderr
+        };
+        // It's drawn there, so that's what matters
+        let interactive = res.0.output_mode.is_interactive_for(output.is_terminal());
+        ProgressBar {
+            interactive,
+            inner: res,
+            output,
+            last: None,
+            drawn: false,
+            style,
+        }
+    }
+
+    /// Set historical rates for each phase. See
+    /// `OptionalProgressRecorderIter::with_phase_rates`.
+    pub fn with_phase_rates(self, rates: impl Into<Option<Vec<f64>>>) -> Self {
+        ProgressRecorderIter(self.0.with_phase_rates(rates))
+    }
+
+    /// Where output is written. See `OptionalProgressRecorderIter::with_output_sink`.
+    pub fn with_output_sink(self, sink: OutputSink) -> Self {
+        ProgressRecorderIter(self.0.with_output_sink(sink))
+    }
+
+    /// Force output to be interactive or plain. See
+    /// `OptionalProgressRecorderIter::with_output_mode`.
+    pub fn with_output_mode(self, mode: OutputMode) -> Self {
+        ProgressRecorderIter(self.0.with_output_mode(mode))
+    }
+
+    /// Whether Unicode can be used for output. See
+    /// `OptionalProgressRecorderIter::with_unicode`.
+    pub fn with_unicode(self, unicode: bool) -> Self {
+        ProgressRecorderIter(self.0.with_unicode(unicode))
+    }
+
+    /// Set the format of the progress message. See
+    /// `OptionalProgressRecorderIter::with_template`.
+    pub fn with_template(self, template: impl Into<Template>) -> Self {
+        ProgressRecorderIter(self.0.with_template(template))
+    }
+
+    /// Make this the inner iterator of `parent`'s current item. See
+    /// `OptionalProgressRecorderIter::with_parent`.
+    pub fn with_parent(self, parent: &NestedProgress) -> Self {
+        ProgressRecorderIter(self.0.with_parent(parent))
+    }
+
+    /// Label the progress. See `OptionalProgressRecorderIter::with_label`.
+    pub fn with_label(self, label: impl Into<Option<String>>) -> Self {
+        ProgressRecorderIter(self.0.with_label(label))
+    }
+
+    /// Set a baseline rate to compare against. See
+    /// `OptionalProgressRecorderIter::with_baseline_rate`.
+    pub fn with_baseline_rate(self, baseline: impl Into<Option<f64>>) -> Self {
+        ProgressRecorderIter(self.0.with_baseline_rate(baseline))
+    }
+
+    /// Set the size of the rolling average window. See
+    /// `OptionalProgressRecorderIter::with_rolling_average`.
+    pub fn with_rolling_average(self, size: impl Into<Option<usize>>) -> Self {
+        ProgressRecorderIter(self.0.with_rolling_average(size))
+    }
+
+    /// Set the exponential average rate. See `OptionalProgressRecorderIter::with_exp_average`.
+    pub fn with_exp_average(self, rate: impl Into<Option<f64>>) -> Self {
+        ProgressRecorderIter(self.0.with_exp_average(rate))
+    }
+
+    /// Set the window for the recent rate. See
+    /// `OptionalProgressRecorderIter::recent_window_hybrid`.
+    pub fn recent_window_hybrid(self, n: usize, d: Duration, policy: WindowPolicy) -> Self {
+        ProgressRecorderIter(self.0.recent_window_hybrid(n, d, policy))
+    }
+
+    /// Keep timestamps for `.rate_over(…)`. See
+    /// `OptionalProgressRecorderIter::track_rates_over`.
+    pub fn track_rates_over(self, max: Duration) -> Self {
+        ProgressRecorderIter(self.0.track_rates_over(max))
+    }
+
+    /// The rate over the trailing `window`. See `OptionalProgressRecorderIter::rate_over`.
+    pub fn rate_over(&self, window: Duration) -> Option<f64> {
+        self.0.rate_over(window)
+    }
+
+    /// Set the window for the recent rate to the last `n` items. See
+    /// `OptionalProgressRecorderIter::recent_window_items`.
+    pub fn recent_window_items(self, n: usize) -> Self {
+        ProgressRecorderIter(self.0.recent_window_items(n))
+    }
+
+    /// Set the window for the recent rate to the last `d` duration. See
+    /// `OptionalProgressRecorderIter::recent_window_duration`.
+    pub fn recent_window_duration(self, d: Duration) -> Self {
+        ProgressRecorderIter(self.0.recent_window_duration(d))
+    }
+
+    /// Log progress to a CSV file. See `OptionalProgressRecorderIter::log_csv`.
+    pub fn log_csv(self, path: impl AsRef<std::path::Path>, every: usize) -> io::Result<Self> {
+        Ok(ProgressRecorderIter(self.0.log_csv(path, every)?))
+    }
+
+    /// Log progress to a CSV file at most every `every`. See
+    /// `OptionalProgressRecorderIter::log_csv_every`.
+    pub fn log_csv_every(
+        self,
+        path: impl AsRef<std::path::Path>,
+        every: Duration,
+    ) -> io::Result<Self> {
+        Ok(ProgressRecorderIter(self.0.log_csv_every(path, every)?))
+    }
+
+    /// Write the progress as JSON lines. See `OptionalProgressRecorderIter::emit_json_lines`.
+    pub fn emit_json_lines(self, sink: OutputSink, every: Duration) -> Self {
+        ProgressRecorderIter(self.0.emit_json_lines(sink, every))
+    }
+
+    /// Only read the clock every `k` items. See `OptionalProgressRecorderIter::sample_time_every`.
+    pub fn sample_time_every(self, k: impl Into<Option<usize>>) -> Self {
+        ProgressRecorderIter(self.0.sample_time_every(k))
+    }
+
+    /// Where the time comes from. See `OptionalProgressRecorderIter::with_clock`.
+    pub fn with_clock(self, clock: impl Clock + 'static) -> Self {
+        ProgressRecorderIter(self.0.with_clock(clock))
+    }
+
+    /// Save `Checkpoint`s. See `OptionalProgressRecorderIter::save_checkpoints`.
+    pub fn save_checkpoints(self, path: impl Into<PathBuf>, every: Duration) -> Self {
+        ProgressRecorderIter(self.0.save_checkpoints(path, every))
+    }
+
+    /// Carry on from a `Checkpoint`. See `OptionalProgressRecorderIter::resume_from`.
+    pub fn resume_from(self, checkpoint: impl Into<Option<Checkpoint>>) -> Self {
+        ProgressRecorderIter(self.0.resume_from(checkpoint))
+    }
+
+    /// Send `ProgressEvent`s over a channel. See `OptionalProgressRecorderIter::send_events`.
+    pub fn send_events(self, sender: Sender<ProgressEvent>, every: Duration) -> Self {
+        ProgressRecorderIter(self.0.send_events(sender, every))
+    }
+
+    /// Show the progress in the terminal title. See
+    /// `OptionalProgressRecorderIter::set_terminal_title`.
+    pub fn set_terminal_title(self, every: usize) -> Self {
+        ProgressRecorderIter(self.0.set_terminal_title(every))
+    }
+
+    /// Call `f` whenever `trigger` fires. See `OptionalProgressRecorderIter::do_every`.
+    pub fn do_every<F>(self, trigger: Trigger, f: F) -> Self
+    where
+        F: FnMut(&ProgressRecord) + Send + 'static,
+    {
+        ProgressRecorderIter(self.0.do_every(trigger, f))
+    }
+
+    /// Call `f` at most once every `every`. See
+    /// `OptionalProgressRecorderIter::do_every_duration`.
+    pub fn do_every_duration<F>(self, every: Duration, f: F) -> Self
+    where
+        F: FnMut(&ProgressRecord) + Send + 'static,
+    {
+        ProgressRecorderIter(self.0.do_every_duration(every, f))
+    }
+
+    /// Detect when an item takes too long. See `OptionalProgressRecorderIter::on_item_timeout`.
+    pub fn on_item_timeout<F>(self, timeout: Duration, callback: F) -> Self
+    where
+        F: FnMut(usize, Duration) + Send + 'static,
+    {
+        ProgressRecorderIter(self.0.on_item_timeout(timeout, callback))
+    }
+
+    /// Keep a histogram of the time between items. See
+    /// `OptionalProgressRecorderIter::with_latency_histogram`.
+    #[cfg(feature = "latency-histogram")]
+    pub fn with_latency_histogram(self) -> Self {
+        ProgressRecorderIter(self.0.with_latency_histogram())
+    }
+
+    /// The histogram of the time between items. See
+    /// `OptionalProgressRecorderIter::latency_histogram`.
+    #[cfg(feature = "latency-histogram")]
+    pub fn latency_histogram(&self) -> Option<&LatencyHistogram> {
+        self.0.latency_histogram()
+    }
+
+    /// Print the progress when sent a signal. See
+    /// `OptionalProgressRecorderIter::print_on_signal`.
+    #[cfg(all(unix, feature = "signals"))]
+    pub fn print_on_signal(self, sink: OutputSink) -> io::Result<Self> {
+        Ok(ProgressRecorderIter(self.0.print_on_signal(sink)?))
+    }
+
+    /// Stop when `token` is cancelled. See `OptionalProgressRecorderIter::with_cancel_token`.
+    pub fn with_cancel_token(self, token: impl Into<Option<CancelToken>>) -> Self {
+        ProgressRecorderIter(self.0.with_cancel_token(token))
+    }
+
+    /// Write the progress on a clock tick. See `OptionalProgressRecorderIter::heartbeat_every`.
+    pub fn heartbeat_every(self, every: impl Into<Option<Duration>>) -> Self {
+        ProgressRecorderIter(self.0.heartbeat_every(every))
+    }
+}
+
+/// Set the terminal's title, with the OSC escape sequence. Errors are ignored.
+fn write_terminal_title(title: &str) {
+    let mut stdout = io::stdout().lock();
+    let _ = write!(stdout, "\x1b]0;{}\x07", title);
+    let _ = stdout.flush();
+}
+
+/// Shows the progress in the terminal's title, for `.set_terminal_title(…)`. The title is
+/// cleared when this is dropped, if it was set, so it isn't left behind when the iterator isn't
+/// run to the end.
+struct TerminalTitle {
+    /// Update it every this many records
+    every: usize,
+
+    /// Whether the title has been set (and not cleared since)
+    set: bool,
+}
+
+impl TerminalTitle {
+    fn update(&mut self, num_records: usize, record: &ProgressRecord) {
+        if num_records % self.every == 0 {
+            self.set = true;
+            write_terminal_title(&record.terminal_title());
+        }
+    }
+
+    fn clear(&mut self) {
+        if self.set {
+            self.set = false;
+            write_terminal_title("");
+        }
+    }
+}
+
+impl Drop for TerminalTitle {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Shortest gap, in seconds, between redraws of `.progress_bar()`
+const BAR_REFRESH_SECS: f32 = 0.1;
+
+/// Shortest gap, in seconds, between lines of progress output when output isn't interactive.
+const PLAIN_OUTPUT_MIN_SECS: f32 = 10.;
+
+/// Where progress output (e.g. from `ProgressRecord::print_every_n_sec`) is written.
+///
+/// ```
+/// # use iter_progress::{OutputSink, ProgressableIter};
+/// # fn main() -> std::io::Result<()> {
+/// # let path = std::env::temp_dir().join("iter-progress-doc-output-sink.log");
+/// let log_file = std::fs::File::create(&path)?;
+/// for (state, _) in (0..100).progress().with_output_sink(OutputSink::writer(log_file)) {
+///     state.print_every_n_items(10, format!("{} done\n", state.num_done()));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub enum OutputSink {
+    /// Standard output
+    #[default]
+    Stdout,
+
+    /// Standard error, e.g. for programs whose stdout is data
+    Stderr,
+
+    /// Anything else, e.g. a file. Never interactive. Create with `OutputSink::writer`.
+    Writer(Arc<Mutex<dyn Write + Send>>),
+}
+
+impl std::fmt::Debug for OutputSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OutputSink::Stdout => write!(f, "Stdout"),
+            OutputSink::Stderr => write!(f, "Stderr"),
+            OutputSink::Writer(_) => write!(f, "Writer(…)"),
+        }
+    }
+}
+
+/// Writers are equal if they're the same writer
+impl PartialEq for OutputSink {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (OutputSink::Stdout, OutputSink::Stdout) | (OutputSink::Stderr, OutputSink::Stderr) => {
+                true
+            }
+            (OutputSink::Writer(a), OutputSink::Writer(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl OutputSink {
+    /// Write output to `writer`
+    pub fn writer(writer: impl Write + Send + 'static) -> OutputSink {
+        OutputSink::Writer(Arc::new(Mutex::new(writer)))
+    }
+
+    /// Whether this is a terminal
+    fn is_terminal(&self) -> bool {
+        match self {
+            OutputSink::Stdout => io::stdout().is_terminal(),
+            OutputSink::Stderr => io::stderr().is_terminal(),
+            OutputSink::Writer(_) => false,
+        }
+    }
+
+    /// Write & flush. Errors are ignored, since progress output shouldn't stop the work.
+    fn write(&self, args: std::fmt::Arguments) {
+        fn write_to(writer: &mut dyn Write, args: std::fmt::Arguments) {
+            let _ = writer.write_fmt(args);
+            let _ = writer.flush();
+        }
+        match self {
+            OutputSink::Stdout => write_to(&mut io::stdout().lock(), args),
+            OutputSink::Stderr => write_to(&mut io::stderr().lock(), args),
+            OutputSink::Writer(writer) => {
+                let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+                write_to(&mut *writer, args)
+            }
+        }
+    }
+}
+
+/// How progress output is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    /// Use `Interactive` or `Plain`, depending on `OutputMode::detect()`
+    #[default]
+    Auto,
+
+    /// Redraw the current line in place, e.g. for a progress bar in a terminal
+    Interactive,
+
+    /// Print plain, newline terminated, lines, at a slow cadence, e.g. for log files
+    Plain,
+}
+
+impl OutputMode {
+    /// Detect whether output should be `Interactive` or `Plain`.
+    ///
+    /// It's `Plain` if running in CI (i.e. if the `CI` or `GITHUB_ACTIONS` environment variables
+    /// are set, to anything but `false` or `0`), or if stdout isn't a terminal. Otherwise it's
+    /// `Interactive`.
+    pub fn detect() -> OutputMode {
+        OutputMode::detect_for(io::stdout().is_terminal())
+    }
+
+    /// Like `OutputMode::detect()`, but for output which `is_terminal` or not, rather than stdout
+    fn detect_for(is_terminal: bool) -> OutputMode {
+        let is_set = |var| {
+            std::env::var_os(var).is_some_and(|val| !val.is_empty() && val != "false" && val != "0")
+        };
+        if is_set("CI") || is_set("GITHUB_ACTIONS") || !is_terminal {
+            OutputMode::Plain
+        } else {
+            OutputMode::Interactive
+        }
+    }
+
+    /// Whether this mode is interactive, detecting it if this is `Auto`
+    fn is_interactive(self) -> bool {
+        self.is_interactive_for(io::stdout().is_terminal())
+    }
+
+    /// Whether this mode is interactive for output which `is_terminal` or not, detecting it if
+    /// this is `Auto`
+    fn is_interactive_for(self, is_terminal: bool) -> bool {
+        match self {
+            OutputMode::Auto => OutputMode::detect_for(is_terminal) == OutputMode::Interactive,
+            OutputMode::Interactive => true,
+            OutputMode::Plain => false,
+        }
+    }
+}
+
+/// The settings for a `ProgressRecorderIter` (the total, windows, output sink & mode, the
+/// bar's units, how often the clock is read, the ETA), in one place. The `Default` is the same
+/// as plain `.progress()`. Callbacks, and other outputs (e.g. `.log_csv(…)`), are set on the
+/// recorder.
+///
+/// ```
+/// # use iter_progress::{ProgressableIter, ProgressConfig};
+/// let config = ProgressConfig::default()
+///     .assume_size(10)
+///     .with_rolling_average(5)
+///     .with_exp_average(0.01);
+/// let mut progressor = (0..).progress_with(config);
+/// let (state, _) = progressor.next().unwrap();
+/// assert_eq!(state.fraction(), Some(0.1));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressConfig {
+    assumed_size: Option<usize>,
+    sample_time_every: Option<usize>,
+    rolling_average: Option<usize>,
+    exp_average: Option<f64>,
+    recent_window: Option<(usize, Duration, WindowPolicy)>,
+    trailing_rates: Option<Duration>,
+    unicode: bool,
+    template: Option<Template>,
+    label: Option<String>,
+    baseline_rate: Option<f64>,
+    deadline: Option<SystemTime>,
+    output_mode: OutputMode,
+    output: Option<OutputSink>,
+    bar_style: Option<BarStyle>,
+    eta_strategy: EtaStrategy,
+}
+
+impl Default for ProgressConfig {
+    fn default() -> Self {
+        ProgressConfig {
+            assumed_size: None,
+            sample_time_every: None,
+            rolling_average: None,
+            exp_average: None,
+            recent_window: None,
+            trailing_rates: None,
+            unicode: true,
+            template: None,
+            label: None,
+            baseline_rate: None,
+            deadline: None,
+            output_mode: OutputMode::Auto,
+            output: None,
+            bar_style: None,
+            eta_strategy: EtaStrategy::Naive,
+        }
+    }
+}
+
+impl ProgressConfig {
+    /// Assume the iterator has this many items. See `OptionalProgressRecorderIter::assume_size`.
+    pub fn assume_size(self, size: impl Into<Option<usize>>) -> Self {
+        let mut res = self;
+        res.assumed_size = size.into();
+        res
+    }
+
+    /// Only read the clock every `k` items. See
+    /// `OptionalProgressRecorderIter::sample_time_every`.
+    pub fn sample_time_every(self, k: impl Into<Option<usize>>) -> Self {
+        let mut res = self;
+        res.sample_time_every = k.into();
+        res
+    }
+
+    /// Size of the rolling average window. See
+    /// `OptionalProgressRecorderIter::with_rolling_average`.
+    pub fn with_rolling_average(self, size: impl Into<Option<usize>>) -> Self {
+        let mut res = self;
+        res.rolling_average = size.into();
+        res
+    }
+
+    /// Exponential average rate. See `OptionalProgressRecorderIter::with_exp_average`.
+    pub fn with_exp_average(self, rate: impl Into<Option<f64>>) -> Self {
+        let mut res = self;
+        res.exp_average = rate.into();
+        res
+    }
+
+    /// Window for the recent rate. See `OptionalProgressRecorderIter::recent_window_hybrid`.
+    pub fn recent_window_hybrid(self, n: usize, d: Duration, policy: WindowPolicy) -> Self {
+        let mut res = self;
+        res.recent_window = Some((n, d, policy));
+        res
+    }
+
+    /// Window of items for the recent rate. See
+    /// `OptionalProgressRecorderIter::recent_window_items`.
+    pub fn recent_window_items(self, n: usize) -> Self {
+        self.recent_window_hybrid(n, Duration::MAX, WindowPolicy::Smaller)
+    }
+
+    /// Window of time for the recent rate. See
+    /// `OptionalProgressRecorderIter::recent_window_duration`.
+    pub fn recent_window_duration(self, d: Duration) -> Self {
+        self.recent_window_hybrid(usize::MAX, d, WindowPolicy::Smaller)
+    }
+
+    /// Longest window for `.rate_over(…)`. See `OptionalProgressRecorderIter::track_rates_over`.
+    pub fn track_rates_over(self, max: Duration) -> Self {
+        let mut res = self;
+        res.trailing_rates = Some(max);
+        res
+    }
+
+    /// Whether Unicode can be used for output. See `OptionalProgressRecorderIter::with_unicode`.
+    pub fn with_unicode(self, unicode: bool) -> Self {
+        let mut res = self;
+        res.unicode = unicode;
+        res
+    }
+
+    /// Format of the progress message. See `OptionalProgressRecorderIter::with_template`.
+    pub fn with_template(self, template: impl Into<Template>) -> Self {
+        let mut res = self;
+        res.template = Some(template.into());
+        res
+    }
+
+    /// Label for the messages. See `OptionalProgressRecorderIter::with_label`.
+    pub fn with_label(self, label: impl Into<Option<String>>) -> Self {
+        let mut res = self;
+        res.label = label.into();
+        res
+    }
+
+    /// Rate to compare against. See `OptionalProgressRecorderIter::with_baseline_rate`.
+    pub fn with_baseline_rate(self, baseline: impl Into<Option<f64>>) -> Self {
+        let mut res = self;
+        res.baseline_rate = baseline.into();
+        res
+    }
+
+    /// Deadline to finish by. See `OptionalProgressRecorderIter::with_deadline_at`.
+    pub fn with_deadline_at(self, when: impl Into<Option<SystemTime>>) -> Self {
+        let mut res = self;
+        res.deadline = when.into();
+        res
+    }
+
+    /// How progress output is shown. See `OptionalProgressRecorderIter::with_output_mode`.
+    pub fn with_output_mode(self, mode: OutputMode) -> Self {
+        let mut res = self;
+        res.output_mode = mode;
+        res
+    }
+
+    /// Where progress output is written. See `OptionalProgressRecorderIter::with_output_sink`.
+    pub fn with_output_sink(self, sink: OutputSink) -> Self {
+        let mut res = self;
+        res.output = Some(sink);
+        res
+    }
+
+    /// The style of `.progress_bar()`, including the units (e.g. bytes) for its counts. See
+    /// `BarStyle`.
+    pub fn with_bar_style(self, style: impl Into<Option<BarStyle>>) -> Self {
+        let mut res = self;
+        res.bar_style = style.into();
+        res
+    }
+
+    /// How the ETA is calculated. See `OptionalProgressRecorderIter::with_eta_strategy`.
+    pub fn with_eta_strategy(self, strategy: EtaStrategy) -> Self {
+        let mut res = self;
+        res.eta_strategy = strategy;
+        res
+    }
+}
+
+/// An iterator that records it's progress as it goes along
+pub trait ProgressableIter<I> {
+    fn progress(self) -> ProgressRecorderIter<I>;
+
+    /// Like `.progress()`, but with all the settings from `config`.
+    fn progress_with(self, config: ProgressConfig) -> ProgressRecorderIter<I>;
+
+    /// Like `.progress()`, but each item counts as `f(&item)` towards `.num_done()`, rather than
+    /// 1.
+    fn progress_counting<F>(self, f: F) -> ProgressRecorderIter<Counting<I, F>>
+    where
+        I: Iterator,
+        F: FnMut(&I::Item) -> usize;
+
+    /// Like `.progress()`, but each item has a weight of `f(&item)`. Same as
+    /// `.progress_counting(…)`.
+    fn progress_weighted<F>(self, f: F) -> ProgressRecorderIter<Counting<I, F>>
+    where
+        I: Iterator,
+        F: FnMut(&I::Item) -> usize;
+
+    /// Like `.progress_counting(…)`, but `f` also gets a mutable accumulator, starting at `init`.
+    fn progress_accumulate<A, F>(
+        self,
+        init: A,
+        f: F,
+    ) -> ProgressRecorderIter<Accumulating<I, A, F>>
+    where
+        I: Iterator,
+        F: FnMut(&mut A, &I::Item) -> usize;
+
+    /// Like `.progress()`, but calls `f` with each record, and yields the items unchanged.
+    fn inspect_progress<F>(self, f: F) -> InspectProgress<I, F>
+    where
+        I: Iterator,
+        F: FnMut(&ProgressRecord);
+
+    /// Draw a progress bar on stderr, and yield the items unchanged.
+    fn progress_bar(self) -> ProgressBar<I>
+    where
+        I: Iterator;
+}
+
+impl<I> ProgressableIter<I> for I
+where
+    I: Iterator,
+{
+    /// Convert an iterator into a `ProgressRecorderIter`.
+    ///
+    /// The items are passed through as they are, so borrowing iterators work as usual. Use
+    /// `.by_ref()` to only record progress for part of an iterator, and carry on using it after.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let nums = vec![1, 2, 3, 4];
+    /// let mut iter = nums.iter();
+    /// for (_state, &num) in iter.by_ref().progress().take(2) {
+    ///     assert!(num <= 2);
+    /// }
+    /// assert_eq!(iter.next(), Some(&3));
+    /// ```
+    fn progress(self) -> ProgressRecorderIter<I> {
+        ProgressRecorderIter::new(self)
+    }
+
+    /// Convert an iterator into a `ProgressRecorderIter` with these settings.
+    fn progress_with(self, config: ProgressConfig) -> ProgressRecorderIter<I> {
+        ProgressRecorderIter(OptionalProgressRecorderIter::new(self, 1).with_config(config))
+    }
+
+    /// Convert an iterator into a `ProgressRecorderIter`, where `.num_done()` (and hence the rate,
+    /// fraction etc.) is in the units returned by `f`, rather than in items. e.g. bytes for an
+    /// iterator of buffers.
+    ///
+    /// The underlying iterator's `.size_hint()` is in items, so it isn't used for the fraction.
+    /// Use `.assume_size(…)` with the total in the same units.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let lines = vec!["hello", "world", "!"];
+    /// let mut progressor = lines.into_iter()
+    ///     .progress_counting(|line| line.len())
+    ///     .assume_size(11);
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.num_done(), 5);
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.num_done(), 10);
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.fraction(), Some(1.));
+    /// ```
+    ///
+    /// `f` can borrow local data, e.g. to look up the size of each item:
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # use std::collections::HashMap;
+    /// let sizes: HashMap<&str, usize> = vec![("a.txt", 100), ("b.txt", 300)].into_iter().collect();
+    /// let total: usize = sizes.values().sum();
+    /// let mut progressor = ["a.txt", "b.txt"]
+    ///     .iter()
+    ///     .progress_counting(|name| sizes[*name])
+    ///     .assume_size(total);
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.fraction(), Some(0.25));
+    /// ```
+    ///
+    /// For slices, `.chunks(…)` & `.windows(…)` return one item per chunk or window. Use
+    /// `ProgressableSlice` to count progress in elements of the slice.
+    fn progress_counting<F>(self, f: F) -> ProgressRecorderIter<Counting<I, F>>
+    where
+        F: FnMut(&I::Item) -> usize,
+    {
+        let counted = Arc::new(AtomicUsize::new(0));
+        let iter = Counting {
+            iter: self,
+            f,
+            counted: counted.clone(),
+        };
+        ProgressRecorderIter(OptionalProgressRecorderIter::new_counting(iter, counted))
+    }
+
+    /// Convert an iterator into a `ProgressRecorderIter`, where progress is derived from some
+    /// state which is accumulated over the items. `f` is called for every item with the
+    /// accumulator (which starts as `init`), and returns how much to advance `.num_done()` by.
+    ///
+    /// e.g. when reading a file in chunks where each item knows its offset in the file, the
+    /// progress is how far the offset has moved on:
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// struct Chunk {
+    ///     offset: usize,
+    ///     data: Vec<u8>,
+    /// }
+    /// # let chunks = vec![Chunk { offset: 100, data: vec![] }, Chunk { offset: 250, data: vec![] }];
+    /// let file_size = 1_000;
+    /// let mut progressor = chunks
+    ///     .into_iter()
+    ///     .progress_accumulate(0, |last_offset, chunk| {
+    ///         let delta = chunk.offset - *last_offset;
+    ///         *last_offset = chunk.offset;
+    ///         delta
+    ///     })
+    ///     .assume_size(file_size);
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.fraction(), Some(0.1));
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.fraction(), Some(0.25));
+    /// ```
+    fn progress_accumulate<A, F>(self, init: A, f: F) -> ProgressRecorderIter<Accumulating<I, A, F>>
+    where
+        F: FnMut(&mut A, &I::Item) -> usize,
+    {
+        let counted = Arc::new(AtomicUsize::new(0));
+        let iter = Accumulating {
+            iter: self,
+            acc: init,
+            f,
+            counted: counted.clone(),
+        };
+        ProgressRecorderIter(OptionalProgressRecorderIter::new_counting(iter, counted))
+    }
+
+    /// Convert an iterator into a `ProgressRecorderIter` where items have different weights
+    /// (i.e. costs), e.g. the size of a file to process. `.num_done()` is the total weight so
+    /// far, and the rate, fraction & ETA are in weight units, with the total weight given with
+    /// `.assume_size(…)`. This is another name for `.progress_counting(…)`.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let file_sizes = vec![5_000_000, 2_000, 3_000_000];
+    /// let total_weight = file_sizes.iter().sum::<usize>();
+    /// let mut progressor = file_sizes.into_iter()
+    ///     .progress_weighted(|size| *size)
+    ///     .assume_size(total_weight);
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.num_done(), 5_000_000);
+    /// assert!(state.fraction().unwrap() > 0.6);
+    /// ```
+    fn progress_weighted<F>(self, f: F) -> ProgressRecorderIter<Counting<I, F>>
+    where
+        F: FnMut(&I::Item) -> usize,
+    {
+        self.progress_counting(f)
+    }
+
+    /// Record the progress of an iterator, calling `f` with each `ProgressRecord`, but yielding
+    /// the original items, so it can be added to an existing chain of iterators without changing
+    /// the rest of it. Use `ProgressRecorderIter::inspect_progress` to change the settings first.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let total: u32 = (0..100)
+    ///     .inspect_progress(|state| {
+    ///         state.do_every_n_sec(1., |state| println!("{} done", state.num_done()));
+    ///     })
+    ///     .filter(|num| num % 2 == 0)
+    ///     .sum();
+    /// assert_eq!(total, 2_450);
+    /// ```
+    fn inspect_progress<F>(self, f: F) -> InspectProgress<I, F>
+    where
+        F: FnMut(&ProgressRecord),
+    {
+        self.progress().inspect_progress(f)
+    }
+
+    /// Draw a progress bar on stderr while iterating, with the percent, count, rate and ETA,
+    /// yielding the original items. Use `ProgressRecorderIter::progress_bar` to change the
+    /// settings first.
+    ///
+    /// If the total isn't known, a spinner with the count, rate and elapsed time is shown
+    /// instead of the bar.
+    ///
+    /// When the output is interactive (see `OutputMode`), the bar is redrawn in place, up to 10
+    /// times a second, and finished with a newline when the iterator ends (or is dropped).
+    /// Otherwise (e.g. in CI logs), a new line is printed at most every 10 seconds.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # let my_big_vec = vec![false; 100];
+    /// for val in my_big_vec.iter().progress_bar() {
+    ///     // Do something with val
+    /// }
+    /// ```
+    fn progress_bar(self) -> ProgressBar<I> {
+        self.progress().progress_bar()
+    }
+}
+
+/// Progress over a slice's `.chunks(…)` & `.windows(…)`, counted in elements of the slice rather
+/// than in chunks or windows.
+pub trait ProgressableSlice<T> {
+    /// `.chunks(chunk_size)`, with progress counted in elements. Each chunk advances `.num_done()`
+    /// by its length, and the total is the length of the slice.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableSlice;
+    /// let data = [0u8; 10];
+    /// let mut progressor = data.progress_chunks(4);
+    /// let (state, chunk) = progressor.next().unwrap();
+    /// assert_eq!(chunk.len(), 4);
+    /// assert_eq!(state.num_done(), 4);
+    /// assert_eq!(state.fraction(), Some(0.4));
+    /// ```
+    fn progress_chunks(&self, chunk_size: usize) -> ProgressRecorderIter<SliceChunks<'_, T>>;
+
+    /// `.windows(window_size)`, with progress counted in elements. `.num_done()` is the number of
+    /// elements which have been in a window so far: the first window advances it by
+    /// `window_size`, every later window by 1. The total is the length of the slice.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableSlice;
+    /// let data = [0u8; 10];
+    /// let mut progressor = data.progress_windows(4);
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.num_done(), 4);
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.num_done(), 5);
+    /// ```
+    fn progress_windows(&self, window_size: usize) -> ProgressRecorderIter<SliceWindows<'_, T>>;
+}
+
+/// The iterator for `ProgressableSlice::progress_chunks`
+pub type SliceChunks<'a, T> = Counting<std::slice::Chunks<'a, T>, fn(&&[T]) -> usize>;
+
+/// The iterator for `ProgressableSlice::progress_windows`
+pub type SliceWindows<'a, T> =
+    Accumulating<std::slice::Windows<'a, T>, usize, fn(&mut usize, &&[T]) -> usize>;
+
+impl<T> ProgressableSlice<T> for [T] {
+    fn progress_chunks(&self, chunk_size: usize) -> ProgressRecorderIter<SliceChunks<'_, T>> {
+        let len: fn(&&[T]) -> usize = |chunk| chunk.len();
+        self.chunks(chunk_size)
+            .progress_counting(len)
+            .assume_size(self.len())
+    }
+
+    fn progress_windows(&self, window_size: usize) -> ProgressRecorderIter<SliceWindows<'_, T>> {
+        // The first window adds all its elements, later ones only their last
+        let next_adds: fn(&mut usize, &&[T]) -> usize = |adds, _| std::mem::replace(adds, 1);
+        self.windows(window_size)
+            .progress_accumulate(window_size, next_adds)
+            .assume_size(self.len())
+    }
+}
+
+impl<I> Iterator for ProgressRecorderIter<I>
+where
+    I: Iterator,
+{
+    type Item = (ProgressRecord, <I as Iterator>::Item);
+
+    #[inline]
+    fn next(&mut self) -> Option<(ProgressRecord, <I as Iterator>::Item)> {
+        self.next_with(Iterator::next)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+impl<I: Iterator> ProgressRecorderIter<I> {
+    /// The next item & record, getting the item with `next`
+    fn next_with(
+        &mut self,
+        next: fn(&mut I) -> Option<<I as Iterator>::Item>,
+    ) -> Option<(ProgressRecord, <I as Iterator>::Item)> {
+        let res = self.0.next_inner(next).map(|a| {
+            self.0.count_item();
+            let fake_now = std::mem::take(&mut self.0._fake_now);
+            // we know there is always a record generated
+            (self.0.generate_record(fake_now).unwrap(), a)
+        });
+        if res.is_none() {
+            self.0.finished();
+        }
+        res
+    }
+}
+
+/// Items taken from the back are counted just like those from the front, e.g. with `.rev()`.
+///
+/// ```
+/// # use iter_progress::ProgressableIter;
+/// let mut progressor = (0..10).progress().rev();
+/// let (state, num) = progressor.next().unwrap();
+/// assert_eq!(num, 9);
+/// assert_eq!(state.num_done(), 1);
+/// assert_eq!(state.fraction(), Some(0.1));
+/// ```
+/// The length is how many items are left.
+///
+/// ```
+/// # use iter_progress::ProgressableIter;
+/// let mut progressor = vec![1, 2, 3].into_iter().progress();
+/// progressor.next();
+/// assert_eq!(progressor.len(), 2);
+/// ```
+impl<I: ExactSizeIterator> ExactSizeIterator for ProgressRecorderIter<I> {}
+
+impl<I: FusedIterator> FusedIterator for ProgressRecorderIter<I> {}
+
+impl<I: DoubleEndedIterator> DoubleEndedIterator for ProgressRecorderIter<I> {
+    #[inline]
+    fn next_back(&mut self) -> Option<(ProgressRecord, <I as Iterator>::Item)> {
+        self.next_with(DoubleEndedIterator::next_back)
+    }
+}
+
+/// Yields the items of the underlying iterator unchanged, adding how much each one counts (from
+/// `f`) to the progress of the `ProgressRecorderIter` it's in. Created with
+/// `.progress_counting(…)` & `.progress_weighted(…)`.
+pub struct Counting<I, F> {
+    iter: I,
+    f: F,
+
+    /// How much the items yielded so far count, which the recorder hasn't taken yet
+    counted: Arc<AtomicUsize>,
+}
+
+impl<I, F> Counting<I, F> {
+    /// Gets the original iterator back, consuming this.
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+}
+
+impl<I, F> Iterator for Counting<I, F>
+where
+    I: Iterator,
+    F: FnMut(&I::Item) -> usize,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.iter.next()?;
+        self.counted.fetch_add((self.f)(&item), Ordering::Relaxed);
+        Some(item)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I, F> DoubleEndedIterator for Counting<I, F>
+where
+    I: DoubleEndedIterator,
+    F: FnMut(&I::Item) -> usize,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<I::Item> {
+        let item = self.iter.next_back()?;
+        self.counted.fetch_add((self.f)(&item), Ordering::Relaxed);
+        Some(item)
+    }
+}
+
+impl<I, F> ExactSizeIterator for Counting<I, F>
+where
+    I: ExactSizeIterator,
+    F: FnMut(&I::Item) -> usize,
+{
+}
+
+impl<I, F> FusedIterator for Counting<I, F>
+where
+    I: FusedIterator,
+    F: FnMut(&I::Item) -> usize,
+{
+}
+
+/// Like `Counting`, but `f` also gets a mutable accumulator. Created with
+/// `.progress_accumulate(…)`.
+pub struct Accumulating<I, A, F> {
+    iter: I,
+    acc: A,
+    f: F,
+
+    /// How much the items yielded so far count, which the recorder hasn't taken yet
+    counted: Arc<AtomicUsize>,
+}
+
+impl<I, A, F> Accumulating<I, A, F> {
+    /// Gets the original iterator back, consuming this.
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+}
+
+impl<I, A, F> Iterator for Accumulating<I, A, F>
+where
+    I: Iterator,
+    F: FnMut(&mut A, &I::Item) -> usize,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.iter.next()?;
+        let count = (self.f)(&mut self.acc, &item);
+        self.counted.fetch_add(count, Ordering::Relaxed);
+        Some(item)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I, A, F> DoubleEndedIterator for Accumulating<I, A, F>
+where
+    I: DoubleEndedIterator,
+    F: FnMut(&mut A, &I::Item) -> usize,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<I::Item> {
+        let item = self.iter.next_back()?;
+        let count = (self.f)(&mut self.acc, &item);
+        self.counted.fetch_add(count, Ordering::Relaxed);
+        Some(item)
+    }
+}
+
+impl<I, A, F> ExactSizeIterator for Accumulating<I, A, F>
+where
+    I: ExactSizeIterator,
+    F: FnMut(&mut A, &I::Item) -> usize,
+{
+}
+
+impl<I, A, F> FusedIterator for Accumulating<I, A, F>
+where
+    I: FusedIterator,
+    F: FnMut(&mut A, &I::Item) -> usize,
+{
+}
+
+/// Calls a function with each `ProgressRecord`, and yields the items of the underlying iterator
+/// unchanged. Created with `.inspect_progress(…)`.
+pub struct InspectProgress<I: Iterator, F> {
+    inner: ProgressRecorderIter<I>,
+    f: F,
+}
+
+impl<I, F> Iterator for InspectProgress<I, F>
+where
+    I: Iterator,
+    F: FnMut(&ProgressRecord),
+{
+    type Item = <I as Iterator>::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (record, item) = self.inner.next()?;
+        (self.f)(&record);
+        Some(item)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Draws a progress bar on stderr (or the recorder's `OutputSink`, if set), and yields the items
+/// of the underlying iterator unchanged. Created with `.progress_bar()`.
+pub struct ProgressBar<I: Iterator> {
+    inner: ProgressRecorderIter<I>,
+
+    /// Where it's drawn
+    output: OutputSink,
+
+    /// The last record, if it hasn't been drawn, for the final redraw
+    last: Option<ProgressRecord>,
+
+    /// Whether anything has been drawn in place, and so needs a newline at the end
+    drawn: bool,
+
+    /// Whether the output is interactive
+    interactive: bool,
+
+    style: BarStyle,
+}
+
+impl<I: Iterator> ProgressBar<I> {
+    /// Set the colours & characters of the bar. See `BarStyle`.
+    pub fn with_style(self, style: BarStyle) -> Self {
+        let mut res = self;
+        res.style = style;
+        res
+    }
+
+    fn draw(&mut self, record: &ProgressRecord) {
+        let line = record.bar_line(&self.style);
+        if record.interactive {
+            // Clear the rest of the line, in case it was longer before
+            self.output.write(format_args!("\r{}\x1b[K", line));
+            self.drawn = true;
+        } else {
+            self.output.write(format_args!("{}\n", line));
+        }
+    }
+
+    /// Draw the final state, unless it was the last thing drawn, and move to the next line
+    fn finish(&mut self) {
+        if let Some(last) = self.last.take() {
+            self.draw(&last);
+        }
+        if self.drawn {
+            self.drawn = false;
+            self.output.write(format_args!("\n"));
+        }
+    }
+}
+
+impl<I: Iterator> Iterator for ProgressBar<I> {
+    type Item = <I as Iterator>::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.inner.next() {
+            Some((mut record, item)) => {
+                record.interactive = self.interactive;
+                let should_draw = if record.interactive {
+                    !self.drawn || record.should_do_every_n_sec(BAR_REFRESH_SECS)
+                } else {
+                    record.should_do_every_n_sec(PLAIN_OUTPUT_MIN_SECS)
+                };
+                if should_draw {
+                    self.draw(&record);
+                    self.last = None;
+                } else {
+                    self.last = Some(record);
+                }
+                Some(item)
+            }
+            None => {
+                self.finish();
+                None
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<I: Iterator> Drop for ProgressBar<I> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Stores the last `ProgressRecord` of a `ProgressRecorderIter` when it's finished with. Created
+/// with `.finish_into(…)`.
+pub struct FinishInto<'a, I: Iterator> {
+    inner: ProgressRecorderIter<I>,
+    slot: &'a mut Option<ProgressRecord>,
+    last: Option<ProgressRecord>,
+}
+
+impl<'a, I: Iterator> FinishInto<'a, I> {
+    fn store(&mut self) {
+        if let Some(last) = self.last.take() {
+            *self.slot = Some(last);
+        }
+    }
+}
+
+impl<'a, I: Iterator> Iterator for FinishInto<'a, I> {
+    type Item = (ProgressRecord, <I as Iterator>::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.inner.next();
+        match res {
+            Some((ref record, _)) => self.last = Some(record.clone()),
+            None => self.store(),
+        }
+        res
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, I: Iterator> Drop for FinishInto<'a, I> {
+    fn drop(&mut self) {
+        self.store();
+    }
+}
+
+/// Prints a summary line when a `ProgressRecorderIter` is dropped. Created with
+/// `.report_on_drop()`.
+pub struct ReportOnDrop<I: Iterator> {
+    inner: ProgressRecorderIter<I>,
+    exhausted: bool,
+}
+
+impl<I: Iterator> Iterator for ReportOnDrop<I> {
+    type Item = (ProgressRecord, <I as Iterator>::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.inner.next();
+        if res.is_none() {
+            self.exhausted = true;
+        }
+        res
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<I: Iterator> Drop for ReportOnDrop<I> {
+    fn drop(&mut self) {
+        if self
+            .inner
+            .cancel
+            .as_ref()
+            .is_some_and(|&(_, reported)| reported)
+        {
+            // It was cancelled, and has already printed a summary saying so
+            return;
+        }
+        let msg = if self.inner.is_cancelled() {
+            "Cancelled"
+        } else if self.exhausted {
+            "Done"
+        } else {
+            "Stopped"
+        };
+        self.inner.finish_with_message(msg);
+    }
+}
+
+impl<I: Iterator> OptionalProgressRecorderIter<I> {
+    pub fn new(iter: I, generate_every_count: usize) -> OptionalProgressRecorderIter<I> {
+        let initial_total = iter.size_hint().1;
+        OptionalProgressRecorderIter {
+            initial_total,
+            iter,
+            count: 0,
+            done: 0,
+            counted: None,
+            generate_every_count,
+            milestones: None,
+            started_iterating: Instant::now(),
+            clock: Arc::new(SystemClock),
+            time_sampling: None,
+            elapsed_offset: Duration::ZERO,
+            previous_record_tm: None,
+            pause: None,
+            paused_at_start: Duration::ZERO,
+            paused_at_previous: Duration::ZERO,
+            num_retries: 0,
+            num_failed: 0,
+            handle: None,
+            last_printed: Arc::new(AtomicU64::new(0)),
+            nested: None,
+            parent: None,
+            rolling_average: None,
+            exp_average: None,
+            recent_window: None,
+            item_durations: RunningStats::default(),
+            previous_recent_rate: None,
+            recent_rate_range: None,
+            trailing_rates: None,
+            assumed_size: None,
+            unicode: true,
+            interactive: OutputMode::Auto.is_interactive(),
+            output_mode: OutputMode::Auto,
+            output: OutputSink::Stdout,
+            output_set: false,
+            baseline_rate: None,
+            deadline: None,
+            phase_rates: None,
+            bar_style: None,
+            eta_strategy: EtaStrategy::Naive,
+            history: None,
+            record_limit: None,
+            num_records: 0,
+            csv_log: None,
+            json_lines: None,
+            events: None,
+            checkpoints: None,
+            terminal_title: None,
+            watchdog: None,
+            periodic: None,
+            template: None,
+            label: None,
+            cancel: None,
+            heartbeat: None,
+            #[cfg(feature = "latency-histogram")]
+            latency_histogram: None,
+            #[cfg(all(unix, feature = "signals"))]
+            signal_printer: None,
+            #[cfg(feature = "opentelemetry")]
+            otel: None,
+            #[cfg(feature = "prometheus")]
+            prometheus: None,
+            #[cfg(feature = "log")]
+            logger: None,
+            #[cfg(feature = "tracing")]
+            tracer: None,
+            _fake_now: None,
+        }
+    }
+
+    /// Like `new`, with every count of 1, but the items count towards `done` by however much
+    /// `iter` (a `Counting` or `Accumulating`) adds to `counted`.
+    fn new_counting(iter: I, counted: Arc<AtomicUsize>) -> OptionalProgressRecorderIter<I> {
+        let mut res = OptionalProgressRecorderIter::new(iter, 1);
+        res.counted = Some(counted);
+        res
+    }
+
+    /// Apply all the settings in `config`.
+    pub fn with_config(self, config: ProgressConfig) -> Self {
+        let res = match config.recent_window {
+            Some((n, d, policy)) => self.recent_window_hybrid(n, d, policy),
+            None => self,
+        };
+        let res = match config.trailing_rates {
+            Some(max) => res.track_rates_over(max),
+            None => res,
+        };
+        let res = match config.template {
+            Some(template) => res.with_template(template),
+            None => res,
+        };
+        let res = match config.output {
+            Some(output) => res.with_output_sink(output),
+            None => res,
+        };
+        let mut res = res
+            .with_label(config.label)
+            .sample_time_every(config.sample_time_every)
+            .assume_size(config.assumed_size)
+            .with_rolling_average(config.rolling_average)
+            .with_exp_average(config.exp_average)
+            .with_unicode(config.unicode)
+            .with_baseline_rate(config.baseline_rate)
+            .with_deadline_at(config.deadline)
+            .with_output_mode(config.output_mode)
+            .with_eta_strategy(config.eta_strategy);
+        res.bar_style = config.bar_style;
+        res
+    }
+
+    /// Force output (e.g. `ProgressRecord::print_progress_every_n_sec`) to be interactive or plain.
+    /// Default: `OutputMode::Auto`, which uses `OutputMode::detect()`, but checks the
+    /// `OutputSink` (or stderr for `.progress_bar()`, since that's where it's drawn) rather than
+    /// stdout.
+    pub fn with_output_mode(self, mode: OutputMode) -> Self {
+        let mut res = self;
+        res.interactive = mode.is_interactive_for(res.output.is_terminal());
+        res.output_mode = mode;
+        res
+    }
+
+    /// Where the `ProgressRecord::print_…` methods write to, and `.progress_bar()` draws.
+    /// Default: `OutputSink::Stdout`, but stderr for `.progress_bar()`.
+    ///
+    /// ```
+    /// # use iter_progress::{OutputSink, ProgressableIter};
+    /// for (state, _) in (0..100).progress().with_output_sink(OutputSink::Stderr) {
+    ///     state.print_progress_every_n_sec(1.);
+    /// }
+    /// ```
+    pub fn with_output_sink(self, sink: OutputSink) -> Self {
+        let mut res = self;
+        res.output = sink;
+        res.output_set = true;
+        res.interactive = res.output_mode.is_interactive_for(res.output.is_terminal());
+        res
+    }
+
+    /// Use `template` for the progress message (`ProgressRecord::message`), including when
+    /// printing with `ProgressRecord::print_progress_every_n_sec`, drawing `.progress_bar()`,
+    /// and logging. See `Template` for the placeholders.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..1_000)
+    ///     .progress()
+    ///     .with_template("{done}/{total} ({percent:.1}%) at {rate}/s, ETA {eta}");
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert!(state.message().starts_with("1/1,000 (0.1%) at "));
+    /// ```
+    pub fn with_template(self, template: impl Into<Template>) -> Self {
+        let mut res = self;
+        res.template = Some(template.into());
+        res
+    }
+
+    /// Label this progress, e.g. `"parsing"` or `"uploading"`. It's on every record
+    /// (`ProgressRecord::label`), and before the progress message, `.progress_bar()`, and the
+    /// line from `.finish()`, so output from several jobs at once can be told apart.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..10)
+    ///     .progress()
+    ///     .with_template("{count}")
+    ///     .with_label("parsing".to_string());
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.label(), Some("parsing"));
+    /// assert_eq!(state.message(), "parsing: 1 / 10");
+    /// ```
+    pub fn with_label(self, label: impl Into<Option<String>>) -> Self {
+        let mut res = self;
+        res.label = label.into().map(Arc::from);
+        res
+    }
+
+    /// Whether Unicode characters can be used for output, e.g. in `ProgressRecord::bar_unicode`.
+    /// Turn this off for terminals which can't display them, and ASCII will be used instead.
+    /// Default: `true`.
+    pub fn with_unicode(self, unicode: bool) -> Self {
+        let mut res = self;
+        res.unicode = unicode;
+        res
+    }
+
+    /// Set a baseline rate (items per second), e.g. from a previous run, which
+    /// `ProgressRecord::speedup` compares the current rate to. `None` to unset.
+    pub fn with_baseline_rate(self, baseline: impl Into<Option<f64>>) -> Self {
+        let mut res = self;
+        res.baseline_rate = baseline.into();
+        res
+    }
+
+    /// Set historical rates (items per second) for each phase of the job, for a better
+    /// `ProgressRecord::eta`. The items are split into equally sized phases, one per rate, in
+    /// order. `None`, or no rates, to unset.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// // The first half usually goes at 100 items/sec, but the second half at 10 items/sec
+    /// for (state, _num) in (0..1_000).progress().with_phase_rates(vec![100., 10.]) {
+    ///     // ...
+    /// #   let _ = state.eta();
+    /// }
+    /// ```
+    pub fn with_phase_rates(self, rates: impl Into<Option<Vec<f64>>>) -> Self {
+        let mut res = self;
+        res.phase_rates = rates
+            .into()
+            .filter(|rates| !rates.is_empty())
+            .map(Arc::from);
+        res
+    }
+
+    /// How `ProgressRecord::eta` is calculated. Default: `EtaStrategy::Naive`.
+    pub fn with_eta_strategy(self, strategy: EtaStrategy) -> Self {
+        let mut res = self;
+        res.eta_strategy = strategy;
+        res
+    }
+
+    /// Set a wall-clock deadline which this must be finished by, for `ProgressRecord::on_track`.
+    /// `None` to unset.
+    ///
+    /// It's compared with `ProgressRecord::estimated_completion_time`, so how long is left is
+    /// measured with the recorder's clock (see `with_clock`).
+    pub fn with_deadline_at(self, when: impl Into<Option<SystemTime>>) -> Self {
+        let mut res = self;
+        res.deadline = when.into();
+        res
+    }
+
+    /// Set the desired size of the rolling average window calculation (if any). `None` to
+    /// disable.
+    /// Larger values slow down each iteration (since the rolling average is calculated each
+    /// iteration).
+    pub fn with_rolling_average(self, size: impl Into<Option<usize>>) -> Self {
+        let mut res = self;
+        res.rolling_average = size.into().map(|size| (size, vec![0.; size]));
+        res
+    }
+
+    /// Calculate `ProgressRecord::recent_rate` over a window of the last `n` items and/or the
+    /// last `d` duration, depending on the `policy`. A window of only items misbehaves for bursty
+    /// sources (the items can span a tiny or huge time), and a window of only time misbehaves for
+    /// sparse sources (it can have 0 or 1 items), so this combines both.
+    ///
+    /// ```
+    /// # use iter_progress::{ProgressableIter, WindowPolicy};
+    /// use std::time::Duration;
+    /// # let my_big_vec = vec![false; 100];
+    /// let progressor = my_big_vec
+    ///     .iter()
+    ///     .progress()
+    ///     .recent_window_hybrid(1_000, Duration::from_secs(10), WindowPolicy::Smaller);
+    /// for (state, _) in progressor {
+    ///     let _rate = state.recent_rate();
+    /// }
+    /// ```
+    pub fn recent_window_hybrid(self, n: usize, d: Duration, policy: WindowPolicy) -> Self {
+        let mut res = self;
+        res.recent_window = Some(RecentWindow {
+            max_items: n,
+            max_duration: d,
+            policy,
+            samples: VecDeque::new(),
+        });
+        res
+    }
+
+    /// Calculate `ProgressRecord::recent_rate` over a window of the last `n` items.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # let my_big_vec = vec![false; 100];
+    /// for (state, _) in my_big_vec.iter().progress().recent_window_items(10) {
+    ///     let _rate = state.recent_rate();
+    /// }
+    /// ```
+    pub fn recent_window_items(self, n: usize) -> Self {
+        self.recent_window_hybrid(n, Duration::MAX, WindowPolicy::Smaller)
+    }
+
+    /// Calculate `ProgressRecord::recent_rate` over a window of the last `d` duration. The window
+    /// always includes at least the previous record, so there is always a rate.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// use std::time::Duration;
+    /// # let my_big_vec = vec![false; 100];
+    /// let progressor = my_big_vec
+    ///     .iter()
+    ///     .progress()
+    ///     .recent_window_duration(Duration::from_secs(10));
+    /// for (state, _) in progressor {
+    ///     let _rate = state.recent_rate();
+    /// }
+    /// ```
+    pub fn recent_window_duration(self, d: Duration) -> Self {
+        self.recent_window_hybrid(usize::MAX, d, WindowPolicy::Smaller)
+    }
+
+    /// Keep the timestamps of the records over the last `max` duration, so `.rate_over(…)` can
+    /// calculate the rate over any window up to that.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// use std::time::Duration;
+    /// let mut progressor = (0..1_000)
+    ///     .progress()
+    ///     .track_rates_over(Duration::from_secs(60));
+    /// (&mut progressor).for_each(|_| {});
+    /// let _last_30_secs = progressor.rate_over(Duration::from_secs(30));
+    /// ```
+    pub fn track_rates_over(self, max: Duration) -> Self {
+        let mut res = self;
+        res.trailing_rates = Some(TrailingRates::new(max));
+        res
+    }
+
+    /// The rate (items per second) over the trailing `window`, up to the latest record. A
+    /// `window` longer than `.track_rates_over(…)`'s is cut down to that, and it's from the
+    /// start if it's longer than the run. None if `.track_rates_over(…)` wasn't used, or there
+    /// aren't 2 records yet.
+    pub fn rate_over(&self, window: Duration) -> Option<f64> {
+        self.trailing_rates.as_ref()?.rate_over(window)
+    }
+
+    /// Set the desired exponential rate
+    /// 0.001 is a good value.
+    ///
+    /// This is the smoothing factor (alpha) of an exponentially weighted moving average of the
+    /// time between items, which `ProgressRecord::rate_ewma` is the rate of. Each new
+    /// duration has weight `rate`, and the previous average `1 - rate`, so smaller values are
+    /// smoother, and larger values respond quicker. `None` to stop.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # let my_big_vec = vec![false; 100];
+    /// for (state, _) in my_big_vec.iter().progress().with_exp_average(0.1) {
+    ///     let _smoothed_rate = state.rate_ewma();
+    /// }
+    /// ```
+    pub fn with_exp_average(self, rate: impl Into<Option<f64>>) -> Self {
+        let mut res = self;
+        res.exp_average = rate.into().map(|rate| (rate, None));
+        res
+    }
+
+    /// Add an 'assumed size' to this iterator. If the iterator doesn't return an exact value for
+    /// `.size_hint()`, you can use this to override
+    /// the `.size_hint()` from the iterator will override this if it returns an exact size (i.e.
+    /// `.size_hint().1 == Some(...size_hint().0).
+    /// Set to `None` to undo this.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..).progress().assume_size(10);
+    /// let (state, num) = progressor.next().unwrap();
+    /// assert_eq!(state.fraction(), Some(0.1));
+    /// ```
+    pub fn assume_size(self, size: impl Into<Option<usize>>) -> Self {
+        let mut new = self;
+        new.assumed_size = size.into();
+        new
+    }
+
+    /// Keep a history of `(duration_since_start, count)` samples, one for every record generated.
+    /// With a `limit`, at most that many samples are kept, chosen with reservoir sampling so
+    /// they're spread over the whole run. `None` keeps every sample.
+    ///
+    /// ```
+    /// # use iter_progress::OptionalProgressableIter;
+    /// let mut progressor = (0..1_000).optional_progress(10).with_history(None);
+    /// (&mut progressor).for_each(|_| {});
+    /// assert_eq!(progressor.samples().len(), 100);
+    /// ```
+    pub fn with_history(self, limit: impl Into<Option<usize>>) -> Self {
+        let mut res = self;
+        res.history = Some(History::new(limit.into()));
+        res
+    }
+
+    /// Only generate the first `max` records, after which only the items are returned (with
+    /// `None` as the record). Timestamps are still sampled, and a uniformly random (reservoir
+    /// sampled) subset of `max` samples from the whole run is kept (see `.samples()`), so memory
+    /// use is bounded no matter how long the iterator is, and adding a sample is O(1).
+    ///
+    /// ```
+    /// # use iter_progress::OptionalProgressableIter;
+    /// let mut progressor = (0..1_000).optional_progress(1).limit_records(5);
+    /// let records = (&mut progressor).filter(|(rec, _)| rec.is_some()).count();
+    /// assert_eq!(records, 5);
+    /// assert_eq!(progressor.samples().len(), 5);
+    /// ```
+    pub fn limit_records(self, max: usize) -> Self {
+        let mut res = self.with_history(max);
+        res.record_limit = Some(max);
+        res
+    }
+
+    /// The `(duration_since_start, count)` samples recorded, in order, if `.with_history(…)` or
+    /// `.limit_records(…)` was used. Otherwise empty.
+    pub fn samples(&self) -> Vec<(Duration, usize)> {
+        match self.history {
+            None => Vec::new(),
+            Some(ref history) => history.in_order(),
+        }
+    }
+
+    /// Keep a histogram of the time between records (i.e. between items, for `.progress()`),
+    /// for percentiles of the latency, with `.latency_histogram()`.
+    ///
+    /// Only available with the `latency-histogram` feature.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..1_000).progress().with_latency_histogram();
+    /// (&mut progressor).for_each(|_| {});
+    /// let histogram = progressor.latency_histogram().unwrap();
+    /// assert_eq!(histogram.len(), 999);
+    /// assert!(histogram.p50() <= histogram.p99());
+    /// ```
+    #[cfg(feature = "latency-histogram")]
+    pub fn with_latency_histogram(self) -> Self {
+        let mut res = self;
+        res.latency_histogram = Some(LatencyHistogram::new());
+        res
+    }
+
+    /// The histogram of the time between records, if `.with_latency_histogram()` was used.
+    #[cfg(feature = "latency-histogram")]
+    pub fn latency_histogram(&self) -> Option<&LatencyHistogram> {
+        self.latency_histogram.as_ref()
+    }
+
+    /// The geometric mean of the rates (items per second) between consecutive `.samples()`.
+    /// None if there are no intervals with a rate, e.g. if there's no history.
+    ///
+    /// Unlike the arithmetic mean, a short burst of very fast items doesn't dominate this, as
+    /// it's the mean of the logs of the rates. So it's the better choice when the rate varies
+    /// multiplicatively (e.g. some intervals are 10× faster, others 10× slower). Intervals with
+    /// no items, or no time, are skipped.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..1_000).progress().with_history(100);
+    /// for _ in progressor.by_ref().take(10) {}
+    /// println!("{:?}", progressor.geomean_rate());
+    /// ```
+    pub fn geomean_rate(&self) -> Option<f64> {
+        let (sum, n) = self
+            .samples()
+            .windows(2)
+            .filter_map(|pair| {
+                let secs = (pair[1].0.saturating_sub(pair[0].0)).as_secs_f64();
+                let items = pair[1].1.saturating_sub(pair[0].1);
+                if secs > 0. && items > 0 {
+                    Some((items as f64 / secs).ln())
+                } else {
+                    None
+                }
+            })
+            .fold((0., 0), |(sum, n), log_rate| (sum + log_rate, n + 1));
+        if n == 0 {
+            None
+        } else {
+            Some((sum / n as f64).exp())
+        }
+    }
+
+    /// Append a row of `elapsed_secs,count,rate,percent` to the CSV file at `path` for every
+    /// `every` records generated. A header row is written if the file is empty. `percent` is left
+    /// blank if it's not known.
+    ///
+    /// Each row is flushed to the file as it is written. If writing fails, logging stops, but
+    /// iteration continues.
+    ///
+    /// ```no_run
+    /// # use iter_progress::OptionalProgressableIter;
+    /// let progressor = (0..1_000_000)
+    ///     .optional_progress(1_000)
+    ///     .log_csv("progress.csv", 10)
+    ///     .expect("Can't open CSV file");
+    /// for (_state, _num) in progressor {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn log_csv(self, path: impl AsRef<std::path::Path>, every: usize) -> io::Result<Self> {
+        let mut res = self;
+        res.csv_log = Some(CsvLog::open(path, CsvInterval::Records(every))?);
+        Ok(res)
+    }
+
+    /// Like `.log_csv()`, but append a row at most every `every`, rather than every n records.
+    ///
+    /// ```no_run
+    /// # use iter_progress::ProgressableIter;
+    /// # use std::time::Duration;
+    /// let progressor = (0..1_000_000)
+    ///     .progress()
+    ///     .log_csv_every("progress.csv", Duration::from_secs(10))
+    ///     .expect("Can't open CSV file");
+    /// for (_state, _num) in progressor {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn log_csv_every(
+        self,
+        path: impl AsRef<std::path::Path>,
+        every: Duration,
+    ) -> io::Result<Self> {
+        let mut res = self;
+        res.csv_log = Some(CsvLog::open(path, CsvInterval::Time(every))?);
+        Ok(res)
+    }
+
+    /// Write the progress to `sink` as JSON lines, i.e. one `ProgressRecord::json_line()` object
+    /// per line, at most every `every`. Useful when progress is read by another program.
+    ///
+    /// ```
+    /// # use iter_progress::{OptionalProgressableIter, OutputSink};
+    /// # use std::time::Duration;
+    /// let progressor = (0..1_000_000)
+    ///     .optional_progress(1_000)
+    ///     .emit_json_lines(OutputSink::Stderr, Duration::from_secs(5));
+    /// for (_state, _num) in progressor {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn emit_json_lines(self, sink: OutputSink, every: Duration) -> Self {
+        let mut res = self;
+        res.json_lines = Some((sink, every));
+        res
+    }
+
+    /// Only read the clock every `k` items, and for the records in between, interpolate the time
+    /// at the rate between the last 2 readings. For very fast iterators, reading the clock for
+    /// every item can take longer than the work itself. The time (& hence the rate & ETA) is a
+    /// little less precise, but never goes backwards. `None` reads it for every record (the
+    /// default).
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let sum: u64 = (0..1_000_000u64)
+    ///     .progress()
+    ///     .sample_time_every(1_000)
+    ///     .map(|(_state, num)| num)
+    ///     .sum();
+    /// ```
+    pub fn sample_time_every(self, k: impl Into<Option<usize>>) -> Self {
+        let mut res = self;
+        res.time_sampling = k.into().map(TimeSampling::new);
+        res
+    }
+
+    /// Get the time from `clock`, rather than the system clock, e.g. a `MockClock` in tests.
+    /// Iterating is treated as starting now, by that clock. Pausing, checkpoints, the heartbeat
+    /// & handles use it too, so set it before getting a `.handle()`. The watchdog always uses
+    /// the system clock.
+    pub fn with_clock(self, clock: impl Clock + 'static) -> Self {
+        let mut res = self;
+        res.clock = Arc::new(clock);
+        res.started_iterating = res.clock.now();
+        if let Some(ref pause) = res.pause {
+            pause.set_clock(res.clock.clone());
+        }
+        res
+    }
+
+    /// Save a `Checkpoint` to `path` at most every `every`, and when the underlying iterator is
+    /// exhausted (or `.finish()` is called), so a job can `.resume_from(…)` it after a restart.
+    /// Errors saving are ignored, and it's tried again next time.
+    pub fn save_checkpoints(self, path: impl Into<PathBuf>, every: Duration) -> Self {
+        let mut res = self;
+        res.checkpoints = Some(checkpoint::CheckpointWriter::new(path.into(), every));
+        res
+    }
+
+    /// Carry on from `checkpoint` (if any), as if its count had been done already, in its time.
+    /// (see `.reset_to(…)`). If no size has been assumed, its total is assumed. The underlying
+    /// iterator should start after the work already done. See `Checkpoint`.
+    pub fn resume_from(self, checkpoint: impl Into<Option<Checkpoint>>) -> Self {
+        let mut res = self;
+        if let Some(checkpoint) = checkpoint.into() {
+            res.reset_to(checkpoint.count, checkpoint.elapsed);
+            if res.assumed_size.is_none() {
+                res.assumed_size = checkpoint.total;
+            }
+        }
+        res
+    }
+
+    /// Send `ProgressEvent`s to `sender`: `Started` when the first item is asked for, a `Tick`
+    /// with the latest record at most every `every`, and `Finished` with a summary when the
+    /// underlying iterator is exhausted, or `.finish()` is called. This way a frontend (e.g. a GUI
+    /// or TUI) can show the progress on another thread. Send errors (e.g. if the receiver has been
+    /// dropped) are ignored.
+    ///
+    /// ```
+    /// # use iter_progress::{ProgressableIter, ProgressEvent};
+    /// # use std::time::Duration;
+    /// use std::sync::mpsc::channel;
+    /// let (sender, receiver) = channel();
+    /// let worker = std::thread::spawn(move || {
+    ///     let progressor = (0..100)
+    ///         .progress()
+    ///         .send_events(sender, Duration::from_millis(100));
+    ///     for (_state, _num) in progressor {
+    ///         // ...
+    ///     }
+    /// });
+    /// for event in receiver {
+    ///     match event {
+    ///         ProgressEvent::Started => println!("Started"),
+    ///         ProgressEvent::Tick(state) => println!("{:?}% done", state.percent()),
+    ///         ProgressEvent::Finished(summary) => println!("{}", summary.summary_line()),
+    ///     }
+    /// }
+    /// worker.join().unwrap();
+    /// ```
+    pub fn send_events(self, sender: Sender<ProgressEvent>, every: Duration) -> Self {
+        let mut res = self;
+        res.events = Some(events::EventSender::new(sender, every));
+        res
+    }
+
+    /// Show the progress in the terminal's (window or tab) title, updating it every `every`
+    /// records generated. The title is cleared when the iterator ends, or this is dropped.
+    ///
+    /// Nothing is done if stdout isn't a terminal.
+    ///
+    /// ```
+    /// # use iter_progress::OptionalProgressableIter;
+    /// for (_state, _num) in (0..1_000_000).optional_progress(1_000).set_terminal_title(10) {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn set_terminal_title(self, every: usize) -> Self {
+        let mut res = self;
+        res.terminal_title = if io::stdout().is_terminal() {
+            Some(TerminalTitle {
+                every: every.max(1),
+                set: false,
+            })
+        } else {
+            None
+        };
+        res
+    }
+
+    /// Call `callback` if the underlying iterator's `.next()` hasn't returned within `timeout`,
+    /// with the (0-based) index of the item it's stuck on, and how long it has been waiting.
+    /// It's called (at most) once per item. Use this to notice when an upstream source (e.g. a
+    /// network service) has stalled.
+    ///
+    /// This is checked on a background thread, and the callback is called from it. It can't
+    /// interrupt the stuck `.next()`, iteration carries on if it ever returns.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # use std::time::Duration;
+    /// let progressor = (0..10)
+    ///     .progress()
+    ///     .on_item_timeout(Duration::from_secs(60), |index, elapsed| {
+    ///         eprintln!("Item {} has taken {:?} so far", index, elapsed);
+    ///     });
+    /// for (_state, _num) in progressor {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn on_item_timeout<F>(self, timeout: Duration, callback: F) -> Self
+    where
+        F: FnMut(usize, Duration) + Send + 'static,
+    {
+        let mut res = self;
+        res.watchdog = Some(watchdog::Watchdog::new(timeout, callback));
+        res
+    }
+
+    /// Call `f` with the record whenever `trigger` fires, i.e. after enough items and/or time
+    /// since it was last called (or the start). Only one callback is kept.
+    ///
+    /// ```
+    /// # use iter_progress::{ProgressableIter, Trigger};
+    /// let progressor = (0..1_000)
+    ///     .progress()
+    ///     .do_every(Trigger::items(100), |state| eprintln!("{} done", state.num_done()));
+    /// for (_state, _num) in progressor {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn do_every<F>(self, trigger: Trigger, f: F) -> Self
+    where
+        F: FnMut(&ProgressRecord) + Send + 'static,
+    {
+        let mut res = self;
+        res.periodic = Some(trigger::Periodic::new(
+            trigger,
+            res.done,
+            res.elapsed_offset,
+            f,
+        ));
+        res
+    }
+
+    /// Call `f` with the record at most once every `every`, i.e. with the first record at least
+    /// `every` since the last call (or the start). Unlike `ProgressRecord::do_every_n_sec`, the
+    /// time of the last call is kept, so it's never called more often than that, no matter how
+    /// uneven the items are. The same as `.do_every(Trigger::duration(every), f)`.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # use std::time::Duration;
+    /// let progressor = (0..1_000)
+    ///     .progress()
+    ///     .do_every_duration(Duration::from_secs(5), |state| {
+    ///         eprintln!("{} done", state.num_done());
+    ///     });
+    /// for (_state, _num) in progressor {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn do_every_duration<F>(self, every: Duration, f: F) -> Self
+    where
+        F: FnMut(&ProgressRecord) + Send + 'static,
+    {
+        self.do_every(Trigger::duration(every), f)
+    }
+
+    /// Write a line about the progress to the `OutputSink` every `every`, from a background
+    /// thread, e.g. `" 32.5% 325 items, 12.3 items/sec, 26s elapsed"`. Unlike printing from the
+    /// loop, this carries on when the underlying iterator is slow, so the output doesn't go
+    /// silent between items. The count & rate are as of the latest item.
+    ///
+    /// The thread starts when iterating starts, and stops for good when the iterator is
+    /// exhausted, finished or dropped. A line is always a new line, even when interactive.
+    /// `every` is at least 1ms.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// # use std::time::Duration;
+    /// let progressor = (0..3)
+    ///     .progress()
+    ///     .heartbeat_every(Duration::from_secs(30));
+    /// for (_state, _num) in progressor {
+    ///     // something slow
+    /// }
+    /// ```
+    pub fn heartbeat_every(self, every: impl Into<Option<Duration>>) -> Self {
+        let mut res = self;
+        res.heartbeat = every.into().map(|every| (every, None));
+        res
+    }
+
+    /// Write a line about the progress to `sink` whenever the process is sent `SIGUSR1` (or
+    /// `SIGINFO`, i.e. ctrl-t, on BSDs & macOS), like `dd` does, e.g.
+    /// `" 32.5% 325 items, 12.3 items/sec, 26s elapsed"`. For long jobs which don't print their
+    /// progress continuously. The signals are handled on a background thread, which stops when
+    /// this is dropped. Only on Unix, with the `signals` feature.
+    ///
+    /// ```no_run
+    /// # use iter_progress::{OutputSink, ProgressableIter};
+    /// let progressor = (0..1_000_000)
+    ///     .progress()
+    ///     .print_on_signal(OutputSink::Stderr)
+    ///     .unwrap();
+    /// for (_state, _num) in progressor {
+    ///     // `kill -USR1 <pid>` prints the progress
+    /// }
+    /// ```
+    #[cfg(all(unix, feature = "signals"))]
+    pub fn print_on_signal(self, sink: OutputSink) -> io::Result<Self> {
+        let mut res = self;
+        let handle = res.handle();
+        res.signal_printer = Some(signals::SignalPrinter::new(handle, sink)?);
+        Ok(res)
+    }
+
+    /// Stop yielding items once `token` is cancelled (checked before each item), e.g. from a
+    /// ctrl-c handler, for a graceful stop. A summary line starting with `"Cancelled"` is printed
+    /// then (see `.finish()`), so it's clear how far it got. See `CancelToken`.
+    pub fn with_cancel_token(self, token: impl Into<Option<CancelToken>>) -> Self {
+        let mut res = self;
+        res.cancel = token.into().map(|token| (token, false));
+        res
+    }
+
+    /// Whether the `CancelToken` (if any) has been cancelled, so no more items will be yielded
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|(token, _)| token.is_cancelled())
+    }
+
+    /// Get the next item from the underlying iterator with `next` (e.g. `Iterator::next`, or
+    /// from the back), keeping the watchdog (if any) informed
+    fn next_inner(
+        &mut self,
+        next: fn(&mut I) -> Option<<I as Iterator>::Item>,
+    ) -> Option<<I as Iterator>::Item> {
+        if let Some((ref token, reported)) = self.cancel {
+            if token.is_cancelled() {
+                if !reported {
+                    self.cancel = Some((token.clone(), true));
+                    self.finish_with_message("Cancelled");
+                }
+                return None;
+            }
+        }
+        if let Some(ref mut events) = self.events {
+            events.start();
+        }
+        if let Some((every, None)) = self.heartbeat {
+            let handle = self.handle();
+            let output = self.output.clone();
+            let heartbeat = heartbeat::Heartbeat::new(every, self.clock.clone(), move || {
+                output.write(format_args!("{}\n", handle.status_line()))
+            });
+            self.heartbeat = Some((every, Some(heartbeat)));
+        }
+        #[cfg(feature = "tracing")]
+        let _entered = self.tracer.as_ref().map(|tracer| tracer.span.enter());
+        match self.watchdog {
+            None => next(&mut self.iter),
+            Some(ref watchdog) => {
+                watchdog.start_item(self.count);
+                let res = next(&mut self.iter);
+                watchdog.end_item();
+                res
+            }
+        }
+    }
+
+    /// A handle to pause & resume the clock, so the time spent paused isn't counted in
+    /// `.duration_since_start()`, the rate, or the ETA. See `PauseHandle`.
+    pub fn pause_handle(&mut self) -> PauseHandle {
+        if self.pause.is_none() {
+            self.pause = Some(PauseHandle::new(self.clock.clone()));
+        }
+        self.pause.clone().unwrap()
+    }
+
+    /// A handle to watch the progress from other threads. See `ProgressHandle`.
+    pub fn handle(&mut self) -> ProgressHandle {
+        if self.handle.is_none() {
+            self.handle = Some(ProgressHandle::new(self.clock.clone()));
+        }
+        self.handle.clone().unwrap()
+    }
+
+    /// The progress of this (outer) iterator, including the progress of the inner iterator for
+    /// each item, which is added with `.with_parent(…)`. See `NestedProgress`.
+    pub fn nested(&mut self) -> NestedProgress {
+        if self.nested.is_none() {
+            self.nested = Some(NestedProgress::new(self.handle()));
+        }
+        self.nested.clone().unwrap()
+    }
+
+    /// Make this the inner iterator of `parent`'s current item, so it counts towards its
+    /// progress, and its records have `ProgressRecord::parent_fraction`. Add it after the outer
+    /// iterator has returned the item. See `NestedProgress`.
+    pub fn with_parent(self, parent: &NestedProgress) -> Self {
+        let mut res = self;
+        parent.set_child(res.handle());
+        res.parent = Some(parent.clone());
+        res
+    }
+
+    /// How long it has been paused for in total, as of `now`
+    fn paused_for(&self, now: Instant) -> Duration {
+        self.pause
+            .as_ref()
+            .map_or(Duration::ZERO, |pause| pause.paused_for(now))
+    }
+
+    /// How long it has been iterating for, as of `now`, not including any time paused
+    fn iterating_for(&self, now: Instant) -> Duration {
+        (now - self.started_iterating + self.elapsed_offset)
+            .saturating_sub(self.paused_for(now).saturating_sub(self.paused_at_start))
+    }
+
+    /// Carry on as if `count` had been done already, and we've been iterating for `elapsed`,
+    /// e.g. when resuming from a checkpoint. The next record's `.num_done()` will be `count + 1`,
+    /// and its `.duration_since_start()` will be `elapsed` plus however long it has been since
+    /// this was called. `.started_iterating()` will be when this was called.
+    ///
+    /// `count` is in the same units as `.num_done()` (i.e. items, unless using
+    /// `.progress_counting(…)`). Only that is restored: the items seen by this recorder are
+    /// still counted from where they were, so `.should_do_every_n_items(…)` and how often
+    /// records are generated (for an `OptionalProgressRecorderIter`) carry on as before.
+    ///
+    /// The rolling & exponential averages, and all other stats about the time between items,
+    /// are cleared, since the time between the last item and resuming isn't meaningful. The
+    /// sample history (if any) is kept.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// use std::time::Duration;
+    /// let mut progressor = (0..).progress();
+    /// progressor.reset_to(100, Duration::from_secs(60));
+    /// let (state, _) = progressor.next().unwrap();
+    /// assert_eq!(state.num_done(), 101);
+    /// assert!(state.duration_since_start() >= Duration::from_secs(60));
+    /// ```
+    pub fn reset_to(&mut self, count: usize, elapsed: Duration) {
+        self.done = count;
+        self.initial_total = self
+            .iter
+            .size_hint()
+            .1
+            .and_then(|upper| upper.checked_add(count));
+        self.started_iterating = self._fake_now.unwrap_or_else(|| self.clock.now());
+        self.elapsed_offset = elapsed;
+        self.previous_record_tm = None;
+        self.last_printed
+            .store(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        self.paused_at_start = self.paused_for(self.started_iterating);
+        if let Some((size, ref mut values)) = self.rolling_average {
+            *values = vec![0.; size];
+        }
+        if let Some((rate, _)) = self.exp_average {
+            self.exp_average = Some((rate, None));
+        }
+        if let Some(ref mut window) = self.recent_window {
+            window.samples.clear();
+        }
+        self.item_durations = RunningStats::default();
+        #[cfg(feature = "latency-histogram")]
+        if let Some(ref mut histogram) = self.latency_histogram {
+            *histogram = LatencyHistogram::new();
+        }
+        self.previous_recent_rate = None;
+        self.recent_rate_range = None;
+        if let Some(ref mut trailing_rates) = self.trailing_rates {
+            trailing_rates.samples.clear();
+        }
+        if let Some(ref mut periodic) = self.periodic {
+            periodic.reset_to(count, elapsed);
+        }
+        if let Some(ref mut checkpoints) = self.checkpoints {
+            checkpoints.reset_to(elapsed);
+        }
+        if let Some(ref mut sampling) = self.time_sampling {
+            *sampling = TimeSampling::new(sampling.every);
+        }
+    }
+
+    /// The total, if known, from the underlying iterator's `.size_hint()` if it's exact, or the
+    /// assumed size. Like `ProgressRecord::total`.
+    fn total(&self) -> Option<usize> {
+        let size_hint = self.iter.size_hint();
+        if self.counted.is_none() && size_hint.1 == Some(size_hint.0) {
+            Some(size_hint.0 + self.done)
+        } else {
+            self.assumed_size.or_else(|| self.initial_total())
+        }
+    }
+
+    /// The total from the underlying iterator's upper bound when we started, if counting items.
+    /// Unlike the live `.size_hint()`, this doesn't drift as the iterator is used up.
+    fn initial_total(&self) -> Option<usize> {
+        if self.counted.is_none() {
+            self.initial_total
+        } else {
+            None
+        }
+    }
+
+    /// Advance `done` for the item just taken from the underlying iterator.
+    #[inline]
+    fn count_item(&mut self) {
+        self.done += match self.counted {
+            None => 1,
+            Some(ref counted) => counted.swap(0, Ordering::Relaxed),
+        };
+    }
+
+    /// Calculate the current `ProgressRecord` for where we are now.
+    fn generate_record(&mut self, fake_now: Option<Instant>) -> Option<ProgressRecord> {
+        self.count += 1;
+        if self.count % self.generate_every_count != 0 {
+            return None;
+        }
+        if let Some((step, last_milestone)) = self.milestones {
+            let total = match self.total() {
+                Some(total) if total > 0 => total,
+                _ => return None,
+            };
+            // A little slack, so floating point errors don't skip a milestone
+            let milestone = ((self.done as f64 / total as f64) / step + 1e-9).floor() as usize;
+            if milestone <= last_milestone {
+                return None;
+            }
+            self.milestones = Some((step, milestone));
+        }
+
+        let now = match (fake_now, &mut self.time_sampling) {
+            (Some(fake_now), _) => fake_now,
+            (None, Some(sampling)) => sampling.now(self.count, &*self.clock),
+            (None, None) => self.clock.now(),
+        };
+
+        // Paused time is left out, by acting like the start, and the previous record, happened
+        // that much later
+        let paused = self.paused_for(now);
+        let iterating_for = self.iterating_for(now);
+        let previous_record_tm = self
+            .previous_record_tm
+            .map(|previous_tm| previous_tm + paused.saturating_sub(self.paused_at_previous));
+        let unpaused_now = now.checked_sub(paused).unwrap_or(now);
+
+        if let Some(ref mut history) = self.history {
+            history.add((iterating_for, self.done));
+        }
+
+        if self
+            .record_limit
+            .is_some_and(|limit| self.num_records >= limit)
+        {
+            return None;
+        }
+        self.num_records += 1;
+
+        if let Some(previous_tm) = previous_record_tm {
+            self.item_durations.add((now - previous_tm).as_secs_f64());
+            #[cfg(feature = "latency-histogram")]
+            if let Some(ref mut histogram) = self.latency_histogram {
+                histogram.add(now - previous_tm);
+            }
+        }
+
+        let exp_average_rate = if let Some((rate, last)) = self.exp_average {
+            if let Some(previous_tm) = previous_record_tm {
+                let this_duration = now - previous_tm;
+                let current_ema = match last {
+                    None => this_duration,
+                    Some(last) => this_duration.mul_f64(rate) + last.mul_f64(1. - rate),
+                };
+                self.exp_average = Some((rate, Some(current_ema)));
+                Some(current_ema)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        let rolling_average_duration = match &mut self.rolling_average {
+            None => None,
+            Some((size, values)) => {
+                if let Some(previous_tm) = previous_record_tm {
+                    let this_duration = (now - previous_tm).as_secs_f64();
+                    // How many durations there have been, including this one
+                    let num_durations = self.item_durations.n;
+                    values[(num_durations - 1) % *size] = this_duration;
+                    if num_durations < *size {
+                        // We haven't filled up the buffer yet
+                        Some(Duration::from_secs_f64(
+                            values[0..num_durations].iter().sum::<f64>() / (num_durations as f64),
+                        ))
+                    } else {
+                        Some(Duration::from_secs_f64(
+                            values.iter().sum::<f64>() / (*size as f64),
+                        ))
+                    }
+                } else {
+                    None
+                }
+            }
+        };
+
+        let recent_window_rate = match self.recent_window {
+            Some(ref mut window) => window.add(unpaused_now, self.done),
+            None => None,
+        };
+        if let Some(ref mut trailing_rates) = self.trailing_rates {
+            trailing_rates.add(unpaused_now, self.done);
+        }
+
+        let recent_rate = recent_window_rate
+            .or_else(|| {
+                rolling_average_duration
+                    .or(exp_average_rate)
+                    .map(|d| 1. / d.as_secs_f64())
+            })
+            .filter(|r| r.is_finite());
+        let acceleration = match (self.previous_recent_rate, recent_rate) {
+            (Some((previous_tm, previous_rate)), Some(rate)) if unpaused_now > previous_tm => {
+                Some((rate - previous_rate) / (unpaused_now - previous_tm).as_secs_f64())
+            }
+            _ => None,
+        };
+        if let Some(rate) = recent_rate {
+            self.previous_recent_rate = Some((unpaused_now, rate));
+            self.recent_rate_range = Some(match self.recent_rate_range {
+                None => (rate, rate),
+                Some((trough, peak)) => (trough.min(rate), peak.max(rate)),
+            });
+        }
+
+        // This is built eagerly, for every record, even if nothing looks at it: the clock has
+        // been read above (unless `sample_time_every` is set), and the settings are copied in.
+        // That's 2 `Arc` clones (`last_printed` & `clock`), plus the `template`, `label`,
+        // `phase_rates` & a custom `output`, when set. Only the derived values (rate, ETA, etc.)
+        // are computed lazily, in the accessors.
+        let mut res = ProgressRecord {
+            num: self.done,
+            num_items: self.count,
+            iterating_for,
+            size_hint: self.iter.size_hint(),
+            counting_items: self.counted.is_none(),
+            assumed_size: self.assumed_size,
+            initial_total: self.initial_total(),
+            assumed_fraction: None,
+            started_iterating: self.started_iterating,
+            tm: now,
+            previous_record_tm,
+            rolling_average_duration,
+            exp_average_duration: exp_average_rate,
+            item_durations: self.item_durations,
+            acceleration,
+            recent_window_rate,
+            recent_rate_range: self.recent_rate_range,
+            unicode: self.unicode,
+            template: self.template.clone(),
+            label: self.label.clone(),
+            parent_fraction: None,
+            interactive: self.interactive,
+            num_retries: self.num_retries,
+            num_failed: self.num_failed,
+            baseline_rate: self.baseline_rate,
+            deadline: self.deadline,
+            phase_rates: self.phase_rates.clone(),
+            eta_strategy: self.eta_strategy,
+            output: self.output.clone(),
+            last_printed: self.last_printed.clone(),
+            clock: SharedClock(self.clock.clone()),
+        };
+
+        self.previous_record_tm = Some(now);
+        self.paused_at_previous = paused;
+
+        if let Some(ref handle) = self.handle {
+            handle.update(&res);
+        }
+        if let Some(ref parent) = self.parent {
+            // After the handle, so this record is included
+            res.parent_fraction = parent.fraction();
+        }
+        if let Some(ref mut periodic) = self.periodic {
+            periodic.maybe_call(&res);
+        }
+
+        let csv_failed = match self.csv_log {
+            Some(ref mut csv_log) if csv_log.is_due(self.num_records, &res) => {
+                csv_log.write_row(&res).is_err()
+            }
+            _ => false,
+        };
+        if csv_failed {
+            self.csv_log = None;
+        }
+
+        if let Some((ref sink, every)) = self.json_lines {
+            if res.should_do_every_n_sec(every.as_secs_f32()) {
+                sink.write(format_args!("{}\n", res.json_line()));
+            }
+        }
+
+        if let Some(ref events) = self.events {
+            events.update(&res);
+        }
+
+        if let Some(ref mut checkpoints) = self.checkpoints {
+            checkpoints.update(&res);
+        }
+
+        if let Some(ref mut title) = self.terminal_title {
+            title.update(self.num_records, &res);
+        }
+
+        #[cfg(feature = "opentelemetry")]
+        {
+            if let Some(ref mut otel) = self.otel {
+                otel.update(self.num_records, &res);
+            }
+        }
+
+        #[cfg(feature = "prometheus")]
+        {
+            if let Some(ref mut prometheus) = self.prometheus {
+                prometheus.update(self.num_records, &res);
+            }
+        }
+
+        #[cfg(feature = "log")]
+        {
+            if let Some(ref logger) = self.logger {
+                logger.update(&res);
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            if let Some(ref tracer) = self.tracer {
+                tracer.update(&res);
+            }
+        }
+
+        Some(res)
+    }
+
+    /// Finish up, returning a final record of everything done so far (the total count, the time
+    /// taken, & the average rate), and printing a closing line to the `OutputSink`, e.g.
+    /// `"Done: 1,000 items in 12s, 83.3 items/sec"`. When interactive, this replaces the line
+    /// drawn by `.print_progress_every_n_sec(…)`.
+    ///
+    /// This works after breaking out of a loop too, unlike waiting for the last record.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressableIter;
+    /// let mut progressor = (0..100).progress();
+    /// for (state, num) in progressor.by_ref() {
+    ///     state.print_progress_every_n_sec(1.);
+    ///     if num == 9 {
+    ///         break;
+    ///     }
+    /// }
+    /// let summary = progressor.finish();
+    /// assert_eq!(summary.num_done(), 10);
+    /// ```
+    pub fn finish(&mut self) -> ProgressRecord {
+        self.finish_with_message("Done")
+    }
+
+    /// Like `.finish()`, but the closing line starts with `msg`, rather than `"Done"`.
+    pub fn finish_with_message(&mut self, msg: impl std::fmt::Display) -> ProgressRecord {
+        let now = std::mem::take(&mut self._fake_now).unwrap_or_else(|| self.clock.now());
+        let record = self.summary_record(now);
+        let line = record.labelled(format!("{}: {}", msg, record.summary_line()));
+        if self.interactive {
+            // Clear the rest of the line, in case the progress line was longer
+            self.output.write(format_args!("\r{}\x1b[K\n", line));
+        } else {
+            self.output.write(format_args!("{}\n", line));
+        }
+        if let Some(events) = self.events.take() {
+            events.finish(record.clone());
+        }
+        self.finished();
+        record
+    }
+
+    /// A record of everything done, as of `now`, without the stats about recent items
+    fn summary_record(&self, now: Instant) -> ProgressRecord {
+        ProgressRecord {
+            num: self.done,
+            num_items: self.count,
+            iterating_for: self.iterating_for(now),
+            size_hint: self.iter.size_hint(),
+            counting_items: self.counted.is_none(),
+            assumed_size: self.assumed_size,
+            initial_total: self.initial_total(),
+            assumed_fraction: None,
+            started_iterating: self.started_iterating,
+            tm: now,
+            previous_record_tm: self.previous_record_tm,
+            rolling_average_duration: None,
+            exp_average_duration: None,
+            item_durations: self.item_durations,
+            acceleration: None,
+            recent_window_rate: None,
+            recent_rate_range: self.recent_rate_range,
+            unicode: self.unicode,
+            template: self.template.clone(),
+            label: self.label.clone(),
+            parent_fraction: self.parent.as_ref().and_then(|parent| parent.fraction()),
+            interactive: self.interactive,
+            num_retries: self.num_retries,
+            num_failed: self.num_failed,
+            baseline_rate: self.baseline_rate,
+            deadline: self.deadline,
+            phase_rates: self.phase_rates.clone(),
+            eta_strategy: self.eta_strategy,
+            output: self.output.clone(),
+            last_printed: self.last_printed.clone(),
+            clock: SharedClock(self.clock.clone()),
+        }
+    }
+
+    /// Called when the underlying iterator has ended
+    fn finished(&mut self) {
+        if let Some(checkpoints) = self.checkpoints.take() {
+            checkpoints.finish(&self.summary_record(self.clock.now()));
+        }
+        if let Some(events) = self.events.take() {
+            events.finish(self.summary_record(self.clock.now()));
+        }
+        #[cfg(feature = "opentelemetry")]
+        {
+            if let Some(mut otel) = self.otel.take() {
+                otel.finish(&self.summary_record(self.clock.now()));
+            }
+        }
+        #[cfg(feature = "prometheus")]
+        {
+            if let Some(mut prometheus) = self.prometheus.take() {
+                prometheus.finish(&self.summary_record(self.clock.now()));
+            }
+        }
+        if let Some(ref handle) = self.handle {
+            handle.finish();
+        }
+        // Stops the thread, and it isn't restarted by any later `.next()`
+        self.heartbeat = None;
+        if let Some(ref mut title) = self.terminal_title {
+            title.clear();
+        }
+
+        // Close the span
+        #[cfg(feature = "tracing")]
+        {
+            self.tracer = None;
+        }
+    }
+
+    /// Returns referend to the inner iterator
+    pub fn inner(&self) -> &I {
+        &self.iter
+    }
+
+    /// Gets the original iterator back, consuming this.
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+
+    #[cfg(test)]
+    fn set_fake_now(&mut self, fake_now: impl Into<Option<Instant>>) {
+        self._fake_now = fake_now.into();
+    }
+}
+
+pub trait OptionalProgressableIter<I: Iterator> {
+    fn optional_progress(self, generate_every_count: usize) -> OptionalProgressRecorderIter<I>;
+
+    /// Only generate records at milestones, i.e. every `step` of the way through.
+    fn at_milestones(self, step: f64) -> OptionalProgressRecorderIter<I>;
+}
+
+impl<I> OptionalProgressableIter<I> for I
+where
+    I: Iterator,
+{
+    /// Convert an iterator into an `OptionalProgressRecorderIter`.
+    fn optional_progress(self, generate_every_count: usize) -> OptionalProgressRecorderIter<I> {
+        OptionalProgressRecorderIter::new(self, generate_every_count)
+    }
+
+    /// Convert an iterator into an `OptionalProgressRecorderIter` which only generates a record
+    /// for the items which cross a milestone, i.e. a multiple of `step` through the iterator. e.g.
+    /// with a `step` of `0.1`, there's a record at 10%, 20%, …, 100%. The time isn't looked up
+    /// for any other item.
+    ///
+    /// The total must be known (from the `.size_hint()`, or with `.assume_size(…)`), otherwise
+    /// no records are generated.
+    ///
+    /// # Panics
+    ///
+    /// If `step` isn't more than 0, and at most 1.
+    ///
+    /// ```
+    /// # use iter_progress::OptionalProgressableIter;
+    /// for (state, _val) in (0..1_000).at_milestones(0.1) {
+    ///     if let Some(state) = state {
+    ///         println!("{}% done", state.percent().unwrap());
+    ///     }
+    /// }
+    /// ```
+    fn at_milestones(self, step: f64) -> OptionalProgressRecorderIter<I> {
+        assert!(
+            step > 0. && step <= 1.,
+            "step must be more than 0, and at most 1, not {}",
+            step
+        );
+        let mut res = OptionalProgressRecorderIter::new(self, 1);
+        res.milestones = Some((step, 0));
+        res
+    }
+}
+
+impl<I: Iterator> Iterator for OptionalProgressRecorderIter<I> {
+    type Item = (Option<ProgressRecord>, <I as Iterator>::Item);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with(Iterator::next)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.is_cancelled() {
+            (0, Some(0))
+        } else {
+            self.iter.size_hint()
+        }
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        if self.is_cancelled() {
+            0
+        } else {
+            self.iter.count()
+        }
+    }
+}
+
+impl<I: Iterator> OptionalProgressRecorderIter<I> {
+    /// The next item & record (if any), getting the item with `next`
+    fn next_with(
+        &mut self,
+        next: fn(&mut I) -> Option<<I as Iterator>::Item>,
+    ) -> Option<(Option<ProgressRecord>, <I as Iterator>::Item)> {
+        let fake_now = std::mem::take(&mut self._fake_now);
+        let res = self.next_inner(next).map(|a| {
+            self.count_item();
+            (self.generate_record(fake_now), a)
+        });
+        if res.is_none() {
+            self.finished();
+        }
+        res
+    }
+}
+
+impl<I: ExactSizeIterator> ExactSizeIterator for OptionalProgressRecorderIter<I> {}
+
+impl<I: FusedIterator> FusedIterator for OptionalProgressRecorderIter<I> {}
+
+impl<I: DoubleEndedIterator> DoubleEndedIterator for OptionalProgressRecorderIter<I> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.next_with(DoubleEndedIterator::next_back)
+    }
+}
+
+/// Wraps an iterator of `Result`s, retrying on `Err`, and yielding the `Ok` values with their
+/// `ProgressRecord`. Created with `.progress_retry(…)`.
+pub struct RetryingProgressRecorderIter<I: Iterator> {
+    recorder: OptionalProgressRecorderIter<I>,
+    max_retries: usize,
+}
+
+/// An iterator of `Result`s that records its progress, and retries errors.
+pub trait RetryProgressableIter<I: Iterator> {
+    fn progress_retry(self, max_retries: usize) -> RetryingProgressRecorderIter<I>;
+}
+
+impl<I, T, E> RetryProgressableIter<I> for I
+where
+    I: Iterator<Item = Result<T, E>>,
+{
+    /// Record progress of an iterator of `Result`s, retrying every `Err` up to `max_retries`
+    /// times.
+    ///
+    /// A "retry" is calling `.next()` on the underlying iterator again, so this is for iterators
+    /// which try the same thing again after returning an `Err` (e.g. reading from a flaky network
+    /// connection). There is no delay or backoff between retries, if you want that, sleep in the
+    /// underlying iterator before returning the `Err`. If an item is still `Err` after
+    /// `max_retries` retries, it is counted as failed, the error is dropped, and the next item is
+    /// tried.
+    ///
+    /// Only `Ok` items are returned, and counted in `.num_done()`. The record's `.num_retries()` &
+    /// `.num_failed()` say how many errors there have been. Since every `Err` uses up one of the
+    /// underlying iterator's items, they count towards the `.fraction()` (and so the ETA) too, so
+    /// it reaches 1 at the end, even if some items failed.
+    ///
+    /// ```
+    /// # use iter_progress::RetryProgressableIter;
+    /// let results: Vec<Result<u8, ()>> = vec![Ok(1), Err(()), Ok(2), Err(()), Err(()), Ok(3)];
+    /// let mut progressor = results.into_iter().progress_retry(1);
+    ///
+    /// let (state, val) = progressor.next().unwrap();
+    /// assert_eq!((val, state.num_done(), state.num_retries(), state.num_failed()), (1, 1, 0, 0));
+    /// // Retried once & worked
+    /// let (state, val) = progressor.next().unwrap();
+    /// assert_eq!((val, state.num_done(), state.num_retries(), state.num_failed()), (2, 2, 1, 0));
+    /// // Retried once & failed again, so skipped
+    /// let (state, val) = progressor.next().unwrap();
+    /// assert_eq!((val, state.num_done(), state.num_retries(), state.num_failed()), (3, 3, 2, 1));
+    /// ```
+    fn progress_retry(self, max_retries: usize) -> RetryingProgressRecorderIter<I> {
+        RetryingProgressRecorderIter {
+            recorder: OptionalProgressRecorderIter::new(self, 1),
+            max_retries,
+        }
+    }
+}
+
+impl<I: Iterator> RetryingProgressRecorderIter<I> {
+    /// Returns reference to the inner iterator
+    pub fn inner(&self) -> &I {
+        self.recorder.inner()
+    }
+
+    /// Gets the original iterator back, consuming this.
+    pub fn into_inner(self) -> I {
+        self.recorder.into_inner()
+    }
+
+    /// Finish up, returning a final record, incl. any items which failed after the last one
+    /// returned. See `OptionalProgressRecorderIter::finish`.
+    pub fn finish(&mut self) -> ProgressRecord {
+        self.recorder.finish()
+    }
+}
+
+impl<I, T, E> Iterator for RetryingProgressRecorderIter<I>
+where
+    I: Iterator<Item = Result<T, E>>,
+{
+    type Item = (ProgressRecord, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut retries = 0;
+        loop {
+            let res = match self.recorder.next_inner(Iterator::next) {
+                Some(res) => res,
+                None => {
+                    self.recorder.finished();
+                    return None;
+                }
+            };
+            match res {
+                Ok(item) => {
+                    self.recorder.done += 1;
+                    let fake_now = std::mem::take(&mut self.recorder._fake_now);
+                    // we know there is always a record generated
+                    return Some((self.recorder.generate_record(fake_now).unwrap(), item));
+                }
+                Err(_) if retries < self.max_retries => {
+                    retries += 1;
+                    self.recorder.num_retries += 1;
+                }
+                Err(_) => {
+                    retries = 0;
+                    self.recorder.num_failed += 1;
+                }
+            }
+        }
+    }
+
+    /// Any item could fail, so there's no lower bound
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.recorder.size_hint().1)
+    }
+}
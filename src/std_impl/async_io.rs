@@ -0,0 +1,162 @@
+//! Progress for `tokio` `AsyncRead`s & `AsyncWrite`s.
+
+use super::bytes::ByteProgress;
+use super::ProgressRecord;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+pin_project_lite::pin_project! {
+    /// Wraps a `tokio` `AsyncRead`, and records the progress in bytes read.
+    ///
+    /// Only available with the `tokio` feature.
+    ///
+    /// ```
+    /// # extern crate tokio;
+    /// # extern crate iter_progress;
+    /// # use iter_progress::ProgressAsyncReader;
+    /// # use tokio::io::AsyncReadExt;
+    /// # let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// let data = [0u8; 100];
+    /// let mut reader = ProgressAsyncReader::new(&data[..], data.len());
+    /// let mut buf = [0u8; 40];
+    /// runtime.block_on(reader.read_exact(&mut buf)).unwrap();
+    /// assert_eq!(reader.record().unwrap().fraction(), Some(0.4));
+    /// ```
+    pub struct ProgressAsyncReader<R> {
+        #[pin]
+        inner: R,
+        progress: ByteProgress,
+    }
+}
+
+impl<R> ProgressAsyncReader<R> {
+    /// Wrap `inner`. `total` is the number of bytes expected, if known, for the fraction & ETA.
+    pub fn new(inner: R, total: impl Into<Option<usize>>) -> Self {
+        ProgressAsyncReader {
+            inner,
+            progress: ByteProgress::new(total.into()),
+        }
+    }
+
+    /// The progress as of the latest read, counted in bytes. `None` if nothing has been read
+    /// yet.
+    pub fn record(&self) -> Option<&ProgressRecord> {
+        self.progress.record()
+    }
+
+    /// Returns a reference to the inner reader
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets the inner reader back, consuming this.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for ProgressAsyncReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let before = buf.filled().len();
+        let res = this.inner.poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = res {
+            this.progress.add(buf.filled().len() - before);
+        }
+        res
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// Wraps a `tokio` `AsyncWrite`, and records the progress in bytes written.
+    ///
+    /// Only available with the `tokio` feature.
+    ///
+    /// ```
+    /// # extern crate tokio;
+    /// # extern crate iter_progress;
+    /// # use iter_progress::ProgressAsyncWriter;
+    /// # use tokio::io::AsyncWriteExt;
+    /// # let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// let mut writer = ProgressAsyncWriter::new(Vec::new(), 100);
+    /// runtime.block_on(writer.write_all(&[0u8; 40])).unwrap();
+    /// assert_eq!(writer.record().unwrap().fraction(), Some(0.4));
+    /// ```
+    pub struct ProgressAsyncWriter<W> {
+        #[pin]
+        inner: W,
+        progress: ByteProgress,
+    }
+}
+
+impl<W> ProgressAsyncWriter<W> {
+    /// Wrap `inner`. `total` is the number of bytes expected, if known, for the fraction & ETA.
+    pub fn new(inner: W, total: impl Into<Option<usize>>) -> Self {
+        ProgressAsyncWriter {
+            inner,
+            progress: ByteProgress::new(total.into()),
+        }
+    }
+
+    /// The progress as of the latest write, counted in bytes. `None` if nothing has been
+    /// written yet.
+    pub fn record(&self) -> Option<&ProgressRecord> {
+        self.progress.record()
+    }
+
+    /// Returns a reference to the inner writer
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets the inner writer back, consuming this.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: AsyncWrite> AsyncWrite for ProgressAsyncWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let res = this.inner.poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = res {
+            this.progress.add(n);
+        }
+        res
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let res = this.inner.poll_write_vectored(cx, bufs);
+        if let Poll::Ready(Ok(n)) = res {
+            this.progress.add(n);
+        }
+        res
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+}
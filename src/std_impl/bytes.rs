@@ -0,0 +1,293 @@
+//! Progress counted in bytes, for readers & writers.
+
+use super::{Counting, ProgressRecord, ProgressRecorderIter, ProgressableIter};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+/// Yields the size of the latest read or write, forever
+pub(crate) struct ByteCounts {
+    latest: usize,
+
+    /// How many bytes are left of the expected total, if known
+    remaining: Option<usize>,
+}
+
+impl Iterator for ByteCounts {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        Some(self.latest)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.remaining {
+            // So the fraction can reach 1
+            Some(0) => (0, Some(0)),
+            _ => (0, None),
+        }
+    }
+}
+
+/// Counts each `ByteCounts` item as that many bytes
+type ByteCounting = Counting<ByteCounts, fn(&usize) -> usize>;
+
+/// Records the progress of a reader or writer, in bytes
+pub(crate) struct ByteProgress {
+    recorder: ProgressRecorderIter<ByteCounting>,
+
+    /// The record for the latest read or write
+    last: Option<ProgressRecord>,
+}
+
+impl ByteProgress {
+    pub(crate) fn new(total: Option<usize>) -> ByteProgress {
+        let bytes: fn(&usize) -> usize = |n| *n;
+        ByteProgress {
+            recorder: ByteCounts {
+                latest: 0,
+                remaining: total,
+            }
+            .progress_counting(bytes)
+            .assume_size(total),
+            last: None,
+        }
+    }
+
+    /// `n` more bytes have been read or written
+    pub(crate) fn add(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        let counts = &mut self.recorder.0.iter.iter;
+        counts.latest = n;
+        counts.remaining = counts
+            .remaining
+            .map(|remaining| remaining.saturating_sub(n));
+        self.last = self.recorder.next().map(|(record, _)| record);
+    }
+
+    pub(crate) fn record(&self) -> Option<&ProgressRecord> {
+        self.last.as_ref()
+    }
+}
+
+/// Wraps a `Read`, and records the progress in bytes read.
+///
+/// ```
+/// # use iter_progress::ProgressReader;
+/// # use std::io::Read;
+/// let data = [0u8; 100];
+/// let mut reader = ProgressReader::new(&data[..], data.len());
+/// let mut buf = [0u8; 40];
+/// reader.read_exact(&mut buf).unwrap();
+/// let state = reader.record().unwrap();
+/// assert_eq!(state.num_done(), 40);
+/// assert_eq!(state.fraction(), Some(0.4));
+/// ```
+///
+/// For a file, the total can come from its metadata:
+///
+/// ```no_run
+/// # use iter_progress::ProgressReader;
+/// # use std::io::BufRead;
+/// # fn main() -> std::io::Result<()> {
+/// let file = std::fs::File::open("big.csv")?;
+/// let len = file.metadata()?.len() as usize;
+/// let mut reader = std::io::BufReader::new(ProgressReader::new(file, len));
+/// let mut line = String::new();
+/// while reader.read_line(&mut line)? > 0 {
+///     // parse the line…
+///     line.clear();
+///     if let Some(state) = reader.get_ref().record() {
+///         state.print_progress_every_n_sec(1.);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct ProgressReader<R> {
+    inner: R,
+    progress: ByteProgress,
+}
+
+impl<R> ProgressReader<R> {
+    /// Wrap `inner`. `total` is the number of bytes expected, if known, for the fraction & ETA.
+    pub fn new(inner: R, total: impl Into<Option<usize>>) -> Self {
+        ProgressReader {
+            inner,
+            progress: ByteProgress::new(total.into()),
+        }
+    }
+
+    /// The progress as of the latest read, counted in bytes. `None` if nothing has been read
+    /// yet.
+    pub fn record(&self) -> Option<&ProgressRecord> {
+        self.progress.record()
+    }
+
+    /// Returns a reference to the inner reader
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets the inner reader back, consuming this.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.add(n);
+        Ok(n)
+    }
+}
+
+/// Bytes are counted when they're consumed.
+impl<R: BufRead> BufRead for ProgressReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.progress.add(amt);
+    }
+}
+
+/// Wraps a `Write`, and records the progress in bytes written.
+///
+/// ```
+/// # use iter_progress::ProgressWriter;
+/// # use std::io::Write;
+/// let mut writer = ProgressWriter::new(Vec::new(), 100);
+/// writer.write_all(&[0u8; 40]).unwrap();
+/// let state = writer.record().unwrap();
+/// assert_eq!(state.num_done(), 40);
+/// assert_eq!(state.fraction(), Some(0.4));
+/// ```
+pub struct ProgressWriter<W> {
+    inner: W,
+    progress: ByteProgress,
+}
+
+impl<W> ProgressWriter<W> {
+    /// Wrap `inner`. `total` is the number of bytes expected, if known, for the fraction & ETA.
+    pub fn new(inner: W, total: impl Into<Option<usize>>) -> Self {
+        ProgressWriter {
+            inner,
+            progress: ByteProgress::new(total.into()),
+        }
+    }
+
+    /// The progress as of the latest write, counted in bytes. `None` if nothing has been
+    /// written yet.
+    pub fn record(&self) -> Option<&ProgressRecord> {
+        self.progress.record()
+    }
+
+    /// Returns a reference to the inner writer
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets the inner writer back, consuming this.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ProgressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.progress.add(n);
+        Ok(n)
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        let n = self.inner.write_vectored(bufs)?;
+        self.progress.add(n);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Iterates over the lines of a `BufRead` (e.g. a file), like `BufRead::lines`, with the
+/// progress counted in bytes, so the fraction & ETA come from how far through the file it is,
+/// rather than an (unknown) number of lines. Yields `(ProgressRecord, line)`, with the line
+/// ending removed.
+///
+/// ```no_run
+/// # use iter_progress::ProgressLines;
+/// # fn main() -> std::io::Result<()> {
+/// for line in ProgressLines::open("big.csv")? {
+///     let (state, line) = line?;
+///     // parse the line…
+///     state.print_progress_every_n_sec(1.);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct ProgressLines<R> {
+    reader: ProgressReader<R>,
+}
+
+impl ProgressLines<BufReader<File>> {
+    /// Open the file at `path`, using its length as the total.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        Ok(ProgressLines::new(BufReader::new(file), len))
+    }
+}
+
+impl<R: BufRead> ProgressLines<R> {
+    /// The lines of `reader`. `total` is its length in bytes, if known.
+    ///
+    /// ```
+    /// # use iter_progress::ProgressLines;
+    /// let text = "hello\nworld\n";
+    /// let mut lines = ProgressLines::new(text.as_bytes(), text.len());
+    /// let (state, line) = lines.next().unwrap().unwrap();
+    /// assert_eq!(line, "hello");
+    /// assert_eq!(state.fraction(), Some(0.5));
+    /// ```
+    pub fn new(reader: R, total: impl Into<Option<usize>>) -> Self {
+        ProgressLines {
+            reader: ProgressReader::new(reader, total),
+        }
+    }
+
+    /// Gets the inner reader back, consuming this.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+}
+
+impl<R: BufRead> Iterator for ProgressLines<R> {
+    type Item = io::Result<(ProgressRecord, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                // Something was read, so there is a record
+                let record = self.reader.record().cloned()?;
+                Some(Ok((record, line)))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
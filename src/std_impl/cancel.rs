@@ -0,0 +1,43 @@
+//! Stopping iteration early, from anywhere.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Cancels a `ProgressRecorderIter`, set with `OptionalProgressRecorderIter::with_cancel_token`.
+/// Once cancelled, it stops yielding items (after the current one), and prints a summary line
+/// starting with `"Cancelled"` (see `.finish()`). Clones cancel the same iterators, so one can be
+/// moved into e.g. a ctrl-c handler.
+///
+/// ```
+/// # use iter_progress::{CancelToken, ProgressableIter};
+/// let token = CancelToken::new();
+/// let progressor = (0..100).progress().with_cancel_token(token.clone());
+/// let mut done = 0;
+/// for (_state, num) in progressor {
+///     done += 1;
+///     if num == 9 {
+///         // e.g. from another thread
+///         token.cancel();
+///     }
+/// }
+/// assert_eq!(done, 10);
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Stop the iterators using this, after their current item
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    /// Whether `.cancel()` has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
@@ -0,0 +1,156 @@
+//! Saving the progress to a file, to resume from after a restart.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::ProgressRecord;
+
+/// How far a job had got, saved with `OptionalProgressRecorderIter::save_checkpoints`, so it can
+/// carry on from there after a restart, with `.resume_from(…)`, and the rate & ETA still
+/// account for the work done before.
+///
+/// It's stored as a small text file, e.g.:
+///
+/// ```text
+/// count=1234
+/// elapsed_secs=56.7
+/// total=10000
+/// ```
+///
+/// ```
+/// # use iter_progress::{Checkpoint, ProgressableIter};
+/// # use std::time::Duration;
+/// # let path = std::env::temp_dir().join("iter_progress_checkpoint_doctest");
+/// # let _ = std::fs::remove_file(&path);
+/// let checkpoint = Checkpoint::load(&path).unwrap();
+/// let start = checkpoint.map_or(0, |c| c.count);
+/// let progressor = (0..1_000)
+///     .skip(start)
+///     .progress()
+///     .resume_from(checkpoint)
+///     .save_checkpoints(&path, Duration::from_secs(10));
+/// for (_state, _num) in progressor {
+///     // ...
+/// }
+/// let checkpoint = Checkpoint::load(&path).unwrap().unwrap();
+/// assert_eq!(checkpoint.count, 1_000);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// How much was done, like `ProgressRecord::num_done`
+    pub count: usize,
+
+    /// How long it had been iterating for
+    pub elapsed: Duration,
+
+    /// The total, if known
+    pub total: Option<usize>,
+}
+
+impl Checkpoint {
+    /// The checkpoint for a record
+    pub fn from_record(record: &ProgressRecord) -> Checkpoint {
+        Checkpoint {
+            count: record.num_done(),
+            elapsed: record.duration_since_start(),
+            total: record.total(),
+        }
+    }
+
+    /// Write this to `path`. A temporary file is written, then renamed, so a crash while saving
+    /// doesn't leave a broken checkpoint.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut contents = format!(
+            "count={}\nelapsed_secs={}\n",
+            self.count,
+            self.elapsed.as_secs_f64()
+        );
+        if let Some(total) = self.total {
+            contents.push_str(&format!("total={}\n", total));
+        }
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Read the checkpoint saved at `path`, or `None` if there isn't one (i.e. it's the first
+    /// run).
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Option<Checkpoint>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        let (mut count, mut elapsed, mut total) = (None, None, None);
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("checkpoint line isn't key=value"))?;
+            let value = value.trim();
+            match key.trim() {
+                "count" => count = Some(value.parse().map_err(|_| invalid("invalid count"))?),
+                "elapsed_secs" => {
+                    let secs = value
+                        .parse::<f64>()
+                        .map_err(|_| invalid("invalid elapsed_secs"))?;
+                    let secs = Duration::try_from_secs_f64(secs)
+                        .map_err(|_| invalid("invalid elapsed_secs"))?;
+                    elapsed = Some(secs);
+                }
+                "total" => total = Some(value.parse().map_err(|_| invalid("invalid total"))?),
+                _ => {}
+            }
+        }
+        Ok(Some(Checkpoint {
+            count: count.ok_or_else(|| invalid("checkpoint has no count"))?,
+            elapsed: elapsed.ok_or_else(|| invalid("checkpoint has no elapsed_secs"))?,
+            total,
+        }))
+    }
+}
+
+/// Saves a checkpoint at most every so often, and when finished. It's timed by the records, so
+/// uses the recorder's clock.
+pub(crate) struct CheckpointWriter {
+    path: PathBuf,
+    every: Duration,
+
+    /// `.duration_since_start()` when it was last saved, or started
+    last_saved: Duration,
+}
+
+impl CheckpointWriter {
+    pub(crate) fn new(path: PathBuf, every: Duration) -> CheckpointWriter {
+        CheckpointWriter {
+            path,
+            every,
+            last_saved: Duration::ZERO,
+        }
+    }
+
+    /// Carry on from here, e.g. after resuming
+    pub(crate) fn reset_to(&mut self, elapsed: Duration) {
+        self.last_saved = elapsed;
+    }
+
+    /// Save `record`, if it's due. Errors are ignored, and it's tried again next time, since a
+    /// (temporary) problem saving shouldn't stop the work.
+    pub(crate) fn update(&mut self, record: &ProgressRecord) {
+        let elapsed = record.duration_since_start();
+        if elapsed.saturating_sub(self.last_saved) >= self.every {
+            self.last_saved = elapsed;
+            let _ = Checkpoint::from_record(record).save(&self.path);
+        }
+    }
+
+    pub(crate) fn finish(&self, summary: &ProgressRecord) {
+        let _ = Checkpoint::from_record(summary).save(&self.path);
+    }
+}
@@ -0,0 +1,126 @@
+//! Where the time comes from.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{Instant, TickSource};
+
+/// A source of the current time, for a `ProgressRecorderIter`. Set with
+/// `OptionalProgressRecorderIter::with_clock`, e.g. to a `MockClock` in tests, so the rate &
+/// ETA can be checked without sleeping.
+pub trait Clock: Send + Sync {
+    /// The time now
+    fn now(&self) -> Instant;
+}
+
+/// A recorder's `Clock`, kept by its records too, so they tell the time the same way
+#[derive(Clone)]
+pub(crate) struct SharedClock(pub(crate) Arc<dyn Clock>);
+
+impl SharedClock {
+    pub(crate) fn now(&self) -> Instant {
+        self.0.now()
+    }
+}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedClock")
+    }
+}
+
+/// The real, monotonic, clock, i.e. `Instant::now()`. The default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock which only moves when told to, for deterministic tests. Clones share the same time,
+/// so keep one to move it forward.
+///
+/// ```
+/// # use iter_progress::{MockClock, ProgressableIter};
+/// # use std::time::Duration;
+/// let clock = MockClock::new();
+/// let mut progressor = (0..100).progress().with_clock(clock.clone());
+/// clock.advance(Duration::from_secs(2));
+/// let (state, _) = progressor.nth(9).unwrap();
+/// assert_eq!(state.duration_since_start(), Duration::from_secs(2));
+/// assert_eq!(state.rate(), 5.);
+/// assert_eq!(state.eta(), Some(Duration::from_secs(18)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockClock(Arc<Mutex<Instant>>);
+
+impl Default for MockClock {
+    fn default() -> Self {
+        MockClock::new()
+    }
+}
+
+impl MockClock {
+    /// A clock stopped at the real time now
+    pub fn new() -> MockClock {
+        MockClock(Arc::new(Mutex::new(Instant::now())))
+    }
+
+    /// Move the time forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) += duration;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A `Clock` as a `TickSource`, in nanoseconds since this was made, so a `ProgressCounter` can
+/// use it, e.g. a `MockClock` in tests.
+///
+/// ```
+/// # use iter_progress::{ClockTicks, MockClock, ProgressCounter};
+/// # use std::time::Duration;
+/// let clock = MockClock::new();
+/// let mut counter = ProgressCounter::new(ClockTicks::new(clock.clone()), 10);
+/// clock.advance(Duration::from_secs(2));
+/// counter.add(4);
+/// assert_eq!(counter.rate(), 2.);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClockTicks<C> {
+    clock: C,
+    start: Instant,
+}
+
+impl<C: Clock> ClockTicks<C> {
+    /// Count ticks of `clock` from now
+    pub fn new(clock: C) -> ClockTicks<C> {
+        ClockTicks {
+            start: clock.now(),
+            clock,
+        }
+    }
+}
+
+impl<C: Clock> TickSource for ClockTicks<C> {
+    fn ticks_per_sec(&self) -> u64 {
+        1_000_000_000
+    }
+
+    fn now(&self) -> u64 {
+        let nanos = self
+            .clock
+            .now()
+            .saturating_duration_since(self.start)
+            .as_nanos();
+        u64::try_from(nanos).unwrap_or(u64::MAX)
+    }
+}
@@ -0,0 +1,58 @@
+//! Sending the progress over a channel, as typed events.
+
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use super::ProgressRecord;
+
+/// Something which happened while iterating, sent over a channel set with
+/// `OptionalProgressRecorderIter::send_events`, so a frontend (e.g. a GUI) on another thread can
+/// show the progress.
+#[derive(Debug, Clone)]
+pub enum ProgressEvent {
+    /// Iterating has started, i.e. the first item was asked for
+    Started,
+
+    /// The latest record, at most every so often
+    Tick(ProgressRecord),
+
+    /// Iterating has ended, with a summary of everything done (see
+    /// `OptionalProgressRecorderIter::finish`)
+    Finished(ProgressRecord),
+}
+
+/// Sends `ProgressEvent`s. Errors are ignored, since the receiver going away shouldn't stop the
+/// work.
+pub(crate) struct EventSender {
+    sender: Sender<ProgressEvent>,
+    every: Duration,
+    started: bool,
+}
+
+impl EventSender {
+    pub(crate) fn new(sender: Sender<ProgressEvent>, every: Duration) -> EventSender {
+        EventSender {
+            sender,
+            every,
+            started: false,
+        }
+    }
+
+    /// Iterating is (still) happening
+    pub(crate) fn start(&mut self) {
+        if !self.started {
+            self.started = true;
+            let _ = self.sender.send(ProgressEvent::Started);
+        }
+    }
+
+    pub(crate) fn update(&self, record: &ProgressRecord) {
+        if record.should_do_every_n_sec(self.every.as_secs_f32()) {
+            let _ = self.sender.send(ProgressEvent::Tick(record.clone()));
+        }
+    }
+
+    pub(crate) fn finish(self, summary: ProgressRecord) {
+        let _ = self.sender.send(ProgressEvent::Finished(summary));
+    }
+}
@@ -0,0 +1,183 @@
+//! Observing the progress from other threads.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{format_duration, thousands_separated, Clock, Instant, ProgressRecord, SystemClock};
+
+/// A cheap, cloneable view of the progress of a `ProgressRecorderIter`, which can be polled from
+/// other threads (e.g. for a status endpoint) while the iterator is consumed elsewhere. Get one
+/// with `OptionalProgressRecorderIter::handle`.
+///
+/// It's updated every time a record is generated, with atomics, so reading it never blocks the
+/// iterating thread. Each value is updated separately, so while iterating, they can be from
+/// records a moment apart.
+///
+/// ```
+/// # use iter_progress::ProgressableIter;
+/// let mut progressor = (0..1_000).progress();
+/// let handle = progressor.handle();
+/// let worker = std::thread::spawn(move || progressor.map(|(_, num)| num).sum::<u64>());
+/// // e.g. in a web request handler
+/// println!("{} done, {:.1} items/sec", handle.num_done(), handle.rate());
+/// assert_eq!(worker.join().unwrap(), 499_500);
+/// assert_eq!(handle.num_done(), 1_000);
+/// assert_eq!(handle.fraction(), Some(1.));
+/// assert!(handle.is_finished());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProgressHandle(Arc<HandleState>);
+
+struct HandleState {
+    done: AtomicUsize,
+
+    /// `usize::MAX` if not known
+    total: AtomicUsize,
+
+    /// Bits of an `f64`, NaN if not known
+    fraction: AtomicU64,
+
+    elapsed_nanos: AtomicU64,
+    finished: AtomicBool,
+
+    /// The recorder's clock
+    clock: Arc<dyn Clock>,
+
+    /// When it was last updated, in nanoseconds since `created`
+    created: Instant,
+    updated_nanos: AtomicU64,
+}
+
+impl std::fmt::Debug for HandleState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandleState")
+            .field("done", &self.done)
+            .field("total", &self.total)
+            .field("elapsed_nanos", &self.elapsed_nanos)
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ProgressHandle {
+    fn default() -> Self {
+        ProgressHandle::new(Arc::new(SystemClock))
+    }
+}
+
+impl ProgressHandle {
+    /// A handle using `clock` for the time now
+    pub(crate) fn new(clock: Arc<dyn Clock>) -> ProgressHandle {
+        ProgressHandle(Arc::new(HandleState {
+            done: AtomicUsize::new(0),
+            total: AtomicUsize::new(usize::MAX),
+            fraction: AtomicU64::new(f64::NAN.to_bits()),
+            elapsed_nanos: AtomicU64::new(0),
+            finished: AtomicBool::new(false),
+            created: clock.now(),
+            clock,
+            updated_nanos: AtomicU64::new(0),
+        }))
+    }
+
+    /// Update from the latest record
+    pub(crate) fn update(&self, record: &ProgressRecord) {
+        let state = &self.0;
+        state.done.store(record.num_done(), Ordering::Relaxed);
+        state
+            .total
+            .store(record.total().unwrap_or(usize::MAX), Ordering::Relaxed);
+        state.fraction.store(
+            record.fraction().unwrap_or(f64::NAN).to_bits(),
+            Ordering::Relaxed,
+        );
+        state
+            .elapsed_nanos
+            .store(as_nanos(record.duration_since_start()), Ordering::Relaxed);
+        state.updated_nanos.store(
+            as_nanos(record.tm.saturating_duration_since(state.created)),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// How long it has been iterating for, as of now, rather than the latest record
+    pub(crate) fn duration_since_start_now(&self) -> Duration {
+        let updated = Duration::from_nanos(self.0.updated_nanos.load(Ordering::Relaxed));
+        let since_created = self.0.clock.now().saturating_duration_since(self.0.created);
+        self.duration_since_start() + since_created.saturating_sub(updated)
+    }
+
+    /// Mark the underlying iterator as exhausted
+    pub(crate) fn finish(&self) {
+        self.0.finished.store(true, Ordering::Release);
+    }
+
+    /// How many items are done, as of the latest record
+    pub fn num_done(&self) -> usize {
+        self.0.done.load(Ordering::Relaxed)
+    }
+
+    /// The total number of items, if known. See `ProgressRecord::fraction`.
+    pub fn total(&self) -> Option<usize> {
+        match self.0.total.load(Ordering::Relaxed) {
+            usize::MAX => None,
+            total => Some(total),
+        }
+    }
+
+    /// How far through, as a fraction, if known. See `ProgressRecord::fraction`.
+    pub fn fraction(&self) -> Option<f64> {
+        let fraction = f64::from_bits(self.0.fraction.load(Ordering::Relaxed));
+        if fraction.is_nan() {
+            None
+        } else {
+            Some(fraction)
+        }
+    }
+
+    /// How far through, as a percentage, if known.
+    pub fn percent(&self) -> Option<f64> {
+        self.fraction().map(|f| f * 100.)
+    }
+
+    /// How long it had been iterating for, as of the latest record
+    pub fn duration_since_start(&self) -> Duration {
+        Duration::from_nanos(self.0.elapsed_nanos.load(Ordering::Relaxed))
+    }
+
+    /// Number of items per second, calculated from the start, as of the latest record. 0 if no
+    /// time had passed.
+    pub fn rate(&self) -> f64 {
+        let secs = self.duration_since_start().as_secs_f64();
+        if secs > 0. {
+            (self.num_done() as f64) / secs
+        } else {
+            0.
+        }
+    }
+
+    /// One line summary of the progress, e.g. `" 32.5% 325 items, 12.3 items/sec, 26s elapsed"`,
+    /// with the time elapsed as of now
+    pub(crate) fn status_line(&self) -> String {
+        let mut line = format!(
+            "{} items, {:.1} items/sec, {} elapsed",
+            thousands_separated(self.num_done()),
+            self.rate(),
+            format_duration(self.duration_since_start_now())
+        );
+        if let Some(percent) = self.percent() {
+            line = format!("{:5.1}% {}", percent, line);
+        }
+        line
+    }
+
+    /// Whether the underlying iterator has been exhausted
+    pub fn is_finished(&self) -> bool {
+        self.0.finished.load(Ordering::Acquire)
+    }
+}
+
+fn as_nanos(duration: Duration) -> u64 {
+    duration.as_nanos().min(u64::MAX as u128) as u64
+}
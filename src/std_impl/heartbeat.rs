@@ -0,0 +1,78 @@
+//! Report the progress on a clock tick, even when no items arrive.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::Clock;
+
+/// The shortest time between ticks, so a tiny (or zero) duration doesn't busy-loop
+const MIN_EVERY: Duration = Duration::from_millis(1);
+
+/// Runs a background thread which calls a function (e.g. to write a line about the progress)
+/// every tick
+pub(crate) struct Heartbeat {
+    /// Whether to stop, and to wake the thread when it should
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Heartbeat {
+    /// Call `tick` every `every` (at least `MIN_EVERY`) by `clock`, until this is dropped
+    pub(crate) fn new<F>(every: Duration, clock: Arc<dyn Clock>, tick: F) -> Heartbeat
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let every = every.max(MIN_EVERY);
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread_stop = stop.clone();
+        let thread = thread::spawn(move || beat(&thread_stop, every, &*clock, tick));
+        Heartbeat {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        *self.stop.0.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.stop.1.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Tick on time by `clock`. It waits by the system clock, so with a clock that doesn't move
+/// in real time (e.g. a `MockClock`), it checks again every `every`. If the next tick is too far
+/// in the future for an `Instant`, it never ticks, and just waits to be stopped.
+fn beat(stop: &(Mutex<bool>, Condvar), every: Duration, clock: &dyn Clock, mut tick: impl FnMut()) {
+    let mut next_beat = clock.now().checked_add(every);
+    let mut stopped = stop.0.lock().unwrap_or_else(|e| e.into_inner());
+    loop {
+        if *stopped {
+            return;
+        }
+        let next = match next_beat {
+            Some(next) => next,
+            None => {
+                stopped = stop.1.wait(stopped).unwrap_or_else(|e| e.into_inner());
+                continue;
+            }
+        };
+        let now = clock.now();
+        if now < next {
+            // Could wake up early, so check the time again
+            stopped = stop
+                .1
+                .wait_timeout(stopped, (next - now).min(every))
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+            continue;
+        }
+        tick();
+        // Skip any beats missed while ticking, or when the clock jumped, rather than catch up
+        next_beat = now.checked_add(every);
+    }
+}
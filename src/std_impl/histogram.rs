@@ -0,0 +1,124 @@
+//! A histogram of the time between items, for percentiles.
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// Each power of 2 (in nanoseconds) is split into this many linear sub-buckets, so a bucket is
+/// at most 1/16th as wide as its values, i.e. percentiles are within ~3%.
+const SUB_BUCKET_BITS: u32 = 4;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+const NUM_BUCKETS: usize = (64 - SUB_BUCKET_BITS as usize + 1) * SUB_BUCKETS;
+
+/// A histogram of the time between items (“latency”), with HDR-style log-linear buckets, so
+/// it's constant memory, and adding a value is cheap. Turn it on with
+/// `OptionalProgressRecorderIter::with_latency_histogram`.
+///
+/// Only available with the `latency-histogram` feature.
+///
+/// ```
+/// # use iter_progress::{MockClock, ProgressableIter};
+/// # use std::time::Duration;
+/// let clock = MockClock::new();
+/// let mut progressor = (0..101).progress().with_clock(clock.clone()).with_latency_histogram();
+/// progressor.next();
+/// for i in 1..=100 {
+///     clock.advance(Duration::from_millis(i));
+///     progressor.next();
+/// }
+/// let histogram = progressor.latency_histogram().unwrap();
+/// assert_eq!(histogram.len(), 100);
+/// let p90 = histogram.p90().unwrap();
+/// assert!(p90 > Duration::from_millis(87) && p90 < Duration::from_millis(93));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    counts: Box<[u64]>,
+    len: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram::new()
+    }
+}
+
+impl LatencyHistogram {
+    /// An empty histogram
+    pub fn new() -> LatencyHistogram {
+        LatencyHistogram {
+            counts: vec![0; NUM_BUCKETS].into_boxed_slice(),
+            len: 0,
+        }
+    }
+
+    /// Add one value
+    pub fn add(&mut self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.counts[bucket_index(nanos)] += 1;
+        self.len += 1;
+    }
+
+    /// How many values have been added
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// True iff no values have been added
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The value below which `quantile` (from 0 to 1) of the values are, e.g. `0.99` for the
+    /// 99th percentile. It's the middle of the bucket it's in, so is approximate. None if it's
+    /// empty.
+    pub fn percentile(&self, quantile: f64) -> Option<Duration> {
+        if self.len == 0 {
+            return None;
+        }
+        let rank = ((quantile.clamp(0., 1.) * self.len as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(Duration::from_nanos(bucket_midpoint(index)));
+            }
+        }
+        None
+    }
+
+    /// The median
+    pub fn p50(&self) -> Option<Duration> {
+        self.percentile(0.5)
+    }
+
+    /// The 90th percentile
+    pub fn p90(&self) -> Option<Duration> {
+        self.percentile(0.9)
+    }
+
+    /// The 99th percentile
+    pub fn p99(&self) -> Option<Duration> {
+        self.percentile(0.99)
+    }
+}
+
+/// Values below `SUB_BUCKETS` get their own bucket, above that each power of 2 is split into
+/// `SUB_BUCKETS`.
+fn bucket_index(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS as u64 {
+        return nanos as usize;
+    }
+    let exp = 63 - nanos.leading_zeros();
+    let sub = (nanos >> (exp - SUB_BUCKET_BITS)) as usize & (SUB_BUCKETS - 1);
+    (exp - SUB_BUCKET_BITS + 1) as usize * SUB_BUCKETS + sub
+}
+
+fn bucket_midpoint(index: usize) -> u64 {
+    if index < SUB_BUCKETS {
+        return index as u64;
+    }
+    let shift = (index / SUB_BUCKETS - 1) as u32;
+    let sub = (index % SUB_BUCKETS) as u64;
+    let lower = (SUB_BUCKETS as u64 + sub) << shift;
+    lower + ((1u64 << shift) >> 1)
+}
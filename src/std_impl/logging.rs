@@ -0,0 +1,92 @@
+//! Log progress with the `log` crate.
+
+use super::{OptionalProgressRecorderIter, ProgressRecord, ProgressRecorderIter};
+use log::Level;
+use std::time::Duration;
+
+/// Target used for log messages, unless changed with `.with_log_target()`
+const DEFAULT_TARGET: &str = "iter_progress";
+
+/// Where, & how often, progress is logged
+pub(crate) struct ProgressLogger {
+    /// How often, & at what level. `None` if only the target has been set so far.
+    every: Option<(Duration, Level)>,
+    target: String,
+}
+
+impl ProgressLogger {
+    pub(crate) fn update(&self, record: &ProgressRecord) {
+        let (every, level) = match self.every {
+            Some(every) => every,
+            None => return,
+        };
+        if log::log_enabled!(target: &self.target, level)
+            && record.should_do_every_n_sec(every.as_secs_f32())
+        {
+            log::log!(target: &self.target, level, "{}", record.message());
+        }
+    }
+}
+
+impl<I: Iterator> OptionalProgressRecorderIter<I> {
+    /// Log the progress (like `ProgressRecord::print_progress_every_n_sec`) with the `log`
+    /// crate at `level`, at most every `every`. The target is `iter_progress`, unless changed
+    /// with `.with_log_target()`.
+    ///
+    /// Only available with the `log` feature.
+    ///
+    /// ```
+    /// # use iter_progress::OptionalProgressableIter;
+    /// # use std::time::Duration;
+    /// let progressor = (0..1_000)
+    ///     .optional_progress(10)
+    ///     .log_every(Duration::from_secs(5), log::Level::Info);
+    /// for (_state, _num) in progressor {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn log_every(self, every: Duration, level: Level) -> Self {
+        let mut res = self;
+        let target = match res.logger.take() {
+            Some(logger) => logger.target,
+            None => DEFAULT_TARGET.to_string(),
+        };
+        res.logger = Some(ProgressLogger {
+            every: Some((every, level)),
+            target,
+        });
+        res
+    }
+
+    /// The `log` target for `.log_every()`. Default: `iter_progress`.
+    ///
+    /// Only available with the `log` feature.
+    pub fn with_log_target(self, target: impl Into<String>) -> Self {
+        let mut res = self;
+        if let Some(ref mut logger) = res.logger {
+            logger.target = target.into();
+        } else {
+            res.logger = Some(ProgressLogger {
+                every: None,
+                target: target.into(),
+            });
+        }
+        res
+    }
+}
+
+impl<I: Iterator> ProgressRecorderIter<I> {
+    /// Log the progress with the `log` crate. See `OptionalProgressRecorderIter::log_every`.
+    ///
+    /// Only available with the `log` feature.
+    pub fn log_every(self, every: Duration, level: Level) -> Self {
+        ProgressRecorderIter(self.0.log_every(every, level))
+    }
+
+    /// The `log` target for `.log_every()`. See `OptionalProgressRecorderIter::with_log_target`.
+    ///
+    /// Only available with the `log` feature.
+    pub fn with_log_target(self, target: impl Into<String>) -> Self {
+        ProgressRecorderIter(self.0.with_log_target(target))
+    }
+}
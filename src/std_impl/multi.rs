@@ -0,0 +1,257 @@
+//! Drawing the progress of several iterators at once.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use super::heartbeat::Heartbeat;
+use super::{
+    thousands_separated, OptionalProgressRecorderIter, OutputMode, OutputSink, ProgressHandle,
+    ProgressTree, SystemClock,
+};
+
+/// Draws the progress of several iterators (e.g. one per thread, for parallel downloads), one
+/// line each, plus a line for them all together. It's the only thing writing, so the lines don't
+/// clobber each other. When the output is interactive, the lines are redrawn in place.
+///
+/// Add each recorder with `.add(…)` before it's sent to its thread, and don't print from the
+/// recorders themselves. Its label (`OptionalProgressRecorderIter::with_label`) is used for its
+/// line. A `ProgressTree` can be drawn too, with `.add_tree(…)`.
+///
+/// ```
+/// # use iter_progress::{MultiProgress, OutputSink, ProgressableIter};
+/// # use std::time::Duration;
+/// let multi = MultiProgress::new(OutputSink::Stderr).draw_every(Duration::from_millis(100));
+/// let workers = ["a", "b"]
+///     .iter()
+///     .map(|name| {
+///         let mut progressor = (0..1_000).progress().with_label(name.to_string());
+///         multi.add(&mut progressor);
+///         std::thread::spawn(move || progressor.for_each(|_| {}))
+///     })
+///     .collect::<Vec<_>>();
+/// for worker in workers {
+///     worker.join().unwrap();
+/// }
+/// assert!(multi.is_finished());
+/// ```
+pub struct MultiProgress {
+    shared: Arc<Shared>,
+
+    /// If drawing in the background, the thread doing it
+    drawer: Option<Heartbeat>,
+}
+
+struct Shared {
+    output: OutputSink,
+    bars: Mutex<Bars>,
+}
+
+struct Bars {
+    /// What to draw, in the order added
+    bars: Vec<Bar>,
+
+    /// Whether to redraw the lines in place
+    interactive: bool,
+
+    /// How many lines were drawn last time, to move back up over when interactive
+    lines_drawn: usize,
+}
+
+enum Bar {
+    /// An iterator's progress, with its label (if any)
+    Handle(Option<String>, ProgressHandle),
+
+    /// A tree, with a line for each node
+    Tree(ProgressTree),
+}
+
+impl MultiProgress {
+    /// Draw to `output`. Whether it's interactive is detected, as with `OutputMode::Auto`.
+    pub fn new(output: OutputSink) -> MultiProgress {
+        let interactive = OutputMode::Auto.is_interactive_for(output.is_terminal());
+        MultiProgress {
+            shared: Arc::new(Shared {
+                output,
+                bars: Mutex::new(Bars {
+                    bars: Vec::new(),
+                    interactive,
+                    lines_drawn: 0,
+                }),
+            }),
+            drawer: None,
+        }
+    }
+
+    /// Force the output to be interactive (redrawn in place) or plain (new lines every time).
+    pub fn with_output_mode(self, mode: OutputMode) -> Self {
+        let interactive = mode.is_interactive_for(self.shared.output.is_terminal());
+        self.shared.lock().interactive = interactive;
+        self
+    }
+
+    /// Draw every `every`, from a background thread, until this is dropped.
+    pub fn draw_every(self, every: Duration) -> Self {
+        let mut res = self;
+        let shared = res.shared.clone();
+        res.drawer = Some(Heartbeat::new(every, Arc::new(SystemClock), move || {
+            shared.draw()
+        }));
+        res
+    }
+
+    /// Show the progress of `progressor`, labelled with its label.
+    pub fn add<I: Iterator>(&self, progressor: &mut OptionalProgressRecorderIter<I>) {
+        let label = progressor.label.as_ref().map(|label| label.to_string());
+        self.add_handle(label, progressor.handle());
+    }
+
+    /// Show the progress from `handle`, labelled with `label`.
+    pub fn add_handle(&self, label: impl Into<Option<String>>, handle: ProgressHandle) {
+        self.shared
+            .lock()
+            .bars
+            .push(Bar::Handle(label.into(), handle));
+    }
+
+    /// Show `tree`, with its children indented under it, as in `ProgressTree::render`. Nodes
+    /// can be added to it (e.g. with `ProgressTree::add_recorder`) while it's drawn. It isn't
+    /// counted in the total line, since its root is the total of its nodes.
+    ///
+    /// ```
+    /// # use iter_progress::{MultiProgress, OutputSink, ProgressTree};
+    /// let multi = MultiProgress::new(OutputSink::Stderr);
+    /// let job = ProgressTree::new("build");
+    /// let compile = job.add_child("compile", 3.);
+    /// job.add_child("link", 1.);
+    /// multi.add_tree(&job);
+    /// compile.finish();
+    /// assert_eq!(
+    ///     multi.lines(),
+    ///     vec![
+    ///         "build [###############     ]  75.0%",
+    ///         "  compile [####################] 100.0%",
+    ///         "  link 0 items",
+    ///     ]
+    /// );
+    /// ```
+    pub fn add_tree(&self, tree: &ProgressTree) {
+        self.shared.lock().bars.push(Bar::Tree(tree.clone()));
+    }
+
+    /// The lines to draw: one for each iterator (or node of a tree), in the order they were
+    /// added, then one for all the iterators together.
+    pub fn lines(&self) -> Vec<String> {
+        self.shared.lines()
+    }
+
+    /// Draw the lines now. When interactive, this replaces the lines drawn last time.
+    pub fn draw(&self) {
+        self.shared.draw();
+    }
+
+    /// Whether all the iterators are exhausted, and all the trees finished
+    pub fn is_finished(&self) -> bool {
+        self.shared.lock().bars.iter().all(|bar| match bar {
+            Bar::Handle(_, handle) => handle.is_finished(),
+            Bar::Tree(tree) => tree.fraction() == Some(1.),
+        })
+    }
+}
+
+impl Drop for MultiProgress {
+    /// Stops drawing in the background, and draws the final state, if it was doing that.
+    fn drop(&mut self) {
+        if self.drawer.take().is_some() {
+            self.shared.draw();
+        }
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Bars> {
+        self.bars.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.lock().lines()
+    }
+
+    /// Draw the lines, holding the lock throughout, so draws from different threads don't mix
+    fn draw(&self) {
+        let mut bars = self.lock();
+        let lines = bars.lines();
+        let mut output = String::new();
+        if bars.interactive {
+            if bars.lines_drawn > 0 {
+                output.push_str(&format!("\x1b[{}A", bars.lines_drawn));
+            }
+            for line in lines.iter() {
+                output.push_str(&format!("\r{}\x1b[K\n", line));
+            }
+        } else {
+            for line in lines.iter() {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+        bars.lines_drawn = lines.len();
+        self.output.write(format_args!("{}", output));
+    }
+}
+
+impl Bars {
+    /// One line for each (or each node of a tree), then the total. There's no total if there
+    /// are only trees.
+    fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for bar in self.bars.iter() {
+            match bar {
+                Bar::Handle(Some(label), handle) => {
+                    lines.push(format!("{}: {}", label, handle.status_line()))
+                }
+                Bar::Handle(None, handle) => lines.push(handle.status_line()),
+                Bar::Tree(tree) => lines.extend(tree.render().lines().map(str::to_string)),
+            }
+        }
+        let handles = self
+            .bars
+            .iter()
+            .filter_map(|bar| match bar {
+                Bar::Handle(_, handle) => Some(handle),
+                Bar::Tree(_) => None,
+            })
+            .collect::<Vec<_>>();
+        if !handles.is_empty() || self.bars.is_empty() {
+            lines.push(total_line(&handles));
+        }
+        lines
+    }
+}
+
+/// e.g. `"Total:  42.0% 840 / 2,000 items, 80.5 items/sec"`. The percent is only shown if
+/// every total is known. The rate is the sum of the rates.
+fn total_line(handles: &[&ProgressHandle]) -> String {
+    let done = handles
+        .iter()
+        .map(|handle| handle.num_done())
+        .sum::<usize>();
+    let total = handles
+        .iter()
+        .map(|handle| handle.total())
+        .sum::<Option<usize>>();
+    let rate = handles.iter().map(|handle| handle.rate()).sum::<f64>();
+    match total {
+        Some(total) if total > 0 => format!(
+            "Total: {:5.1}% {} / {} items, {:.1} items/sec",
+            done as f64 / total as f64 * 100.,
+            thousands_separated(done),
+            thousands_separated(total),
+            rate
+        ),
+        _ => format!(
+            "Total: {} items, {:.1} items/sec",
+            thousands_separated(done),
+            rate
+        ),
+    }
+}
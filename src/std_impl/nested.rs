@@ -0,0 +1,129 @@
+//! Progress of an outer iterator, including how far through its inner iterators are.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use super::{format_duration, thousands_separated, ProgressHandle};
+
+/// The progress of an outer iterator (e.g. over files), which includes the progress of the inner
+/// iterator for the current item (e.g. over each file's records). Get one from the outer
+/// recorder with `OptionalProgressRecorderIter::nested`, and register each inner recorder as its
+/// child with `OptionalProgressRecorderIter::with_parent`.
+///
+/// The outer recorder's records only know about whole items, but this counts how far through
+/// the current item is too, so the fraction & ETA move smoothly. Inner records have this
+/// fraction as `ProgressRecord::parent_fraction`.
+///
+/// It's a cheap, cloneable handle, which can be read from other threads.
+///
+/// ```
+/// # use iter_progress::ProgressableIter;
+/// let files = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
+/// let mut progressor = files.iter().progress();
+/// let nested = progressor.nested();
+/// for (_state, file) in progressor {
+///     for (state, _record) in file.iter().progress().with_parent(&nested) {
+///         // Both levels
+///         let (everything, this_file) = (state.parent_fraction(), state.fraction());
+///     }
+/// }
+/// assert_eq!(nested.fraction(), Some(1.));
+/// ```
+#[derive(Debug, Clone)]
+pub struct NestedProgress(Arc<Mutex<NestedState>>);
+
+#[derive(Debug)]
+struct NestedState {
+    parent: ProgressHandle,
+
+    /// The inner recorder for the current item, and the parent's count when it was added
+    child: Option<(usize, ProgressHandle)>,
+}
+
+impl NestedProgress {
+    pub(crate) fn new(parent: ProgressHandle) -> NestedProgress {
+        NestedProgress(Arc::new(Mutex::new(NestedState {
+            parent,
+            child: None,
+        })))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, NestedState> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Make `child` the inner progress of the parent's current item
+    pub(crate) fn set_child(&self, child: ProgressHandle) {
+        let mut state = self.lock();
+        let done = state.parent.num_done();
+        state.child = Some((done, child));
+    }
+
+    /// How many of the outer items are done, counting the current one as the fraction of its
+    /// inner iterator that's done. An inner iterator whose fraction isn't known counts as 0.
+    pub fn num_done(&self) -> f64 {
+        let state = self.lock();
+        let done = state.parent.num_done();
+        if state.parent.is_finished() || done == 0 {
+            return done as f64;
+        }
+        let current = match state.child {
+            Some((child_done, ref child)) if child_done == done => {
+                child.fraction().unwrap_or(0.).min(1.)
+            }
+            _ => 0.,
+        };
+        (done - 1) as f64 + current
+    }
+
+    /// How far through the outer iterator is, including the current item's inner progress. None
+    /// if the outer total isn't known, e.g. before the first item.
+    pub fn fraction(&self) -> Option<f64> {
+        let total = self.lock().parent.total()?;
+        if total == 0 {
+            return Some(1.);
+        }
+        Some((self.num_done() / total as f64).min(1.))
+    }
+
+    /// How far through, as a percentage, if known.
+    pub fn percent(&self) -> Option<f64> {
+        self.fraction().map(|f| f * 100.)
+    }
+
+    /// The estimated time left, from `.num_done()` & the time since the start. None if the
+    /// outer total isn't known, or nothing is done yet.
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction()?;
+        let elapsed = self.lock().parent.duration_since_start_now();
+        if fraction <= 0. {
+            return None;
+        }
+        Some(elapsed.mul_f64((1. - fraction) / fraction))
+    }
+
+    /// Both levels, on 2 lines, e.g.
+    /// `" 37.5% 1.5 / 4 items, ETA 10s\n   50.0% 2 items, 4.0 items/sec, 1s elapsed"`.
+    pub fn render(&self) -> String {
+        let total = self.lock().parent.total();
+        let mut line = match (self.percent(), total) {
+            (Some(percent), Some(total)) => format!(
+                "{:5.1}% {:.1} / {} items",
+                percent,
+                self.num_done(),
+                thousands_separated(total)
+            ),
+            _ => format!("{:.1} items", self.num_done()),
+        };
+        if let Some(eta) = self.eta() {
+            line.push_str(&format!(", ETA {}", format_duration(eta)));
+        }
+        let state = self.lock();
+        match state.child {
+            Some((child_done, ref child)) if child_done == state.parent.num_done() => {
+                format!("{}\n  {}", line, child.status_line())
+            }
+            _ => line,
+        }
+    }
+}
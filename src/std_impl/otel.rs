@@ -0,0 +1,99 @@
+//! Export progress as OpenTelemetry metrics.
+
+use super::{OptionalProgressRecorderIter, ProgressRecord, ProgressRecorderIter};
+use opentelemetry::metrics::{Counter, Gauge, Meter};
+
+/// The OpenTelemetry instruments which progress is recorded to
+pub(crate) struct OtelExporter {
+    items: Counter<u64>,
+    rate: Gauge<f64>,
+    percent: Gauge<f64>,
+    every: usize,
+
+    /// `num_done()` when the items counter was last updated
+    last_done: usize,
+}
+
+impl OtelExporter {
+    fn new(meter: &Meter, every: usize) -> OtelExporter {
+        OtelExporter {
+            items: meter
+                .u64_counter("iter_progress.items")
+                .with_description("Number of items processed")
+                .build(),
+            rate: meter
+                .f64_gauge("iter_progress.rate")
+                .with_description("Items processed per second, since the start")
+                .with_unit("{item}/s")
+                .build(),
+            percent: meter
+                .f64_gauge("iter_progress.percent")
+                .with_description("Percentage of the items processed")
+                .with_unit("%")
+                .build(),
+            every: every.max(1),
+            last_done: 0,
+        }
+    }
+
+    pub(crate) fn update(&mut self, num_records: usize, record: &ProgressRecord) {
+        if num_records % self.every == 0 {
+            self.record(record);
+        }
+    }
+
+    /// Record the summary when finished, so the last items (since the last update) are counted
+    pub(crate) fn finish(&mut self, summary: &ProgressRecord) {
+        if summary.num_done() != self.last_done {
+            self.record(summary);
+        }
+    }
+
+    fn record(&mut self, record: &ProgressRecord) {
+        let done = record.num_done();
+        self.items
+            .add(done.saturating_sub(self.last_done) as u64, &[]);
+        self.last_done = done;
+        let rate = record.rate();
+        if rate.is_finite() {
+            self.rate.record(rate, &[]);
+        }
+        if let Some(percent) = record.percent() {
+            self.percent.record(percent, &[]);
+        }
+    }
+}
+
+impl<I: Iterator> OptionalProgressRecorderIter<I> {
+    /// Record progress as OpenTelemetry metrics with `meter`, updated every `every` records
+    /// (rather than every item, to keep the overhead down), and when finished. The metrics are:
+    ///
+    /// * `iter_progress.items`: counter of items done
+    /// * `iter_progress.rate`: gauge of items per second, since the start
+    /// * `iter_progress.percent`: gauge of the percentage done, if known
+    ///
+    /// Only available with the `opentelemetry` feature.
+    ///
+    /// ```
+    /// # use iter_progress::OptionalProgressableIter;
+    /// let meter = opentelemetry::global::meter("my_job");
+    /// let progressor = (0..1_000).optional_progress(10).export_otel(&meter, 10);
+    /// for (_state, _num) in progressor {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn export_otel(self, meter: &Meter, every: usize) -> Self {
+        let mut res = self;
+        res.otel = Some(OtelExporter::new(meter, every));
+        res
+    }
+}
+
+impl<I: Iterator> ProgressRecorderIter<I> {
+    /// Record progress as OpenTelemetry metrics. See `OptionalProgressRecorderIter::export_otel`.
+    ///
+    /// Only available with the `opentelemetry` feature.
+    pub fn export_otel(self, meter: &Meter, every: usize) -> Self {
+        ProgressRecorderIter(self.0.export_otel(meter, every))
+    }
+}
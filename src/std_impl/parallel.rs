@@ -0,0 +1,241 @@
+//! Progress for `rayon` parallel iterators.
+
+use super::{
+    OptionalProgressRecorderIter, ProgressConfig, ProgressHandle, ProgressRecord,
+    ProgressRecorderIter,
+};
+use rayon::iter::plumbing::UnindexedConsumer;
+use rayon::iter::ParallelIterator;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// One `()` for every item of the parallel iterator, so the recorder knows how many are left.
+/// It never ends, so the recorder is told it's finished once the parallel iterator is.
+struct Ticks {
+    remaining: Option<usize>,
+}
+
+impl Iterator for Ticks {
+    type Item = ();
+
+    fn next(&mut self) -> Option<()> {
+        if let Some(ref mut remaining) = self.remaining {
+            *remaining = remaining.saturating_sub(1);
+        }
+        Some(())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.remaining {
+            Some(remaining) => (remaining, Some(remaining)),
+            None => (0, None),
+        }
+    }
+}
+
+impl OptionalProgressRecorderIter<Ticks> {
+    /// Count the items before the `count`th which didn't get a record, as if they'd been
+    /// iterated over
+    fn catch_up(&mut self, count: usize) {
+        let skipped = count.saturating_sub(self.count);
+        self.count += skipped;
+        self.done += skipped;
+        if let Some(ref mut remaining) = self.iter.remaining {
+            *remaining = remaining.saturating_sub(skipped);
+        }
+    }
+}
+
+/// A parallel iterator which yields `(ProgressRecord, item)`, like `ProgressRecorderIter`.
+///
+/// All threads share one recorder (behind a `Mutex`), so records are numbered in the order items
+/// are processed, whichever thread they're on. When the parallel iterator has been driven to
+/// completion, the recorder is finished, as when a `ProgressRecorderIter` is exhausted (e.g.
+/// `ProgressHandle::is_finished` is true, and events & checkpoints are finished).
+///
+/// Every item takes the lock, and holds it while its record is generated (and any output, e.g.
+/// a CSV log, is written), so with many threads and cheap items, they spend much of their time
+/// waiting for each other. Use `.optional_progress(n)` to only take it every `n` items.
+pub struct ParallelProgress<I: ParallelIterator> {
+    base: I,
+    recorder: Arc<Mutex<ProgressRecorderIter<Ticks>>>,
+}
+
+impl<I: ParallelIterator> ParallelProgress<I> {
+    /// A handle to watch the progress from other threads. See `ProgressHandle`.
+    pub fn handle(&self) -> ProgressHandle {
+        self.recorder
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .handle()
+    }
+}
+
+impl<I: ParallelIterator> ParallelIterator for ParallelProgress<I> {
+    type Item = (ProgressRecord, I::Item);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let recorder = self.recorder.clone();
+        let result = self
+            .base
+            .map(move |item| {
+                let mut recorder = recorder.lock().unwrap_or_else(|e| e.into_inner());
+                // `Ticks` never ends
+                let (record, ()) = recorder.next().unwrap();
+                (record, item)
+            })
+            .drive_unindexed(consumer);
+        self.recorder
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .0
+            .finished();
+        result
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        self.base.opt_len()
+    }
+}
+
+/// A parallel iterator which yields `(Option<ProgressRecord>, item)`, like
+/// `OptionalProgressRecorderIter`, with a record for (at most) every `generate_every_count`th
+/// item.
+///
+/// The items are counted with an atomic, so the threads only take the recorder's lock for the
+/// items which get a record. If a later item's record has already been generated (by another
+/// thread), an item which was due one doesn't get it. It's finished like a `ParallelProgress`.
+pub struct OptionalParallelProgress<I: ParallelIterator> {
+    base: I,
+    generate_every_count: usize,
+    recorder: Arc<Mutex<OptionalProgressRecorderIter<Ticks>>>,
+}
+
+impl<I: ParallelIterator> OptionalParallelProgress<I> {
+    /// A handle to watch the progress from other threads. See `ProgressHandle`.
+    pub fn handle(&self) -> ProgressHandle {
+        self.recorder
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .handle()
+    }
+}
+
+impl<I: ParallelIterator> ParallelIterator for OptionalParallelProgress<I> {
+    type Item = (Option<ProgressRecord>, I::Item);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let every = self.generate_every_count;
+        let seen = Arc::new(AtomicUsize::new(0));
+        let (recorder, map_seen) = (self.recorder.clone(), seen.clone());
+        let result = self
+            .base
+            .map(move |item| {
+                let seen = map_seen.fetch_add(1, Ordering::Relaxed) + 1;
+                if seen % every != 0 {
+                    return (None, item);
+                }
+                let mut recorder = recorder.lock().unwrap_or_else(|e| e.into_inner());
+                if seen <= recorder.count {
+                    // Another thread got in first, with a later item
+                    return (None, item);
+                }
+                recorder.catch_up(seen - 1);
+                // `Ticks` never ends
+                let (record, ()) = recorder.next().unwrap();
+                (record, item)
+            })
+            .drive_unindexed(consumer);
+        let mut recorder = self.recorder.lock().unwrap_or_else(|e| e.into_inner());
+        recorder.catch_up(seen.load(Ordering::Relaxed));
+        recorder.finished();
+        result
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        self.base.opt_len()
+    }
+}
+
+/// Track the progress of a `rayon` `ParallelIterator`.
+///
+/// Only available with the `rayon` feature.
+///
+/// ```
+/// # extern crate rayon;
+/// # extern crate iter_progress;
+/// use iter_progress::ParallelProgressableIter;
+/// use rayon::prelude::*;
+///
+/// (0..1_000).into_par_iter().progress().for_each(|(state, _num)| {
+///     state.print_progress_every_n_sec(1.);
+/// });
+/// ```
+pub trait ParallelProgressableIter<I: ParallelIterator> {
+    fn progress(self) -> ParallelProgress<I>;
+
+    /// Like `.progress()`, but with all the settings from `config`.
+    fn progress_with(self, config: ProgressConfig) -> ParallelProgress<I>;
+
+    /// Only generate a record for every `generate_every_count`th item, like
+    /// `OptionalProgressableIter::optional_progress`. The other items don't take the lock on the
+    /// shared recorder. See `OptionalParallelProgress`.
+    ///
+    /// # Panics
+    ///
+    /// If `generate_every_count` is 0.
+    ///
+    /// ```
+    /// # extern crate rayon;
+    /// # extern crate iter_progress;
+    /// use iter_progress::ParallelProgressableIter;
+    /// use rayon::prelude::*;
+    ///
+    /// (0..1_000_000).into_par_iter().optional_progress(1_000).for_each(|(state, _num)| {
+    ///     if let Some(state) = state {
+    ///         state.print_progress_every_n_sec(1.);
+    ///     }
+    /// });
+    /// ```
+    fn optional_progress(self, generate_every_count: usize) -> OptionalParallelProgress<I>;
+}
+
+impl<I: ParallelIterator> ParallelProgressableIter<I> for I {
+    fn progress(self) -> ParallelProgress<I> {
+        self.progress_with(ProgressConfig::default())
+    }
+
+    fn progress_with(self, config: ProgressConfig) -> ParallelProgress<I> {
+        let ticks = Ticks {
+            remaining: self.opt_len(),
+        };
+        let recorder = OptionalProgressRecorderIter::new(ticks, 1).with_config(config);
+        ParallelProgress {
+            base: self,
+            recorder: Arc::new(Mutex::new(ProgressRecorderIter(recorder))),
+        }
+    }
+
+    fn optional_progress(self, generate_every_count: usize) -> OptionalParallelProgress<I> {
+        assert!(
+            generate_every_count > 0,
+            "generate_every_count must be more than 0"
+        );
+        let ticks = Ticks {
+            remaining: self.opt_len(),
+        };
+        // Only called for the items which get a record, so each one generates it
+        let recorder = OptionalProgressRecorderIter::new(ticks, 1);
+        OptionalParallelProgress {
+            base: self,
+            generate_every_count,
+            recorder: Arc::new(Mutex::new(recorder)),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Pausing the clock, so idle time isn't counted.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use super::{Clock, Instant, SystemClock};
+
+/// Pauses & resumes the clock of a `ProgressRecorderIter`, so time spent deliberately idle
+/// (e.g. waiting for user input, or sleeping to back off) isn't counted in
+/// `.duration_since_start()`, the rate, or the ETA. Get one with
+/// `OptionalProgressRecorderIter::pause_handle`, before iterating.
+///
+/// Clones control the same recorder, so it can be paused from inside a loop, or another thread.
+///
+/// ```
+/// # use iter_progress::ProgressableIter;
+/// # use std::time::Duration;
+/// let mut progressor = (0..3).progress();
+/// let pause = progressor.pause_handle();
+/// for (state, num) in progressor {
+///     if num == 1 {
+///         pause.pause();
+///         std::thread::sleep(Duration::from_millis(100)); // back off
+///         pause.resume();
+///     }
+///     # if num == 2 { assert!(state.duration_since_start() < Duration::from_millis(100)); }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PauseHandle(Arc<Mutex<PauseState>>);
+
+struct PauseState {
+    /// Where the time comes from, the recorder's clock
+    clock: Arc<dyn Clock>,
+
+    /// When it was paused, if it is now
+    paused_since: Option<Instant>,
+
+    /// How long it has been paused for, not including the current pause
+    paused_for: Duration,
+}
+
+impl Default for PauseState {
+    fn default() -> Self {
+        PauseState {
+            clock: Arc::new(SystemClock),
+            paused_since: None,
+            paused_for: Duration::ZERO,
+        }
+    }
+}
+
+impl std::fmt::Debug for PauseState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PauseState")
+            .field("paused_since", &self.paused_since)
+            .field("paused_for", &self.paused_for)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PauseHandle {
+    /// A handle using `clock` for the time
+    pub(crate) fn new(clock: Arc<dyn Clock>) -> PauseHandle {
+        let res = PauseHandle::default();
+        res.set_clock(clock);
+        res
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PauseState> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Use `clock` for the time from now on, e.g. when the recorder's clock is changed
+    pub(crate) fn set_clock(&self, clock: Arc<dyn Clock>) {
+        self.lock().clock = clock;
+    }
+
+    /// Stop the clock. Does nothing if it's already paused.
+    pub fn pause(&self) {
+        let now = self.lock().clock.now();
+        self.pause_at(now);
+    }
+
+    /// Start the clock again. Does nothing if it's not paused.
+    pub fn resume(&self) {
+        let now = self.lock().clock.now();
+        self.resume_at(now);
+    }
+
+    /// Whether the clock is stopped now
+    pub fn is_paused(&self) -> bool {
+        self.lock().paused_since.is_some()
+    }
+
+    pub(crate) fn pause_at(&self, now: Instant) {
+        let mut state = self.lock();
+        if state.paused_since.is_none() {
+            state.paused_since = Some(now);
+        }
+    }
+
+    pub(crate) fn resume_at(&self, now: Instant) {
+        let mut state = self.lock();
+        if let Some(since) = state.paused_since.take() {
+            state.paused_for += now.saturating_duration_since(since);
+        }
+    }
+
+    /// How long it has been paused for in total, as of `now`
+    pub(crate) fn paused_for(&self, now: Instant) -> Duration {
+        let state = self.lock();
+        match state.paused_since {
+            Some(since) => state.paused_for + now.saturating_duration_since(since),
+            None => state.paused_for,
+        }
+    }
+}
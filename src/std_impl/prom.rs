@@ -0,0 +1,117 @@
+//! Export progress as Prometheus metrics.
+
+use super::{OptionalProgressRecorderIter, ProgressRecord, ProgressRecorderIter};
+use prometheus::{Gauge, IntCounter, Registry};
+
+/// The Prometheus metrics which progress is recorded to
+pub(crate) struct PrometheusExporter {
+    items: IntCounter,
+    rate: Gauge,
+    fraction: Gauge,
+    elapsed: Gauge,
+    every: usize,
+
+    /// `num_done()` when the items counter was last updated
+    last_done: usize,
+}
+
+impl PrometheusExporter {
+    fn new(registry: &Registry, every: usize) -> prometheus::Result<PrometheusExporter> {
+        let items = IntCounter::new("iter_progress_items_total", "Number of items processed")?;
+        let rate = Gauge::new(
+            "iter_progress_rate",
+            "Items processed per second, since the start",
+        )?;
+        let fraction = Gauge::new(
+            "iter_progress_fraction",
+            "Fraction (0 to 1) of the items processed",
+        )?;
+        let elapsed = Gauge::new(
+            "iter_progress_elapsed_seconds",
+            "Seconds since iterating started",
+        )?;
+        registry.register(Box::new(items.clone()))?;
+        registry.register(Box::new(rate.clone()))?;
+        registry.register(Box::new(fraction.clone()))?;
+        registry.register(Box::new(elapsed.clone()))?;
+        Ok(PrometheusExporter {
+            items,
+            rate,
+            fraction,
+            elapsed,
+            every: every.max(1),
+            last_done: 0,
+        })
+    }
+
+    pub(crate) fn update(&mut self, num_records: usize, record: &ProgressRecord) {
+        if num_records % self.every == 0 {
+            self.set(record);
+        }
+    }
+
+    /// Update the metrics with the summary when finished, so the last items (since the last
+    /// update) are counted
+    pub(crate) fn finish(&mut self, summary: &ProgressRecord) {
+        self.set(summary);
+    }
+
+    fn set(&mut self, record: &ProgressRecord) {
+        let done = record.num_done();
+        self.items
+            .inc_by(done.saturating_sub(self.last_done) as u64);
+        self.last_done = done;
+        let rate = record.rate();
+        if rate.is_finite() {
+            self.rate.set(rate);
+        }
+        if let Some(fraction) = record.fraction() {
+            self.fraction.set(fraction);
+        }
+        self.elapsed
+            .set(record.duration_since_start().as_secs_f64());
+    }
+}
+
+impl<I: Iterator> OptionalProgressRecorderIter<I> {
+    /// Register Prometheus metrics with `registry`, and update them every `every` records
+    /// (rather than every item, to keep the overhead down), and when finished. The metrics are:
+    ///
+    /// * `iter_progress_items_total`: counter of items done
+    /// * `iter_progress_rate`: gauge of items per second, since the start
+    /// * `iter_progress_fraction`: gauge of the fraction done, if known
+    /// * `iter_progress_elapsed_seconds`: gauge of the time since iterating started
+    ///
+    /// Returns an error if the metrics can't be registered, e.g. if they already are.
+    ///
+    /// Only available with the `prometheus` feature.
+    ///
+    /// ```
+    /// # use iter_progress::OptionalProgressableIter;
+    /// let registry = prometheus::Registry::new();
+    /// let progressor = (0..1_000)
+    ///     .optional_progress(10)
+    ///     .export_prometheus(&registry, 10)
+    ///     .expect("Can't register metrics");
+    /// for (_state, _num) in progressor {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn export_prometheus(self, registry: &Registry, every: usize) -> prometheus::Result<Self> {
+        let mut res = self;
+        res.prometheus = Some(PrometheusExporter::new(registry, every)?);
+        Ok(res)
+    }
+}
+
+impl<I: Iterator> ProgressRecorderIter<I> {
+    /// Record progress as Prometheus metrics. See
+    /// `OptionalProgressRecorderIter::export_prometheus`.
+    ///
+    /// Only available with the `prometheus` feature.
+    pub fn export_prometheus(self, registry: &Registry, every: usize) -> prometheus::Result<Self> {
+        Ok(ProgressRecorderIter(
+            self.0.export_prometheus(registry, every)?,
+        ))
+    }
+}
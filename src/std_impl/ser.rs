@@ -0,0 +1,29 @@
+//! `serde` serialisation of `ProgressRecord`.
+
+use super::ProgressRecord;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// Serialised as a struct of the main stats, with durations in (fractional) seconds:
+///
+/// * `done`: `.num_done()`
+/// * `total`: the total number of items, if known
+/// * `elapsed_secs`: `.duration_since_start()`
+/// * `rate`: `.rate()`
+/// * `fraction`: `.fraction()`
+/// * `eta_secs`: `.eta()`
+///
+/// There's no `Deserialize`, since a record can't be recreated from these.
+///
+/// Only available with the `serde` feature.
+impl Serialize for ProgressRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ProgressRecord", 6)?;
+        state.serialize_field("done", &self.num_done())?;
+        state.serialize_field("total", &self.total())?;
+        state.serialize_field("elapsed_secs", &self.duration_since_start().as_secs_f64())?;
+        state.serialize_field("rate", &self.rate())?;
+        state.serialize_field("fraction", &self.fraction())?;
+        state.serialize_field("eta_secs", &self.eta().map(|eta| eta.as_secs_f64()))?;
+        state.end()
+    }
+}
@@ -0,0 +1,58 @@
+//! Print the progress on demand, when sent a signal, like `dd` does.
+
+use std::io;
+use std::thread::{self, JoinHandle};
+
+use signal_hook::consts::SIGUSR1;
+use signal_hook::iterator::{Handle, Signals};
+
+use super::{OutputSink, ProgressHandle};
+
+/// The signals which print the progress
+#[cfg(any(
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "macos"
+))]
+const SIGNALS: &[i32] = &[SIGUSR1, signal_hook::consts::SIGINFO];
+#[cfg(not(any(
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "macos"
+)))]
+const SIGNALS: &[i32] = &[SIGUSR1];
+
+/// Runs a background thread which writes a line about the progress when a signal arrives
+pub(crate) struct SignalPrinter {
+    signals: Handle,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SignalPrinter {
+    pub(crate) fn new(handle: ProgressHandle, output: OutputSink) -> io::Result<SignalPrinter> {
+        let mut signals = Signals::new(SIGNALS)?;
+        let signals_handle = signals.handle();
+        let thread = thread::spawn(move || {
+            for _ in signals.forever() {
+                output.write(format_args!("{}\n", handle.status_line()));
+            }
+        });
+        Ok(SignalPrinter {
+            signals: signals_handle,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for SignalPrinter {
+    fn drop(&mut self) {
+        self.signals.close();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
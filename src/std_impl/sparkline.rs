@@ -0,0 +1,82 @@
+//! Render the sample history as a small SVG line chart.
+
+use super::{OptionalProgressRecorderIter, ProgressRecorderIter};
+use std::fmt::Write;
+
+impl<I: Iterator> OptionalProgressRecorderIter<I> {
+    /// Render the throughput over the run, as an SVG sparkline `width`×`height` pixels. The
+    /// rate between each of the samples (see `.with_history(…)`) is plotted against time, so
+    /// slowdowns show up as dips. None if there are fewer than 2 samples.
+    ///
+    /// Only available with the `sparkline` feature.
+    ///
+    /// ```
+    /// # use iter_progress::OptionalProgressableIter;
+    /// let mut progressor = (0..1_000).optional_progress(100).with_history(None);
+    /// (&mut progressor).for_each(|_| {});
+    /// let svg = progressor.sparkline(100, 20).unwrap();
+    /// assert!(svg.starts_with("<svg"));
+    /// ```
+    pub fn sparkline(&self, width: u32, height: u32) -> Option<String> {
+        let samples = self.samples();
+        if samples.len() < 2 {
+            return None;
+        }
+        let rates = samples
+            .windows(2)
+            .map(|w| {
+                // Time & count can go back, with `.reset_to(…)`, which keeps the history
+                let secs = w[1].0.saturating_sub(w[0].0).as_secs_f64();
+                let items = w[1].1.saturating_sub(w[0].1) as f64;
+                (
+                    w[1].0.as_secs_f64(),
+                    if secs > 0. { items / secs } else { 0. },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let start = samples[0].0.as_secs_f64();
+        let end = samples[samples.len() - 1].0.as_secs_f64();
+        let max_rate = rates.iter().map(|&(_, r)| r).fold(0., f64::max);
+
+        let mut points = String::new();
+        for (i, &(tm, rate)) in rates.iter().enumerate() {
+            let x = if end > start {
+                (tm - start) / (end - start) * f64::from(width)
+            } else {
+                i as f64 / (rates.len() as f64) * f64::from(width)
+            };
+            let y = if max_rate > 0. {
+                f64::from(height) * (1. - rate / max_rate)
+            } else {
+                f64::from(height)
+            };
+            if i > 0 {
+                points.push(' ');
+            }
+            write!(points, "{:.1},{:.1}", x, y).unwrap();
+        }
+
+        Some(format!(
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" ",
+                "viewBox=\"0 0 {w} {h}\">",
+                "<polyline fill=\"none\" stroke=\"currentColor\" stroke-width=\"1\" points=\"{p}\"/>",
+                "</svg>"
+            ),
+            w = width,
+            h = height,
+            p = points
+        ))
+    }
+}
+
+impl<I: Iterator> ProgressRecorderIter<I> {
+    /// Render the throughput over the run as an SVG sparkline. See
+    /// `OptionalProgressRecorderIter::sparkline`.
+    ///
+    /// Only available with the `sparkline` feature.
+    pub fn sparkline(&self, width: u32, height: u32) -> Option<String> {
+        self.0.sparkline(width, height)
+    }
+}
@@ -0,0 +1,94 @@
+//! Progress for `futures` streams.
+
+use super::{OptionalProgressRecorderIter, ProgressConfig, ProgressRecord, ProgressRecorderIter};
+use futures_core::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// One `()` per item of the stream, with the stream's latest `size_hint`, so the recorder knows
+/// how many are left
+struct StreamHint((usize, Option<usize>));
+
+impl Iterator for StreamHint {
+    type Item = ();
+
+    fn next(&mut self) -> Option<()> {
+        Some(())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// A stream which yields `(ProgressRecord, item)`, like `ProgressRecorderIter` does for
+    /// iterators.
+    pub struct ProgressStream<S> {
+        #[pin]
+        stream: S,
+        recorder: ProgressRecorderIter<StreamHint>,
+    }
+}
+
+impl<S: Stream> Stream for ProgressStream<S> {
+    type Item = (ProgressRecord, S::Item);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        match this.stream.as_mut().poll_next(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(None) => {
+                this.recorder.0.finished();
+                Poll::Ready(None)
+            }
+            Poll::Ready(Some(item)) => {
+                // This is now the stream's size hint for after this item
+                (this.recorder.0).iter.0 = this.stream.size_hint();
+                // `StreamHint` never ends
+                let (record, ()) = this.recorder.next().unwrap();
+                Poll::Ready(Some((record, item)))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+/// Track the progress of a `futures` `Stream`.
+///
+/// Only available with the `futures` feature.
+///
+/// ```
+/// # extern crate futures;
+/// # extern crate iter_progress;
+/// use iter_progress::ProgressableStream;
+///
+/// let stream = futures::stream::iter(0..1_000).progress();
+/// for (state, _num) in futures::executor::block_on_stream(stream) {
+///     state.print_progress_every_n_sec(1.);
+/// }
+/// ```
+pub trait ProgressableStream<S: Stream> {
+    fn progress(self) -> ProgressStream<S>;
+
+    /// Like `.progress()`, but with all the settings from `config`.
+    fn progress_with(self, config: ProgressConfig) -> ProgressStream<S>;
+}
+
+impl<S: Stream> ProgressableStream<S> for S {
+    fn progress(self) -> ProgressStream<S> {
+        self.progress_with(ProgressConfig::default())
+    }
+
+    fn progress_with(self, config: ProgressConfig) -> ProgressStream<S> {
+        let hint = StreamHint(self.size_hint());
+        let recorder = OptionalProgressRecorderIter::new(hint, 1).with_config(config);
+        ProgressStream {
+            stream: self,
+            recorder: ProgressRecorderIter(recorder),
+        }
+    }
+}
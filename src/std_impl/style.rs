@@ -0,0 +1,158 @@
+//! Colours & characters for `.progress_bar()`.
+
+use super::ByteUnits;
+
+/// A terminal colour, for `BarStyle`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Color {
+    /// The ANSI SGR code for this as the foreground colour
+    fn code(self) -> u8 {
+        30 + self as u8
+    }
+}
+
+/// How `.progress_bar()` looks. The `Default` is plain, with `[` & `]` around the bar.
+///
+/// Colours (and bold) are only used when output is interactive, and can be turned off with
+/// `.colors(false)`. They're off by default if the `NO_COLOR` environment variable is set.
+///
+/// ```
+/// # use iter_progress::{BarStyle, Color, ProgressableIter};
+/// let style = BarStyle::default()
+///     .filled_color(Color::Green)
+///     .unfilled_color(Color::White)
+///     .unfilled_char('░')
+///     .bold_percent(true)
+///     .brackets('|', '|');
+/// for _ in (0..100).progress().progress_bar().with_style(style) {
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BarStyle {
+    pub(crate) filled_color: Option<Color>,
+    pub(crate) unfilled_color: Option<Color>,
+    pub(crate) unfilled_char: char,
+    pub(crate) bold_percent: bool,
+    pub(crate) brackets: (char, char),
+    pub(crate) colors: bool,
+    pub(crate) byte_units: Option<ByteUnits>,
+    pub(crate) short_counts: Option<usize>,
+    pub(crate) completion_time: bool,
+}
+
+impl Default for BarStyle {
+    fn default() -> Self {
+        BarStyle {
+            filled_color: None,
+            unfilled_color: None,
+            unfilled_char: ' ',
+            bold_percent: false,
+            brackets: ('[', ']'),
+            colors: std::env::var_os("NO_COLOR").map_or(true, |val| val.is_empty()),
+            byte_units: None,
+            short_counts: None,
+            completion_time: false,
+        }
+    }
+}
+
+impl BarStyle {
+    /// Colour of the filled part of the bar. `None` for the terminal's default.
+    pub fn filled_color(self, color: impl Into<Option<Color>>) -> Self {
+        let mut res = self;
+        res.filled_color = color.into();
+        res
+    }
+
+    /// Colour of the unfilled part of the bar. `None` for the terminal's default.
+    pub fn unfilled_color(self, color: impl Into<Option<Color>>) -> Self {
+        let mut res = self;
+        res.unfilled_color = color.into();
+        res
+    }
+
+    /// Character for the unfilled part of the bar. Default: a space.
+    pub fn unfilled_char(self, c: char) -> Self {
+        let mut res = self;
+        res.unfilled_char = c;
+        res
+    }
+
+    /// Whether the percent is bold. Default: `false`.
+    pub fn bold_percent(self, bold: bool) -> Self {
+        let mut res = self;
+        res.bold_percent = bold;
+        res
+    }
+
+    /// Characters before & after the bar. Default: `[` & `]`.
+    pub fn brackets(self, left: char, right: char) -> Self {
+        let mut res = self;
+        res.brackets = (left, right);
+        res
+    }
+
+    /// Whether colours & bold can be used at all. Default: `true`, unless the `NO_COLOR`
+    /// environment variable is set.
+    pub fn colors(self, colors: bool) -> Self {
+        let mut res = self;
+        res.colors = colors;
+        res
+    }
+
+    /// Show the count & rate as bytes (e.g. `"1.2 GiB / 4.0 GiB, 12.4 MiB/s"`), for when the
+    /// progress is counted in bytes. Default: `None`, i.e. items.
+    pub fn byte_units(self, units: impl Into<Option<ByteUnits>>) -> Self {
+        let mut res = self;
+        res.byte_units = units.into();
+        res
+    }
+
+    /// Abbreviate the count & rate with `format_count`, with this many decimal places (e.g.
+    /// `"1.2M / 5.0M, 45.3k items/sec"`), for long runs. Default: `None`, i.e. in full.
+    pub fn short_counts(self, precision: impl Into<Option<usize>>) -> Self {
+        let mut res = self;
+        res.short_counts = precision.into();
+        res
+    }
+
+    /// Also show when it's estimated to finish, e.g. `"done at 14:32 UTC"`. Default: `false`.
+    pub fn completion_time(self, show: bool) -> Self {
+        let mut res = self;
+        res.completion_time = show;
+        res
+    }
+
+    /// `text` in this colour, and maybe bold, if colours are `enabled`
+    pub(crate) fn paint(
+        &self,
+        text: &str,
+        color: Option<Color>,
+        bold: bool,
+        enabled: bool,
+    ) -> String {
+        if !enabled || !self.colors || (color.is_none() && !bold) || text.is_empty() {
+            return text.to_string();
+        }
+        let mut codes = vec![];
+        if bold {
+            codes.push("1".to_string());
+        }
+        if let Some(color) = color {
+            codes.push(color.code().to_string());
+        }
+        format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+    }
+}
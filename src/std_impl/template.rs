@@ -0,0 +1,167 @@
+//! Message templates, with placeholders for the stats.
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+use super::{format_duration, thousands_separated, ProgressRecord};
+
+/// The format of the progress message, e.g.
+/// `"{done}/{total} ({percent:.1}%) at {rate}/s, ETA {eta}"`. Set it with
+/// `OptionalProgressRecorderIter::with_template`, and it's used by `ProgressRecord::message()`,
+/// `.print_progress_every_n_sec(…)`, `.progress_bar()` & logging.
+///
+/// The placeholders are:
+///
+/// * `{done}`: the count, e.g. `1,234`
+/// * `{total}`: the total, or `?` if it's not known
+/// * `{count}`: the count of the total, e.g. `1,234 / 10,000`, or just `1,234`
+/// * `{percent}`: the percent done, to 1 decimal place by default (`{percent:.0}` for none), or
+///   `?`
+/// * `{rate}`: items per second, to 1 decimal place by default (`{rate:.2}` for 2)
+/// * `{eta}`: the time left, e.g. `1m 05s`, or `?`
+/// * `{elapsed}`: the time since the start
+/// * `{bar}`: a progress bar, 20 characters wide by default (`{bar:40}` for 40), or nothing
+///
+/// Use `{{` & `}}` for literal braces. Anything else in braces is left as it is.
+///
+/// ```
+/// # use iter_progress::{ProgressableIter, Template};
+/// let template = Template::new("{done}/{total} ({percent:.0}%)");
+/// let mut progressor = (0..4).progress().with_template(template);
+/// let (state, _) = progressor.next().unwrap();
+/// assert_eq!(state.message(), "1/4 (25%)");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template(Arc<[Part]>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Field(Field, Option<usize>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Done,
+    Total,
+    Count,
+    Percent,
+    Rate,
+    Eta,
+    Elapsed,
+    Bar,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        Some(match name {
+            "done" => Field::Done,
+            "total" => Field::Total,
+            "count" => Field::Count,
+            "percent" => Field::Percent,
+            "rate" => Field::Rate,
+            "eta" => Field::Eta,
+            "elapsed" => Field::Elapsed,
+            "bar" => Field::Bar,
+            _ => return None,
+        })
+    }
+}
+
+impl Template {
+    /// Parse `template`
+    pub fn new(template: &str) -> Template {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(i) = rest.find(['{', '}']) {
+            literal.push_str(&rest[..i]);
+            rest = &rest[i..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                literal.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+            let placeholder = rest
+                .find('}')
+                .filter(|_| rest.starts_with('{'))
+                .and_then(|end| parse_placeholder(&rest[1..end]).map(|field| (field, end)));
+            match placeholder {
+                Some(((field, spec), end)) => {
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Field(field, spec));
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    literal.push_str(&rest[..1]);
+                    rest = &rest[1..];
+                }
+            }
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Template(parts.into())
+    }
+
+    /// Fill in the placeholders with the stats from `record`
+    pub fn render(&self, record: &ProgressRecord) -> String {
+        let mut res = String::new();
+        for part in self.0.iter() {
+            match *part {
+                Part::Literal(ref text) => res.push_str(text),
+                Part::Field(field, spec) => render_field(&mut res, record, field, spec),
+            }
+        }
+        res
+    }
+}
+
+impl<'a> From<&'a str> for Template {
+    fn from(template: &'a str) -> Template {
+        Template::new(template)
+    }
+}
+
+/// `name`, `name:.N` or `name:N`, to the field & the number, if it's a known field
+fn parse_placeholder(placeholder: &str) -> Option<(Field, Option<usize>)> {
+    let (name, spec) = match placeholder.find(':') {
+        Some(i) => (&placeholder[..i], Some(&placeholder[i + 1..])),
+        None => (placeholder, None),
+    };
+    let field = Field::from_name(name)?;
+    let spec = match spec {
+        None => None,
+        Some(spec) => Some(spec.trim_start_matches('.').parse().ok()?),
+    };
+    Some((field, spec))
+}
+
+fn render_field(res: &mut String, record: &ProgressRecord, field: Field, spec: Option<usize>) {
+    match field {
+        Field::Done => res.push_str(&thousands_separated(record.num_done())),
+        Field::Total => match record.total() {
+            Some(total) => res.push_str(&thousands_separated(total)),
+            None => res.push('?'),
+        },
+        Field::Count => res.push_str(&record.count_of_total()),
+        Field::Percent => match record.percent() {
+            Some(percent) => write!(res, "{:.*}", spec.unwrap_or(1), percent).unwrap(),
+            None => res.push('?'),
+        },
+        Field::Rate => write!(res, "{:.*}", spec.unwrap_or(1), record.rate()).unwrap(),
+        Field::Eta => match record.eta() {
+            Some(eta) => res.push_str(&format_duration(eta)),
+            None => res.push('?'),
+        },
+        Field::Elapsed => res.push_str(&format_duration(record.duration_since_start())),
+        Field::Bar => {
+            if let Some(bar) = record.bar_unicode(spec.unwrap_or(20)) {
+                res.push_str(&bar);
+            }
+        }
+    }
+}
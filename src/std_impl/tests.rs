@@ -0,0 +1,3457 @@
+use super::*;
+use counter::ProgressCounter;
+
+#[test]
+#[allow(clippy::bool_assert_comparison, clippy::clone_on_copy)]
+fn test_simple() {
+    use super::ProgressableIter;
+    use std::time::Duration;
+
+    let mut progressor = (0..).progress();
+
+    // 0
+    let (state, _) = progressor.next().unwrap();
+    let mut fake_now = state.started_iterating().clone();
+
+    // It'll always print on the first one
+    assert_eq!(state.should_do_every_n_items(2), true);
+    assert_eq!(state.should_do_every_n_items(3), true);
+    assert_eq!(state.should_do_every_n_items(5), true);
+    // First run, so there should be nothing here
+    assert!(state.previous_record_tm().is_none());
+
+    // 1 +500ms
+    fake_now += Duration::from_millis(500);
+    progressor.set_fake_now(fake_now.clone());
+    let (state, _) = progressor.next().unwrap();
+
+    assert_eq!(state.rate().round(), 4.0);
+
+    assert_eq!(state.should_do_every_n_sec(1.), false);
+    assert_eq!(state.should_do_every_n_sec(2.), false);
+    assert_eq!(state.should_do_every_n_sec(0.3), true);
+
+    // 2 +1sec
+    fake_now += Duration::from_millis(500);
+    progressor.set_fake_now(fake_now.clone());
+    let (state, _) = progressor.next().unwrap();
+
+    assert_eq!(state.should_do_every_n_items(2), true);
+    assert_eq!(state.should_do_every_n_items(3), false);
+    assert_eq!(state.should_do_every_n_items(5), false);
+    assert_eq!(state.rate().round(), 3.);
+    assert_eq!(state.should_do_every_n_sec(1.), true);
+    assert_eq!(state.should_do_every_n_sec(2.), false);
+    assert_eq!(state.should_do_every_n_sec(0.8), true);
+
+    // 3 +1.5sec
+    fake_now += Duration::from_millis(500);
+    progressor.set_fake_now(fake_now.clone());
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.should_do_every_n_items(2), false);
+    assert_eq!(state.should_do_every_n_items(3), true);
+    assert_eq!(state.should_do_every_n_items(5), false);
+    assert_eq!(state.rate().round(), 3.);
+    assert_eq!(state.should_do_every_n_sec(1.), false);
+    assert_eq!(state.should_do_every_n_sec(2.), false);
+    assert_eq!(state.should_do_every_n_sec(0.8), false);
+    assert_eq!(state.should_do_every_n_sec(1.5), true);
+
+    // 4 +2sec
+    fake_now += Duration::from_millis(500);
+    progressor.set_fake_now(fake_now.clone());
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.should_do_every_n_items(2), true);
+    assert_eq!(state.should_do_every_n_items(3), false);
+    assert_eq!(state.should_do_every_n_items(4), true);
+    assert_eq!(state.should_do_every_n_items(5), false);
+    assert_eq!(state.rate().round(), 3.);
+}
+
+#[test]
+fn test_size_hint() {
+    use super::ProgressableIter;
+
+    let vec: Vec<u8> = vec![0, 1, 2, 3, 4];
+    let mut progressor = vec.iter().progress();
+
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.fraction(), Some(0.2));
+    assert_eq!(state.percent(), Some(20.));
+
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.fraction(), Some(0.4));
+    assert_eq!(state.percent(), Some(40.));
+
+    let mut progressor = (0..).progress();
+
+    let (state, val) = progressor.next().unwrap();
+    assert_eq!(val, 0);
+    assert_eq!(state.fraction(), None);
+    let (state, val) = progressor.next().unwrap();
+    assert_eq!(val, 1);
+    assert_eq!(state.fraction(), None);
+}
+
+#[test]
+fn assume_fraction1() {
+    use super::ProgressableIter;
+
+    let vec: Vec<u8> = vec![0, 1, 2, 3, 4];
+    let mut progressor = vec.iter().progress();
+
+    let (mut state, _) = progressor.next().unwrap();
+    assert_eq!(state.fraction(), Some(0.2));
+    assert_eq!(state.percent(), Some(20.));
+    state.assume_fraction(0.5);
+    assert_eq!(state.fraction(), Some(0.5));
+    assert_eq!(state.percent(), Some(50.));
+
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.fraction(), Some(0.4));
+    assert_eq!(state.percent(), Some(40.));
+
+    let mut progressor = (0..).progress();
+
+    let (mut state, val) = progressor.next().unwrap();
+    assert_eq!(val, 0);
+    assert_eq!(state.fraction(), None);
+    state.assume_fraction(0.2);
+    assert_eq!(state.fraction(), Some(0.2));
+    let (state, val) = progressor.next().unwrap();
+    assert_eq!(val, 1);
+    assert_eq!(state.fraction(), None);
+}
+
+#[test]
+fn optional() {
+    let vec: Vec<u8> = vec![0, 1, 2, 3, 4];
+    let progressed_iterator = vec.iter().optional_progress(3).collect::<Vec<_>>();
+    dbg!(&progressed_iterator);
+    assert!(progressed_iterator[0].0.is_none());
+    assert!(progressed_iterator[1].0.is_none());
+    assert!(progressed_iterator[2].0.is_some());
+    assert!(progressed_iterator[3].0.is_none());
+    assert!(progressed_iterator[4].0.is_none());
+}
+
+#[test]
+fn limit_records() {
+    let mut progressor = (0..10_000).optional_progress(1).limit_records(50);
+    let records = (&mut progressor)
+        .filter_map(|(rec, _)| rec)
+        .map(|rec| rec.num_done())
+        .collect::<Vec<_>>();
+    assert_eq!(records, (1..=50).collect::<Vec<_>>());
+
+    let samples = progressor.samples();
+    assert_eq!(samples.len(), 50);
+    // samples are in order, and spread over the whole run, not just the start or end
+    assert!(samples.windows(2).all(|w| w[0].1 < w[1].1));
+    assert!(samples[0].1 < 2_500);
+    assert!(samples[49].1 > 7_500);
+}
+
+#[test]
+fn log_csv() {
+    let path = std::env::temp_dir().join(format!("iter-progress-test-{}.csv", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut progressor = vec![0; 10]
+        .into_iter()
+        .progress()
+        .log_csv(&path, 5)
+        .unwrap();
+    let start = progressor.started_iterating;
+    for i in 1..=10 {
+        progressor.set_fake_now(start + Duration::from_secs(i));
+        progressor.next().unwrap();
+    }
+    drop(progressor);
+
+    // Appending doesn't add another header
+    let progressor = (0..5).progress().log_csv(&path, 5).unwrap();
+    progressor.for_each(|_| {});
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let lines = contents.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "elapsed_secs,count,rate,percent");
+    assert_eq!(lines[1], "5.000,5,1.000,50.00");
+    assert_eq!(lines[2], "10.000,10,1.000,100.00");
+    assert!(lines[3].starts_with("0.0"));
+}
+
+#[test]
+fn log_csv_every() {
+    let path = std::env::temp_dir().join(format!(
+        "iter-progress-test-every-{}.csv",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    let mut progressor = (0..10)
+        .progress()
+        .log_csv_every(&path, Duration::from_secs(3))
+        .unwrap();
+    let start = progressor.started_iterating;
+    for i in 1..=10 {
+        progressor.set_fake_now(start + Duration::from_secs(i));
+        progressor.next().unwrap();
+    }
+    drop(progressor);
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let counts = contents
+        .lines()
+        .skip(1)
+        .map(|line| line.split(',').nth(1).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(counts, vec!["3", "6", "9"]);
+}
+
+#[test]
+fn item_duration_stats() {
+    let mut progressor = (0..).progress();
+    let started_iterating = progressor.started_iterating;
+    progressor.set_fake_now(started_iterating);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.item_duration_mean(), None);
+    assert_eq!(state.item_duration_stddev(), None);
+
+    // gaps of 1s, 3s, 1s, 3s
+    let mut fake_now = state.started_iterating();
+    for gap in &[1, 3, 1, 3] {
+        fake_now += Duration::from_secs(*gap);
+        progressor.set_fake_now(fake_now);
+        progressor.next().unwrap();
+    }
+    fake_now += Duration::from_secs(2);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+
+    // gaps of 1, 3, 1, 3, 2 → mean 2, population variance 0.8
+    let mean = state.item_duration_mean().unwrap().as_secs_f64();
+    let stddev = state.item_duration_stddev().unwrap().as_secs_f64();
+    assert!((mean - 2.).abs() < 1e-6);
+    assert!((stddev - 0.8_f64.sqrt()).abs() < 1e-6);
+}
+
+#[test]
+fn since_previous() {
+    let mut progressor = (0..).progress();
+    let mut fake_now = progressor.started_iterating;
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.since_previous(), None);
+
+    fake_now += Duration::from_millis(250);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.since_previous(), Some(Duration::from_millis(250)));
+
+    fake_now += Duration::from_secs(3);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.since_previous(), Some(Duration::from_secs(3)));
+}
+
+#[test]
+fn progress_with_config() {
+    let mut progressor = (0..).progress_with(ProgressConfig::default());
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.fraction(), None);
+    assert_eq!(*state.rolling_average_duration(), None);
+
+    let config = ProgressConfig::default()
+        .assume_size(4)
+        .with_rolling_average(2);
+    let mut progressor = (0..).progress_with(config);
+    let mut fake_now = progressor.started_iterating;
+    for _ in 0..3 {
+        fake_now += Duration::from_secs(1);
+        progressor.set_fake_now(fake_now);
+        progressor.next().unwrap();
+    }
+    fake_now += Duration::from_secs(1);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    // It could go on past the assumed size, so isn't finished
+    assert_eq!(state.fraction(), Some(0.8));
+    assert_eq!(state.rolling_average_rate(), Some(1.));
+
+    // Output, units & clock sampling
+    let (sink, buf) = SharedBuf::sink();
+    let config = ProgressConfig::default()
+        .with_output_sink(sink.clone())
+        .with_output_mode(OutputMode::Plain)
+        .with_bar_style(BarStyle::default().byte_units(ByteUnits::Binary))
+        .sample_time_every(4);
+    assert_eq!(config, config.clone().with_output_sink(sink));
+    assert_ne!(config, config.clone().with_output_sink(OutputSink::Stderr));
+    let progressor = (0..2048).progress_with(config.clone());
+    assert_eq!(progressor.time_sampling.as_ref().map(|s| s.every), Some(4));
+    let (state, _) = (0..10).progress_with(config.clone()).next().unwrap();
+    state.print_every_n_items(1, "1 done\n");
+    assert_eq!(&*buf.lock().unwrap(), b"1 done\n");
+    let bar = (0..2048).progress_with(config).progress_bar();
+    assert_eq!(bar.style.byte_units, Some(ByteUnits::Binary));
+}
+
+#[test]
+fn bar() {
+    let mut progressor = (0..10).progress();
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.bar(10).unwrap(), "#         ");
+    assert_eq!(state.bar_unicode(10).unwrap(), "█         ");
+    assert_eq!(state.bar_unicode(3).unwrap(), "▎  ");
+
+    let (state, _) = progressor.nth(8).unwrap();
+    assert_eq!(state.bar(10).unwrap(), "##########");
+    assert_eq!(state.bar_unicode(3).unwrap(), "███");
+
+    let mut progressor = (0..10).progress_with(ProgressConfig::default().with_unicode(false));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.bar_unicode(10), state.bar(10));
+
+    let (state, _) = (0..).progress().next().unwrap();
+    assert_eq!(state.bar(10), None);
+    assert_eq!(state.bar_unicode(10), None);
+}
+
+#[test]
+fn speedup() {
+    let mut progressor = (0..).progress().with_baseline_rate(2.);
+    let mut fake_now = progressor.started_iterating;
+    for _ in 0..3 {
+        fake_now += Duration::from_millis(250);
+        progressor.set_fake_now(fake_now);
+        progressor.next().unwrap();
+    }
+    fake_now += Duration::from_millis(250);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.rate(), 4.);
+    assert_eq!(state.speedup(), Some(2.));
+
+    let (state, _) = (0..).progress().with_baseline_rate(0.).next().unwrap();
+    assert_eq!(state.speedup(), None);
+    let (state, _) = (0..).progress().next().unwrap();
+    assert_eq!(state.speedup(), None);
+}
+
+#[cfg(feature = "sparkline")]
+#[test]
+fn sparkline() {
+    let mut progressor = (0..).progress().with_history(None);
+    let mut fake_now = progressor.started_iterating;
+    assert_eq!(progressor.sparkline(10, 10), None);
+
+    // 1 item/sec, then 2 items/sec
+    for gap in &[1000, 1000, 500, 500] {
+        fake_now += Duration::from_millis(*gap);
+        progressor.set_fake_now(fake_now);
+        progressor.next().unwrap();
+    }
+    assert_eq!(
+        progressor.sparkline(30, 10).unwrap(),
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"30\" height=\"10\" viewBox=\"0 0 30 10\">\
+         <polyline fill=\"none\" stroke=\"currentColor\" stroke-width=\"1\" \
+         points=\"15.0,5.0 22.5,0.0 30.0,0.0\"/></svg>"
+    );
+
+    // Going back, with `.reset_to(…)`, keeps the history
+    progressor.reset_to(2, Duration::ZERO);
+    fake_now += Duration::from_secs(1);
+    progressor.set_fake_now(fake_now);
+    progressor.next().unwrap();
+    assert!(progressor.sparkline(30, 10).unwrap().starts_with("<svg"));
+}
+
+#[test]
+fn chunks_and_windows() {
+    let data = [0u8; 10];
+
+    let done = data
+        .progress_chunks(3)
+        .map(|(state, _)| (state.num_done(), state.fraction().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(done, vec![(3, 0.3), (6, 0.6), (9, 0.9), (10, 1.)]);
+
+    let done = data
+        .progress_windows(8)
+        .map(|(state, _)| (state.num_done(), state.fraction().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(done, vec![(8, 0.8), (9, 0.9), (10, 1.)]);
+}
+
+#[test]
+fn acceleration() {
+    let mut progressor = (0..).progress().with_rolling_average(2);
+    let mut fake_now = progressor.started_iterating;
+    let mut accelerations = vec![];
+    // Slowing down: 1 item every 0.5s, then every 1s
+    for gap in &[500, 500, 500, 1000, 1000] {
+        fake_now += Duration::from_millis(*gap);
+        progressor.set_fake_now(fake_now);
+        let (state, _) = progressor.next().unwrap();
+        accelerations.push(state.acceleration());
+    }
+    assert_eq!(accelerations[..3], [None, None, Some(0.)]);
+    assert!(accelerations[3].unwrap() < 0.);
+    assert!(accelerations[4].unwrap() < 0.);
+
+    let (state, _) = (0..).progress().next().unwrap();
+    assert_eq!(state.recent_rate(), None);
+    assert_eq!(state.acceleration(), None);
+}
+
+#[test]
+fn output_mode() {
+    let (state, _) = (0..)
+        .progress()
+        .with_output_mode(OutputMode::Plain)
+        .next()
+        .unwrap();
+    assert!(!state.interactive);
+
+    let config = ProgressConfig::default().with_output_mode(OutputMode::Interactive);
+    let mut progressor = (0..10).progress_with(config);
+    let start = progressor.started_iterating;
+    progressor.set_fake_now(start + Duration::from_secs(1));
+    let (state, _) = progressor.next().unwrap();
+    assert!(state.interactive);
+    assert_eq!(
+        state.status_line(),
+        "[██                  ]  10.0% 1 / 10 items, 1.0 items/sec, ETA 9s"
+    );
+
+    let mut progressor = (0..).progress();
+    let start = progressor.started_iterating;
+    progressor.set_fake_now(start + Duration::from_secs(133));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(
+        state.status_line(),
+        "1 items, 0.0 items/sec, 2m 13s elapsed"
+    );
+}
+
+#[test]
+fn reset_to() {
+    let mut progressor = (0..).progress().with_rolling_average(2);
+    let start = progressor.started_iterating;
+    for i in 1..=3 {
+        progressor.set_fake_now(start + Duration::from_secs(i));
+        progressor.next().unwrap();
+    }
+
+    let resumed = start + Duration::from_secs(3600);
+    progressor.set_fake_now(resumed);
+    progressor.reset_to(100, Duration::from_secs(99));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.num_done(), 101);
+    assert_eq!(state.duration_since_start(), Duration::from_secs(99));
+    assert_eq!(state.started_iterating(), resumed);
+    assert_eq!(state.since_previous(), None);
+    assert_eq!(state.rolling_average_rate(), None);
+    assert_eq!(state.item_duration_mean(), None);
+
+    progressor.set_fake_now(resumed + Duration::from_secs(1));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.num_done(), 102);
+    assert_eq!(state.duration_since_start(), Duration::from_secs(100));
+    assert_eq!(state.rate(), 1.02);
+    assert_eq!(state.since_previous(), Some(Duration::from_secs(1)));
+    assert_eq!(state.rolling_average_rate(), Some(1.));
+    assert!(!state.should_do_every_n_sec(3.));
+    assert!(state.should_do_every_n_sec(1.));
+}
+
+#[test]
+fn reset_to_keeps_item_count() {
+    // Only the weight done is restored, not how many items this recorder has seen
+    let mut progressor = vec![5, 5, 5, 5].into_iter().progress_weighted(|w| *w);
+    progressor.next().unwrap();
+    progressor.reset_to(1_000, Duration::ZERO);
+    let results = progressor
+        .map(|(state, _)| (state.num_done(), state.should_do_every_n_items(2)))
+        .collect::<Vec<_>>();
+    assert_eq!(results, vec![(1_005, false), (1_010, true), (1_015, false)]);
+
+    // Records are still generated every 3 items
+    let mut progressor = (0..9).optional_progress(3);
+    progressor.nth(1).unwrap();
+    progressor.reset_to(100, Duration::ZERO);
+    let results = progressor
+        .map(|(state, _)| state.map(|state| state.num_done()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        vec![Some(101), None, None, Some(104), None, None, Some(107)]
+    );
+}
+
+#[test]
+fn progress_tree() {
+    let job = ProgressTree::new("job");
+    assert_eq!(job.fraction(), None);
+
+    let a = job.add_child("a", 1.);
+    let b = job.add_child("b", 3.);
+    let b1 = b.add_child("b1", 1.);
+    let b2 = b.add_child("b2", 1.);
+    assert_eq!(job.fraction(), Some(0.));
+
+    a.set_progress(1, 2);
+    b1.finish();
+    assert_eq!(b.fraction(), Some(0.5));
+    assert_eq!(job.fraction(), Some((0.5 + 3. * 0.5) / 4.));
+    b2.set_progress(7, None);
+
+    assert_eq!(
+        job.render(),
+        "job [##########          ]  50.0%\n  \
+         a [##########          ]  50.0%\n  \
+         b [##########          ]  50.0%\n    \
+         b1 [####################] 100.0%\n    \
+         b2 7 items"
+    );
+
+    assert!(b.remove_child(&b2));
+    assert!(!b.remove_child(&b2));
+    assert_eq!(job.fraction(), Some((0.5 + 3.) / 4.));
+
+    // Progress from recorders, live
+    let job = ProgressTree::new("job");
+    let mut download = (0..4).progress().with_label("download".to_string());
+    let mut unpack = (0..).progress();
+    let download_node = job.add_recorder("ignored", 1., &mut download);
+    let unpack_node = job.add_recorder("unpack", 1., &mut unpack);
+    assert_eq!(download_node.label(), "download");
+    assert_eq!(unpack_node.label(), "unpack");
+    download.nth(1).unwrap();
+    unpack.nth(2).unwrap();
+    assert_eq!(job.fraction(), Some(0.25));
+    assert!(
+        job.render().ends_with("\n  unpack 3 items"),
+        "{}",
+        job.render()
+    );
+    download.by_ref().for_each(drop);
+    assert_eq!(job.fraction(), Some(0.5));
+    unpack.finish();
+    assert_eq!(job.fraction(), Some(1.));
+}
+
+#[test]
+#[should_panic(expected = "weight must be finite & not negative, not NaN")]
+fn progress_tree_nan_weight() {
+    ProgressTree::new("job").add_child("a", f64::NAN);
+}
+
+#[test]
+#[should_panic(expected = "weight must be finite & not negative, not -1")]
+fn progress_tree_negative_weight() {
+    ProgressTree::new("job").add_child("a", -1.);
+}
+
+#[test]
+fn thousands() {
+    assert_eq!(thousands_separated(0), "0");
+    assert_eq!(thousands_separated(999), "999");
+    assert_eq!(thousands_separated(1_000), "1,000");
+    assert_eq!(thousands_separated(123_456), "123,456");
+    assert_eq!(thousands_separated(1_234_567), "1,234,567");
+}
+
+#[test]
+fn recent_window_hybrid() {
+    // Returns the recent rate for each item, and how many samples are in the window at the end
+    fn run(policy: WindowPolicy, gaps: &[u64]) -> (Vec<Option<f64>>, usize) {
+        let mut progressor =
+            (0..)
+                .progress()
+                .recent_window_hybrid(2, Duration::from_secs(4), policy);
+        let mut fake_now = progressor.started_iterating;
+        let rates = gaps
+            .iter()
+            .map(|gap| {
+                fake_now += Duration::from_secs(*gap);
+                progressor.set_fake_now(fake_now);
+                progressor.next().unwrap().0.recent_rate()
+            })
+            .collect();
+        (
+            rates,
+            progressor.recent_window.as_ref().unwrap().samples.len(),
+        )
+    }
+
+    // 1 item/sec: 2 items is the smaller window, 4 secs the larger
+    let steady = [1; 6];
+    let (rates, len) = run(WindowPolicy::Smaller, &steady);
+    assert_eq!(
+        rates,
+        [None, Some(1.), Some(1.), Some(1.), Some(1.), Some(1.)]
+    );
+    assert_eq!(len, 3);
+    let (rates, len) = run(WindowPolicy::Larger, &steady);
+    assert_eq!(
+        rates,
+        [None, Some(1.), Some(1.), Some(1.), Some(1.), Some(1.)]
+    );
+    assert_eq!(len, 5);
+
+    // Slow then fast: 1 item every 10 sec, then every sec
+    let (rates, _) = run(WindowPolicy::Smaller, &[10, 10, 1, 1]);
+    assert_eq!(rates, [None, Some(0.1), Some(1.), Some(1.)]);
+    let (rates, _) = run(WindowPolicy::Larger, &[10, 10, 1, 1]);
+    assert_eq!(rates, [None, Some(0.1), Some(2. / 11.), Some(3. / 12.)]);
+}
+
+#[test]
+fn on_track() {
+    let deadline = SystemTime::now() + Duration::from_secs(100);
+    let mut progressor = (0..10).progress().with_deadline_at(deadline);
+    let start = progressor.started_iterating;
+
+    // 1 item in 5 sec → 45 sec left
+    progressor.set_fake_now(start + Duration::from_secs(5));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.eta(), Some(Duration::from_secs(45)));
+    assert_eq!(state.on_track(), Some(true));
+
+    // 2 items in 40 sec → 160 sec left
+    progressor.set_fake_now(start + Duration::from_secs(40));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.on_track(), Some(false));
+
+    let (state, _) = (0..).progress().with_deadline_at(deadline).next().unwrap();
+    assert_eq!(state.on_track(), None);
+    let (state, _) = (0..10).progress().next().unwrap();
+    assert_eq!(state.on_track(), None);
+
+    // How long is left is measured by the recorder's clock, which needn't match the real one
+    let clock = MockClock::new();
+    clock.advance(Duration::from_secs(10_000));
+    let mut progressor = (0..10)
+        .progress()
+        .with_clock(clock.clone())
+        .with_deadline_at(deadline);
+    clock.advance(Duration::from_secs(1));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.eta(), Some(Duration::from_secs(9)));
+    assert_eq!(state.on_track(), Some(true));
+}
+
+#[test]
+fn at_milestones() {
+    let milestones = (0..95)
+        .at_milestones(0.25)
+        .filter_map(|(state, _)| state.map(|s| s.num_done()))
+        .collect::<Vec<_>>();
+    assert_eq!(milestones, vec![24, 48, 72, 95]);
+
+    let milestones = (0..10)
+        .at_milestones(0.1)
+        .filter_map(|(state, _)| state.map(|s| s.num_done()))
+        .collect::<Vec<_>>();
+    assert_eq!(milestones, (1..=10).collect::<Vec<_>>());
+
+    // Unknown size
+    assert!((0..)
+        .take_while(|&x| x < 10)
+        .at_milestones(0.5)
+        .all(|(s, _)| s.is_none()));
+    let milestones = (0..)
+        .take_while(|&x| x < 10)
+        .at_milestones(0.5)
+        .assume_size(10)
+        .filter_map(|(state, _)| state.map(|s| s.num_done()))
+        .collect::<Vec<_>>();
+    assert_eq!(milestones, vec![5, 10]);
+}
+
+#[test]
+fn at_milestones_invalid_step() {
+    for step in [0., -0.1, 1.5, f64::NAN] {
+        let res = std::panic::catch_unwind(|| (0..10).at_milestones(step));
+        assert!(res.is_err(), "{}", step);
+    }
+    assert_eq!(
+        (0..10)
+            .at_milestones(1.)
+            .filter(|(s, _)| s.is_some())
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn rate_per() {
+    let mut progressor = (0..).progress();
+    let mut fake_now = progressor.started_iterating;
+    for _ in 0..4 {
+        fake_now += Duration::from_millis(500);
+        progressor.set_fake_now(fake_now);
+        progressor.next().unwrap();
+    }
+    fake_now += Duration::from_millis(500);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.rate(), 2.);
+    assert_eq!(state.rate_per(Duration::from_secs(60)), 120.);
+    assert_eq!(state.rate_per(Duration::from_secs(5 * 60)), 600.);
+    assert_eq!(state.rate_per(Duration::from_millis(100)), 0.2);
+}
+
+#[test]
+fn terminal_title() {
+    let mut progressor = (0..1_000).progress().skip(469);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.terminal_title(), "47% (470 / 1,000)");
+
+    let mut progressor = (0..).progress().skip(1_233);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.terminal_title(), "1,234 items");
+
+    // Tests don't run in a terminal, so nothing is written
+    let progressor = (0..10).progress().set_terminal_title(1);
+    assert!(progressor.terminal_title.is_none());
+}
+
+#[test]
+fn on_item_timeout() {
+    use std::sync::mpsc;
+    let (tx, rx) = mpsc::channel();
+    let slow_items = (0..4).inspect(|&i| {
+        if i == 2 {
+            std::thread::sleep(Duration::from_millis(300));
+        }
+    });
+    let progressor =
+        slow_items
+            .progress()
+            .on_item_timeout(Duration::from_millis(100), move |index, elapsed| {
+                tx.send((index, elapsed)).unwrap();
+            });
+    assert_eq!(
+        progressor.map(|(_, i)| i).collect::<Vec<_>>(),
+        vec![0, 1, 2, 3]
+    );
+
+    // The watchdog thread has stopped, and it was only called once
+    let timeouts = rx.iter().collect::<Vec<_>>();
+    assert_eq!(timeouts.len(), 1);
+    assert_eq!(timeouts[0].0, 2);
+    assert!(timeouts[0].1 >= Duration::from_millis(100));
+}
+
+#[test]
+fn seconds_since_last_item() {
+    let (state, _) = (0..10).progress().next().unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(state.seconds_since_last_item() >= 0.02);
+
+    // A record from the future (fake now) hasn't stalled
+    let mut progressor = (0..10).progress();
+    let fake_now = progressor.started_iterating + Duration::from_secs(60);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.seconds_since_last_item(), 0.);
+
+    // Timed by the recorder's clock
+    let clock = MockClock::new();
+    let (state, _) = (0..10).progress().with_clock(clock.clone()).next().unwrap();
+    assert_eq!(state.seconds_since_last_item(), 0.);
+    clock.advance(Duration::from_secs(90));
+    assert_eq!(state.seconds_since_last_item(), 90.);
+}
+
+#[test]
+fn pause_resume() {
+    let mut progressor = (0..10).progress().with_exp_average(0.5);
+    let pause = progressor.pause_handle();
+    let start = progressor.started_iterating;
+
+    progressor.set_fake_now(start + Duration::from_secs(1));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.duration_since_start(), Duration::from_secs(1));
+
+    // Paused for 10 sec, between the 1st & 2nd items
+    pause.pause_at(start + Duration::from_secs(2));
+    assert!(pause.is_paused());
+    pause.resume_at(start + Duration::from_secs(12));
+    assert!(!pause.is_paused());
+
+    progressor.set_fake_now(start + Duration::from_secs(13));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.duration_since_start(), Duration::from_secs(3));
+    assert_eq!(state.since_previous(), Some(Duration::from_secs(2)));
+    assert_eq!(state.exp_average_duration(), &Some(Duration::from_secs(2)));
+    assert_eq!(state.rate(), 2. / 3.);
+
+    // Still paused, so that time isn't counted either
+    pause.pause_at(start + Duration::from_secs(14));
+    progressor.set_fake_now(start + Duration::from_secs(20));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.duration_since_start(), Duration::from_secs(4));
+
+    // A record from before the pause started doesn't panic
+    progressor.set_fake_now(start + Duration::from_secs(15));
+    progressor.next().unwrap();
+
+    // Pausing uses the recorder's clock
+    let clock = MockClock::new();
+    let mut progressor = (0..10).progress();
+    let pause = progressor.pause_handle();
+    let mut progressor = progressor.with_clock(clock.clone());
+    clock.advance(Duration::from_secs(1));
+    pause.pause();
+    clock.advance(Duration::from_secs(10));
+    pause.resume();
+    clock.advance(Duration::from_secs(1));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.duration_since_start(), Duration::from_secs(2));
+}
+
+#[test]
+fn finish() {
+    let (sink, buf) = SharedBuf::sink();
+    let mut progressor = (0..100).progress().with_output_sink(sink);
+    progressor.nth(9).unwrap();
+
+    let fake_now = progressor.started_iterating + Duration::from_secs(5);
+    progressor.set_fake_now(fake_now);
+    let summary = progressor.finish();
+    assert_eq!(summary.num_done(), 10);
+    assert_eq!(summary.duration_since_start(), Duration::from_secs(5));
+    assert_eq!(summary.rate(), 2.);
+    assert_eq!(
+        String::from_utf8(buf.lock().unwrap().clone()).unwrap(),
+        "Done: 10 items in 5s, 2.0 items/sec\n"
+    );
+
+    let (sink, buf) = SharedBuf::sink();
+    let mut progressor = (0..1000).progress().with_output_sink(sink);
+    progressor.by_ref().for_each(drop);
+    let fake_now = progressor.started_iterating + Duration::from_secs(100);
+    progressor.set_fake_now(fake_now);
+    progressor.finish_with_message("Copied");
+    assert_eq!(
+        String::from_utf8(buf.lock().unwrap().clone()).unwrap(),
+        "Copied: 1,000 items in 1m 40s, 10.0 items/sec\n"
+    );
+}
+
+#[test]
+fn report_on_drop() {
+    let (sink, buf) = SharedBuf::sink();
+    let mut progressor = (0..100).progress().with_output_sink(sink).report_on_drop();
+    progressor.nth(9).unwrap();
+    let fake_now = progressor.inner.started_iterating + Duration::from_secs(5);
+    progressor.inner.set_fake_now(fake_now);
+    drop(progressor);
+    assert_eq!(
+        String::from_utf8(buf.lock().unwrap().clone()).unwrap(),
+        "Stopped: 10 items in 5s, 2.0 items/sec\n"
+    );
+
+    let (sink, buf) = SharedBuf::sink();
+    let count = (0..10)
+        .progress()
+        .with_output_sink(sink)
+        .report_on_drop()
+        .count();
+    assert_eq!(count, 10);
+    let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    assert!(output.starts_with("Done: 10 items in 0s, "), "{}", output);
+
+    // Only the "Cancelled" summary when cancelled
+    let (sink, buf) = SharedBuf::sink();
+    let token = CancelToken::new();
+    let mut progressor = (0..10)
+        .progress()
+        .with_output_sink(sink)
+        .with_cancel_token(token.clone())
+        .report_on_drop();
+    progressor.nth(2).unwrap();
+    token.cancel();
+    assert!(progressor.next().is_none());
+    drop(progressor);
+    let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    assert!(output.starts_with("Cancelled: 3 items in "), "{}", output);
+    assert_eq!(output.lines().count(), 1, "{}", output);
+
+    // Or dropped after being cancelled, without asking for the next item
+    let (sink, buf) = SharedBuf::sink();
+    let token = CancelToken::new();
+    let mut progressor = (0..10)
+        .progress()
+        .with_output_sink(sink)
+        .with_cancel_token(token.clone())
+        .report_on_drop();
+    progressor.nth(2).unwrap();
+    token.cancel();
+    drop(progressor);
+    let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    assert!(output.starts_with("Cancelled: 3 items in "), "{}", output);
+}
+
+#[test]
+fn progress_handle() {
+    let mut progressor = (0..10).progress();
+    let handle = progressor.handle();
+    assert_eq!(handle.num_done(), 0);
+    assert_eq!(handle.fraction(), None);
+
+    let fake_now = progressor.started_iterating + Duration::from_secs(2);
+    progressor.set_fake_now(fake_now);
+    progressor.next().unwrap();
+    let other = handle.clone();
+    std::thread::spawn(move || {
+        assert_eq!(other.num_done(), 1);
+        assert_eq!(other.total(), Some(10));
+        assert_eq!(other.fraction(), Some(0.1));
+        assert_eq!(other.duration_since_start(), Duration::from_secs(2));
+        assert_eq!(other.rate(), 0.5);
+        assert!(!other.is_finished());
+    })
+    .join()
+    .unwrap();
+
+    progressor.by_ref().for_each(drop);
+    assert_eq!(handle.num_done(), 10);
+    assert_eq!(handle.percent(), Some(100.));
+    assert!(handle.is_finished());
+
+    let mut progressor = (0..).progress();
+    let handle = progressor.handle();
+    progressor.nth(4).unwrap();
+    assert_eq!(handle.num_done(), 5);
+    assert_eq!(handle.total(), None);
+    assert_eq!(handle.fraction(), None);
+}
+
+#[test]
+fn heartbeat_every() {
+    let (sink, buf) = SharedBuf::sink();
+    let progressor = (0..2)
+        .inspect(|_| std::thread::sleep(Duration::from_millis(100)))
+        .progress()
+        .with_output_sink(sink)
+        .heartbeat_every(Duration::from_millis(30));
+    assert_eq!(progressor.map(|(_, num)| num).sum::<i32>(), 1);
+    let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    let lines = output.lines().collect::<Vec<_>>();
+    // Lines while waiting for the first item, even though no records were generated
+    assert!(lines.len() >= 4, "{:?}", lines);
+    assert_eq!(lines[0], "0 items, 0.0 items/sec, 0s elapsed");
+    assert!(lines.iter().all(|line| line.ends_with(" elapsed")));
+
+    // The thread has stopped
+    let len = output.len();
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(buf.lock().unwrap().len(), len);
+
+    // Not restarted by items after an explicit finish, even with a zero duration
+    let (sink, buf) = SharedBuf::sink();
+    let mut progressor = (0..10)
+        .progress()
+        .with_output_sink(sink)
+        .heartbeat_every(Duration::ZERO);
+    progressor.next().unwrap();
+    progressor.finish_with_message("Stopped");
+    let len = buf.lock().unwrap().len();
+    progressor.next().unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(buf.lock().unwrap().len(), len);
+}
+
+#[test]
+fn heartbeat_too_long() {
+    use std::sync::mpsc::{channel, RecvTimeoutError};
+    // The next tick is too far away for an `Instant`, so it waits to be stopped. If the thread
+    // had panicked, `tick` (& the sender) would be dropped
+    let (sender, receiver) = channel::<()>();
+    let heartbeat =
+        heartbeat::Heartbeat::new(Duration::MAX, Arc::new(MockClock::new()), move || {
+            sender.send(()).unwrap()
+        });
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_millis(50)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    drop(heartbeat);
+    assert_eq!(receiver.recv(), Err(std::sync::mpsc::RecvError));
+
+    // Through the recorder too
+    let (sink, buf) = SharedBuf::sink();
+    let progressor = (0..10)
+        .progress()
+        .with_output_sink(sink)
+        .heartbeat_every(Duration::MAX);
+    assert_eq!(progressor.map(|(_, num)| num).sum::<i32>(), 45);
+    assert!(buf.lock().unwrap().is_empty());
+}
+
+#[test]
+fn send_events() {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut progressor = (0..5)
+        .progress()
+        .send_events(sender, Duration::from_secs(2));
+    for secs in 1..=5 {
+        let fake_now = progressor.started_iterating + Duration::from_secs(secs);
+        progressor.set_fake_now(fake_now);
+        progressor.next().unwrap();
+    }
+    assert!(progressor.next().is_none());
+    // Only sent once
+    progressor.finish();
+
+    let events = receiver.try_iter().collect::<Vec<_>>();
+    assert_eq!(events.len(), 4, "{:?}", events);
+    assert!(matches!(events[0], ProgressEvent::Started));
+    match (&events[1], &events[2], &events[3]) {
+        (
+            ProgressEvent::Tick(first),
+            ProgressEvent::Tick(second),
+            ProgressEvent::Finished(summary),
+        ) => {
+            assert_eq!(first.num_done(), 2);
+            assert_eq!(second.num_done(), 4);
+            assert_eq!(summary.num_done(), 5);
+            assert_eq!(summary.fraction(), Some(1.));
+        }
+        _ => panic!("{:?}", events),
+    }
+}
+
+#[test]
+fn cancel_token() {
+    let (sink, buf) = SharedBuf::sink();
+    let token = CancelToken::new();
+    let mut progressor = (0..100)
+        .progress()
+        .with_output_sink(sink)
+        .with_cancel_token(token.clone());
+    progressor.nth(9).unwrap();
+    assert!(!token.is_cancelled());
+    token.clone().cancel();
+    assert!(token.is_cancelled());
+
+    let fake_now = progressor.started_iterating + Duration::from_secs(5);
+    progressor.set_fake_now(fake_now);
+    assert!(progressor.next().is_none());
+    assert!(progressor.next().is_none());
+    assert_eq!(
+        String::from_utf8(buf.lock().unwrap().clone()).unwrap(),
+        "Cancelled: 10 items in 5s, 2.0 items/sec\n"
+    );
+}
+
+#[cfg(all(unix, feature = "signals"))]
+#[test]
+fn print_on_signal() {
+    let (sink, buf) = SharedBuf::sink();
+    let mut progressor = (0..100).progress().print_on_signal(sink).unwrap();
+    progressor.nth(24).unwrap();
+    signal_hook::low_level::raise(signal_hook::consts::SIGUSR1).unwrap();
+    for _ in 0..100 {
+        if !buf.lock().unwrap().is_empty() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    assert!(output.starts_with(" 25.0% 25 items, "), "{:?}", output);
+}
+
+#[test]
+fn checkpoints() {
+    let path =
+        std::env::temp_dir().join(format!("iter_progress_checkpoints_{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(Checkpoint::load(&path).unwrap(), None);
+
+    let mut progressor = (0..100)
+        .filter(|_| true)
+        .progress()
+        .assume_size(100)
+        .save_checkpoints(&path, Duration::from_secs(10));
+    for secs in 1..=25 {
+        let fake_now = progressor.started_iterating + Duration::from_secs(secs);
+        progressor.set_fake_now(fake_now);
+        progressor.next().unwrap();
+    }
+    // Saved at 10 & 20 sec
+    let checkpoint = Checkpoint::load(&path).unwrap().unwrap();
+    assert_eq!(
+        checkpoint,
+        Checkpoint {
+            count: 20,
+            elapsed: Duration::from_secs(20),
+            total: Some(100),
+        }
+    );
+    // "Crash"
+    drop(progressor);
+
+    let mut progressor = (20..100)
+        .filter(|_| true)
+        .progress()
+        .resume_from(Checkpoint::load(&path).unwrap())
+        .save_checkpoints(&path, Duration::from_secs(10));
+    let fake_now = progressor.started_iterating + Duration::from_secs(20);
+    progressor.set_fake_now(fake_now);
+    let (state, num) = progressor.next().unwrap();
+    assert_eq!(num, 20);
+    assert_eq!(state.num_done(), 21);
+    assert_eq!(state.duration_since_start(), Duration::from_secs(40));
+    assert_eq!(state.fraction(), Some(0.21));
+
+    progressor.by_ref().for_each(drop);
+    let checkpoint = Checkpoint::load(&path).unwrap().unwrap();
+    assert_eq!(checkpoint.count, 100);
+    assert_eq!(checkpoint.total, Some(100));
+
+    std::fs::write(&path, "count=12\n").unwrap();
+    assert_eq!(
+        Checkpoint::load(&path).unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    for elapsed in ["1e30", "-1", "inf", "NaN"] {
+        std::fs::write(&path, format!("count=12\nelapsed_secs={}\n", elapsed)).unwrap();
+        assert_eq!(
+            Checkpoint::load(&path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData,
+            "{}",
+            elapsed
+        );
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn mock_clock() {
+    let clock = MockClock::new();
+    let mut progressor = (0..10)
+        .progress()
+        .with_clock(clock.clone())
+        .with_exp_average(0.5);
+    assert_eq!(progressor.started_iterating, clock.now());
+
+    clock.advance(Duration::from_secs(1));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.duration_since_start(), Duration::from_secs(1));
+    assert_eq!(state.eta(), Some(Duration::from_secs(9)));
+
+    clock.advance(Duration::from_secs(3));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.since_previous(), Some(Duration::from_secs(3)));
+    assert_eq!(state.rate(), 0.5);
+
+    // A fake now (for one record) still takes precedence
+    let fake_now = clock.now() + Duration::from_secs(6);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.duration_since_start(), Duration::from_secs(10));
+
+    clock.advance(Duration::from_secs(8));
+    let summary = progressor.finish_with_message("Done");
+    assert_eq!(summary.duration_since_start(), Duration::from_secs(12));
+}
+
+#[test]
+fn mock_clock_helpers() {
+    let path = std::env::temp_dir().join(format!(
+        "iter_progress_mock_clock_helpers_{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let clock = MockClock::new();
+    let (sink, buf) = SharedBuf::sink();
+    let mut progressor = (0..10)
+        .progress()
+        .with_clock(clock.clone())
+        .with_output_sink(sink)
+        .save_checkpoints(&path, Duration::from_secs(10))
+        .heartbeat_every(Duration::from_millis(50));
+    let handle = progressor.handle();
+
+    clock.advance(Duration::from_secs(5));
+    progressor.next().unwrap();
+    assert!(Checkpoint::load(&path).unwrap().is_none());
+    // The clock hasn't moved, so no heartbeat
+    std::thread::sleep(Duration::from_millis(150));
+    assert!(buf.lock().unwrap().is_empty());
+
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(handle.duration_since_start_now(), Duration::from_secs(15));
+    for _ in 0..100 {
+        if !buf.lock().unwrap().is_empty() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    // Only one, not one for every 50ms the clock jumped
+    std::thread::sleep(Duration::from_millis(150));
+    assert_eq!(
+        String::from_utf8(buf.lock().unwrap().clone()).unwrap(),
+        " 10.0% 1 items, 0.2 items/sec, 15s elapsed\n"
+    );
+
+    progressor.next().unwrap();
+    assert_eq!(Checkpoint::load(&path).unwrap().unwrap().count, 2);
+    drop(progressor);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn sample_time_every() {
+    let clock = MockClock::new();
+    let mut progressor = (0..20)
+        .progress()
+        .with_clock(clock.clone())
+        .sample_time_every(4);
+    let mut elapsed = Vec::new();
+    for secs in 1..=9 {
+        // The clock is read for items 1, 5 & 9. It speeds up after item 5, so the time read for
+        // item 9 is before the interpolated time for item 8
+        if secs <= 5 {
+            clock.advance(Duration::from_secs(1));
+        } else {
+            clock.advance(Duration::from_millis(100));
+        }
+        let (state, _) = progressor.next().unwrap();
+        elapsed.push(state.duration_since_start().as_secs_f64());
+    }
+    assert_eq!(elapsed, vec![1., 1., 1., 1., 5., 6., 7., 8., 8.]);
+}
+
+#[test]
+fn double_ended() {
+    let mut progressor = (0..10).progress();
+    let (state, num) = progressor.next_back().unwrap();
+    assert_eq!((num, state.num_done()), (9, 1));
+    let (state, num) = progressor.next().unwrap();
+    assert_eq!((num, state.num_done()), (0, 2));
+    let (state, num) = progressor.by_ref().rev().last().unwrap();
+    assert_eq!((num, state.num_done()), (1, 10));
+    assert_eq!(state.fraction(), Some(1.));
+
+    let nums = (0..6)
+        .optional_progress(2)
+        .rev()
+        .map(|(state, num)| (state.map(|state| state.num_done()), num))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        nums,
+        vec![
+            (None, 5),
+            (Some(2), 4),
+            (None, 3),
+            (Some(4), 2),
+            (None, 1),
+            (Some(6), 0)
+        ]
+    );
+}
+
+#[test]
+fn exact_size_and_fused() {
+    fn assert_fused<I: std::iter::FusedIterator>(_: &I) {}
+
+    let mut progressor = (0..10).progress();
+    assert_fused(&progressor);
+    assert_eq!(progressor.len(), 10);
+    progressor.nth(3).unwrap();
+    assert_eq!(progressor.len(), 6);
+    progressor.by_ref().for_each(drop);
+    assert_eq!(progressor.len(), 0);
+    assert!(progressor.next().is_none());
+
+    let mut progressor = (0..10).optional_progress(3);
+    assert_fused(&progressor);
+    progressor.next().unwrap();
+    assert_eq!(progressor.len(), 9);
+
+    // Nothing more is yielded after cancelling
+    let token = CancelToken::new();
+    let mut progressor = (0..10)
+        .progress()
+        .with_output_sink(SharedBuf::sink().0)
+        .with_cancel_token(token.clone());
+    progressor.next().unwrap();
+    token.cancel();
+    assert_eq!(progressor.len(), 0);
+    assert!(progressor.next().is_none());
+}
+
+#[test]
+fn record_eq() {
+    let mut progressor = (0..10).progress();
+    let (first, _) = progressor.next().unwrap();
+    let (second, _) = progressor.next().unwrap();
+    assert_eq!(first.clone(), first);
+    assert_ne!(first, second);
+    assert_eq!(second.size_hint(), (8, Some(8)));
+
+    // Where the output goes doesn't matter
+    let mut other = first.clone();
+    other.output = OutputSink::Stderr;
+    assert_eq!(other, first);
+}
+
+#[test]
+fn thread_safety() {
+    fn assert_send<T: Send>() {}
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send::<ProgressRecorderIter<std::vec::IntoIter<String>>>();
+    assert_send::<OptionalProgressRecorderIter<std::vec::IntoIter<String>>>();
+    assert_send::<ProgressRecorderIter<Counting<std::vec::IntoIter<String>, fn(&String) -> usize>>>(
+    );
+    assert_send_sync::<ProgressRecord>();
+    assert_send_sync::<ProgressEvent>();
+    assert_send_sync::<ProgressHandle>();
+    assert_send_sync::<PauseHandle>();
+    assert_send_sync::<CancelToken>();
+    assert_send_sync::<OutputSink>();
+    assert_send_sync::<MockClock>();
+    assert_send_sync::<MultiProgress>();
+    assert_send_sync::<NestedProgress>();
+}
+
+#[test]
+fn initial_total() {
+    let mut progressor = (0..100).filter(|n| n % 2 == 0).progress();
+    let fractions = progressor
+        .by_ref()
+        .take(3)
+        .map(|(state, _)| state.fraction())
+        .collect::<Vec<_>>();
+    assert_eq!(fractions, vec![Some(0.01), Some(0.02), Some(0.03)]);
+
+    // Resuming counts what was done before
+    let mut progressor = (50..100).filter(|_| true).progress();
+    progressor.reset_to(50, Duration::ZERO);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.fraction(), Some(0.51));
+
+    // An assumed size takes precedence
+    let mut progressor = (0..100).filter(|_| true).progress().assume_size(10);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.fraction(), Some(0.1));
+
+    // Not when counting something other than items
+    let mut progressor = (0..100).filter(|_| true).progress_counting(|_| 2);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.fraction(), None);
+}
+
+#[test]
+fn fraction_bounds() {
+    let mut progressor = (0..10).filter(|_| true).progress();
+    let (state, _) = progressor.nth(1).unwrap();
+    assert_eq!(state.fraction_bounds(), Some((0.2, 1.)));
+    let (state, _) = progressor.last().unwrap();
+    assert_eq!(state.fraction_bounds(), Some((1., 1.)));
+
+    let (state, _) = (0..10)
+        .filter(|_| true)
+        .progress()
+        .assume_size(4)
+        .next()
+        .unwrap();
+    assert_eq!(state.fraction_bounds(), Some((0.25, 0.25)));
+
+    let (state, _) = (0..).filter(|_| true).progress().next().unwrap();
+    assert_eq!(state.fraction_bounds(), None);
+    let (state, _) = (0..10).progress_counting(|_| 2).next().unwrap();
+    assert_eq!(state.fraction_bounds(), None);
+}
+
+#[test]
+fn remaining() {
+    let mut progressor = (0..20).filter(|_| true).progress().assume_size(10);
+    let (state, _) = progressor.nth(3).unwrap();
+    assert_eq!(state.remaining(), Some(6));
+    // Past the assumed size
+    let (state, _) = progressor.nth(9).unwrap();
+    assert_eq!(state.remaining(), Some(0));
+    let (state, _) = progressor.last().unwrap();
+    assert_eq!(state.remaining(), Some(0));
+
+    let (state, _) = (0..10).progress_counting(|_| 3).next().unwrap();
+    assert_eq!(state.remaining(), None);
+}
+
+#[test]
+fn time_since_last_item() {
+    let mut progressor = (0..10).progress();
+    let fake_now = progressor.started_iterating + Duration::from_secs(2);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.since_previous(), None);
+    assert_eq!(state.time_since_last_item(), Duration::from_secs(2));
+
+    let fake_now = progressor.started_iterating + Duration::from_secs(7);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.time_since_last_item(), Duration::from_secs(5));
+}
+
+#[test]
+fn item_duration_min_max() {
+    let clock = MockClock::new();
+    let mut progressor = (0..10).progress().with_clock(clock.clone());
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.item_duration_min(), None);
+    assert_eq!(state.item_duration_max(), None);
+    for secs in [3, 1, 5, 3] {
+        clock.advance(Duration::from_secs(secs));
+        progressor.next().unwrap();
+    }
+    let summary = progressor.finish_with_message("Done");
+    assert_eq!(summary.item_duration_min(), Some(Duration::from_secs(1)));
+    assert_eq!(summary.item_duration_max(), Some(Duration::from_secs(5)));
+    assert_eq!(summary.item_duration_mean(), Some(Duration::from_secs(3)));
+    assert_eq!(
+        summary.item_duration_stddev(),
+        Some(Duration::from_secs_f64(2f64.sqrt()))
+    );
+}
+
+#[cfg(feature = "latency-histogram")]
+#[test]
+fn latency_histogram() {
+    let clock = MockClock::new();
+    let mut progressor = (0..20).progress().with_clock(clock.clone());
+    progressor.next();
+    assert!(progressor.latency_histogram().is_none());
+
+    let mut progressor = (0..20)
+        .progress()
+        .with_clock(clock.clone())
+        .with_latency_histogram();
+    progressor.next();
+    assert_eq!(progressor.latency_histogram().unwrap().p50(), None);
+    for _ in 0..9 {
+        clock.advance(Duration::from_millis(10));
+        progressor.next();
+    }
+    clock.advance(Duration::from_secs(1));
+    progressor.next();
+
+    let histogram = progressor.latency_histogram().unwrap();
+    assert_eq!(histogram.len(), 10);
+    let p50 = histogram.p50().unwrap();
+    assert!(p50 > Duration::from_micros(9_700) && p50 < Duration::from_micros(10_300));
+    assert_eq!(histogram.p90(), histogram.p50());
+    let p99 = histogram.p99().unwrap();
+    assert!(p99 > Duration::from_millis(970) && p99 < Duration::from_millis(1_030));
+    assert_eq!(histogram.percentile(0.), histogram.p50());
+    assert_eq!(histogram.percentile(1.), histogram.p99());
+}
+
+#[cfg(feature = "latency-histogram")]
+#[test]
+fn latency_histogram_small_values() {
+    let mut histogram = LatencyHistogram::new();
+    for nanos in 0..16 {
+        histogram.add(Duration::from_nanos(nanos));
+    }
+    assert_eq!(histogram.percentile(0.5), Some(Duration::from_nanos(7)));
+    assert_eq!(histogram.percentile(1.), Some(Duration::from_nanos(15)));
+    histogram.add(Duration::MAX);
+    assert!(histogram.percentile(1.).unwrap() > Duration::from_secs(1 << 33));
+}
+
+#[test]
+fn peak_trough_rate() {
+    let clock = MockClock::new();
+    let mut progressor = (0..10)
+        .progress()
+        .with_clock(clock.clone())
+        .recent_window_items(1);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.peak_rate(), None);
+    assert_eq!(state.trough_rate(), None);
+    for millis in [100, 500, 250] {
+        clock.advance(Duration::from_millis(millis));
+        progressor.next().unwrap();
+    }
+    let summary = progressor.finish_with_message("Done");
+    assert_eq!(summary.peak_rate(), Some(10.));
+    assert_eq!(summary.trough_rate(), Some(2.));
+
+    let (state, _) = (0..10).progress().last().unwrap();
+    assert_eq!(state.peak_rate(), None);
+}
+
+#[test]
+fn rate_over() {
+    let clock = MockClock::new();
+    let mut progressor = (0..100)
+        .progress()
+        .with_clock(clock.clone())
+        .track_rates_over(Duration::from_secs(10));
+    progressor.next();
+    assert_eq!(progressor.rate_over(Duration::from_secs(5)), None);
+    // 1 item/sec for 10 sec, then 10 items/sec for 5 sec
+    for _ in 0..10 {
+        clock.advance(Duration::from_secs(1));
+        progressor.next();
+    }
+    for _ in 0..50 {
+        clock.advance(Duration::from_millis(100));
+        progressor.next();
+    }
+    assert_eq!(progressor.rate_over(Duration::from_secs(5)), Some(10.));
+    assert_eq!(progressor.rate_over(Duration::from_secs(10)), Some(5.5));
+    // Only 10 sec is kept
+    assert_eq!(
+        progressor.rate_over(Duration::from_secs(60)),
+        progressor.rate_over(Duration::from_secs(10))
+    );
+
+    let mut progressor = (0..100).progress();
+    progressor.next();
+    progressor.next();
+    assert_eq!(progressor.rate_over(Duration::from_secs(5)), None);
+}
+
+#[test]
+fn config_windows() {
+    let clock = MockClock::new();
+    let config = ProgressConfig::default()
+        .with_rolling_average(2)
+        .recent_window_items(3)
+        .track_rates_over(Duration::from_secs(2));
+    let mut progressor = (0..100).progress_with(config).with_clock(clock.clone());
+    progressor.next();
+    for millis in [1_000, 1_000, 500, 500, 500] {
+        clock.advance(Duration::from_millis(millis));
+        progressor.next();
+    }
+    let (state, _) = progressor.next().unwrap();
+    // The last 2 durations were 500ms & 0
+    assert_eq!(state.rolling_average_rate(), Some(4.));
+    // 3 items since 1s ago
+    assert_eq!(state.recent_rate(), Some(3.));
+    assert_eq!(progressor.rate_over(Duration::from_secs(1)), Some(3.));
+    // Back to the last record from at least 2s ago
+    assert_eq!(progressor.rate_over(Duration::from_secs(60)), Some(2.));
+}
+
+#[test]
+fn do_every_fn_once() {
+    let mut messages = Vec::new();
+    for (state, _) in (0..10).progress() {
+        let msg = format!("{} done", state.num_done());
+        state.do_every_n_items(3, |_| messages.push(msg));
+    }
+    assert_eq!(messages, vec!["1 done", "4 done", "7 done", "10 done"]);
+
+    let clock = MockClock::new();
+    let mut progressor = (0..10).progress().with_clock(clock.clone());
+    let mut fired = 0;
+    for _ in 0..5 {
+        clock.advance(Duration::from_secs(1));
+        let (state, _) = progressor.next().unwrap();
+        state.do_every_n_sec(2., |_| fired += 1);
+    }
+    assert_eq!(fired, 2);
+}
+
+#[test]
+fn do_every_duration() {
+    let clock = MockClock::new();
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let calls2 = calls.clone();
+    let mut progressor = (0..100)
+        .progress()
+        .with_clock(clock.clone())
+        .do_every_duration(Duration::from_secs(2), move |state| {
+            calls2.lock().unwrap().push(state.num_done())
+        });
+    progressor.next();
+    for millis in [1_500, 1_500, 100, 1_000, 3_000, 100, 100, 1_800] {
+        clock.advance(Duration::from_millis(millis));
+        progressor.next();
+    }
+    // Called at 3s (then ≥ 5s): 4.1s is too soon, 7.1s isn't, then ≥ 9.1s
+    assert_eq!(*calls.lock().unwrap(), vec![3, 6, 9]);
+}
+
+#[test]
+fn do_every_trigger() {
+    let run = |trigger| {
+        let clock = MockClock::new();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let calls2 = calls.clone();
+        let mut progressor = (0..100)
+            .progress()
+            .with_clock(clock.clone())
+            .do_every(trigger, move |state| {
+                calls2.lock().unwrap().push(state.num_done())
+            });
+        progressor.next();
+        // Fast to start with, then slow
+        for millis in [10, 10, 10, 10, 10, 10, 3_000, 3_000, 10] {
+            clock.advance(Duration::from_millis(millis));
+            progressor.next();
+        }
+        let calls = calls.lock().unwrap().clone();
+        calls
+    };
+    assert_eq!(run(Trigger::items(3)), vec![3, 6, 9]);
+    assert_eq!(run(Trigger::duration(Duration::from_secs(2))), vec![8, 9]);
+    assert_eq!(
+        run(Trigger::items_or_duration(3, Duration::from_secs(2))),
+        vec![3, 6, 8, 9]
+    );
+}
+
+#[test]
+fn print_every_with() {
+    let (sink, buf) = SharedBuf::sink();
+    let clock = MockClock::new();
+    let mut progressor = (0..10)
+        .progress()
+        .with_output_sink(sink)
+        .with_clock(clock.clone());
+    let mut formatted = 0;
+    for _ in 0..10 {
+        clock.advance(Duration::from_millis(600));
+        let (state, num) = progressor.next().unwrap();
+        state.print_every_n_items_with(4, || {
+            formatted += 1;
+            format!("item {}\n", num)
+        });
+        state.print_every_n_sec_with(2., || format!("{}s\n", num));
+    }
+    assert_eq!(formatted, 3);
+    assert_eq!(
+        String::from_utf8(buf.lock().unwrap().clone()).unwrap(),
+        // Every 2 seconds since the last print, at 2.4s & 4.8s
+        "item 0\n3s\nitem 4\n7s\nitem 8\n"
+    );
+}
+
+#[test]
+fn template() {
+    let clock = MockClock::new();
+    let mut progressor = (0..2_000)
+        .progress()
+        .with_clock(clock.clone())
+        .with_template(
+            "{{{done}}} {count} {percent}% {percent:.0}% {rate:.2}/s ETA {eta}, {elapsed} [{bar:4}] {nope} {rate:x} {",
+        );
+    clock.advance(Duration::from_secs(2));
+    let (state, _) = progressor.nth(499).unwrap();
+    assert_eq!(
+        state.message(),
+        "{500} 500 / 2,000 25.0% 25% 250.00/s ETA 6s, 2s [█   ] {nope} {rate:x} {"
+    );
+    assert_eq!(state.bar_line(&BarStyle::default()), state.message());
+
+    let (state, _) = (0..)
+        .progress()
+        .with_template("{done} of {total}, {percent}%, ETA {eta}, [{bar}]")
+        .next()
+        .unwrap();
+    assert_eq!(state.message(), "1 of ?, ?%, ETA ?, []");
+
+    let (state, _) = (0..10)
+        .progress_with(ProgressConfig::default().with_template("{count}"))
+        .next()
+        .unwrap();
+    assert_eq!(state.message(), "1 / 10");
+    assert_eq!(Template::new("a {done}"), Template::from("a {done}"),);
+}
+
+#[test]
+fn display() {
+    let clock = MockClock::new();
+    let mut progressor = (0..4).progress().with_clock(clock.clone());
+    clock.advance(Duration::from_secs(1));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.to_string(), state.message());
+    assert_eq!(
+        format!("{}", state),
+        "[█████               ]  25.0% 1 / 4 items, 1.0 items/sec, ETA 3s"
+    );
+
+    let (state, _) = (0..4).progress().with_template("{done}").next().unwrap();
+    assert_eq!(format!("<{}>", state), "<1>");
+}
+
+#[test]
+fn default_message() {
+    let clock = MockClock::new();
+    let mut progressor = (0..10_000).progress().with_clock(clock.clone());
+    clock.advance(Duration::from_secs(5));
+    let (state, _) = progressor.nth(2_499).unwrap();
+    assert_eq!(
+        state.message(),
+        "[█████               ]  25.0% 2,500 / 10,000 items, 500.0 items/sec, ETA 15s"
+    );
+
+    let mut progressor = (0..).progress().with_clock(clock.clone());
+    clock.advance(Duration::from_secs(5));
+    let (state, _) = progressor.nth(2_499).unwrap();
+    assert_eq!(state.message(), "2,500 items, 500.0 items/sec, 5s elapsed");
+}
+
+#[test]
+fn to_json() {
+    let mut progressor = (0..4).progress();
+    let fake_now = progressor.started_iterating + Duration::from_secs(10);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(
+        state.to_json(),
+        r#"{"done":1,"total":4,"elapsed_secs":10.0,"rate":0.1,"fraction":0.25,"eta_secs":30.0}"#
+    );
+
+    let mut progressor = (0..).filter(|_| true).progress();
+    let fake_now = progressor.started_iterating;
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(
+        state.to_json(),
+        r#"{"done":1,"total":null,"elapsed_secs":0.0,"rate":0.0,"fraction":null,"eta_secs":null}"#
+    );
+}
+
+#[test]
+fn label() {
+    let (sink, buf) = SharedBuf::sink();
+    let clock = MockClock::new();
+    let mut progressor = (0..4)
+        .progress()
+        .with_clock(clock.clone())
+        .with_output_sink(sink)
+        .with_output_mode(OutputMode::Plain)
+        .with_label("uploading".to_string());
+    clock.advance(Duration::from_secs(1));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.label(), Some("uploading"));
+    assert_eq!(
+        state.to_string(),
+        "uploading: [█████               ]  25.0% 1 / 4 items, 1.0 items/sec, ETA 3s"
+    );
+    assert_eq!(
+        state.bar_line(&BarStyle::default()),
+        "uploading: [█████               ]  25.0% 1 / 4, 1.0 items/sec, ETA 3s"
+    );
+    let summary = progressor.finish();
+    assert_eq!(summary.label(), Some("uploading"));
+    assert_eq!(
+        String::from_utf8(buf.lock().unwrap().clone()).unwrap(),
+        "uploading: Done: 1 items in 1s, 1.0 items/sec\n"
+    );
+
+    let (state, _) = (0..4)
+        .progress_with(ProgressConfig::default().with_label("parsing".to_string()))
+        .with_template("{done}")
+        .next()
+        .unwrap();
+    assert_eq!(state.message(), "parsing: 1");
+
+    let (state, _) = (0..4).progress().next().unwrap();
+    assert_eq!(state.label(), None);
+}
+
+#[test]
+fn multi_progress() {
+    let (sink, buf) = SharedBuf::sink();
+    let multi = MultiProgress::new(sink).with_output_mode(OutputMode::Plain);
+    let clock = MockClock::new();
+    let mut download = (0..10)
+        .progress()
+        .with_clock(clock.clone())
+        .with_label("download".to_string());
+    let mut parse = (0..30).progress().with_clock(clock.clone());
+    multi.add(&mut download);
+    multi.add(&mut parse);
+    clock.advance(Duration::from_secs(2));
+    download.nth(4).unwrap();
+    parse.nth(9).unwrap();
+
+    let lines = multi.lines();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("download:  50.0% 5 items, 2.5 items/sec, "));
+    assert!(lines[1].starts_with(" 33.3% 10 items, 5.0 items/sec, "));
+    assert_eq!(lines[2], "Total:  37.5% 15 / 40 items, 7.5 items/sec");
+    assert!(!multi.is_finished());
+
+    multi.draw();
+    multi.draw();
+    let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    assert_eq!(output.lines().count(), 6);
+    assert!(!output.contains('\x1b'));
+
+    download.for_each(|_| {});
+    parse.for_each(|_| {});
+    assert!(multi.is_finished());
+
+    let (sink, buf) = SharedBuf::sink();
+    let multi = MultiProgress::new(sink).with_output_mode(OutputMode::Interactive);
+    multi.add_handle(None, ProgressHandle::default());
+    multi.draw();
+    multi.draw();
+    let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    assert!(output.starts_with("\r0 items, 0.0 items/sec, "));
+    assert_eq!(output.matches("\x1b[2A").count(), 1);
+    assert!(output.ends_with("\rTotal: 0 items, 0.0 items/sec\x1b[K\n"));
+}
+
+#[test]
+fn multi_progress_tree() {
+    let (sink, buf) = SharedBuf::sink();
+    let multi = MultiProgress::new(sink).with_output_mode(OutputMode::Interactive);
+    let job = ProgressTree::new("job");
+    multi.add_tree(&job);
+    // Just the tree, without a total
+    assert_eq!(multi.lines(), vec!["job 0 items"]);
+
+    let mut download = (0..4).progress().with_label("download".to_string());
+    job.add_recorder("unused", 1., &mut download);
+    let mut other = (0..2).progress();
+    multi.add(&mut other);
+    download.nth(1).unwrap();
+    let lines = multi.lines();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "job [##########          ]  50.0%");
+    assert_eq!(lines[1], "  download [##########          ]  50.0%");
+    assert!(lines[2].starts_with("0 items, "));
+    // Only the iterators added directly count towards the total
+    assert_eq!(lines[3], "Total: 0 items, 0.0 items/sec");
+
+    multi.draw();
+    multi.draw();
+    let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    assert_eq!(output.matches("\x1b[4A").count(), 1);
+
+    download.for_each(|_| {});
+    assert!(!multi.is_finished());
+    other.for_each(|_| {});
+    assert!(multi.is_finished());
+}
+
+#[test]
+fn multi_progress_draw_every() {
+    let (sink, buf) = SharedBuf::sink();
+    let multi = MultiProgress::new(sink)
+        .with_output_mode(OutputMode::Plain)
+        .draw_every(Duration::from_millis(10));
+    let mut progressor = (0..3).progress().with_label("job".to_string());
+    multi.add(&mut progressor);
+    std::thread::spawn(move || progressor.for_each(|_| {}))
+        .join()
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    drop(multi);
+    let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    assert!(output.lines().count() >= 4);
+    // The final draw, when dropped
+    assert!(output
+        .lines()
+        .last()
+        .unwrap()
+        .starts_with("Total: 100.0% 3 / 3 items, "));
+}
+
+#[test]
+fn nested_progress() {
+    let files = [vec![1, 2, 3, 4], vec![5, 6], vec![7, 8, 9, 10]];
+    let mut outer = files.iter().progress();
+    let nested = outer.nested();
+    // Nothing's known until the first item
+    assert_eq!(nested.fraction(), None);
+    assert_eq!(nested.eta(), None);
+
+    let mut parent_fractions = Vec::new();
+    let (_, file) = outer.next().unwrap();
+    let mut inner = file.iter().progress().with_parent(&nested);
+    assert_eq!(nested.num_done(), 0.);
+    parent_fractions.extend(
+        inner
+            .by_ref()
+            .map(|(state, _)| state.parent_fraction().unwrap()),
+    );
+    assert_eq!(
+        parent_fractions,
+        vec![1. / 12., 2. / 12., 3. / 12., 4. / 12.]
+    );
+    assert_eq!(nested.num_done(), 1.);
+
+    // The previous inner iterator doesn't count for the next item
+    let (_, file) = outer.next().unwrap();
+    assert_eq!(nested.num_done(), 1.);
+    let mut inner = file.iter().progress().with_parent(&nested);
+    let (state, _) = inner.next().unwrap();
+    assert_eq!(state.parent_fraction(), Some(0.5));
+    assert_eq!(nested.percent(), Some(50.));
+    assert!(nested.eta().is_some());
+    let render = nested.render();
+    assert!(render.starts_with(" 50.0% 1.5 / 3 items"));
+    assert!(render.contains("\n   50.0% 1 items, "));
+    inner.for_each(|_| {});
+
+    let (_, file) = outer.next().unwrap();
+    file.iter().progress().with_parent(&nested).for_each(|_| {});
+    assert_eq!(nested.fraction(), Some(1.));
+    assert!(outer.next().is_none());
+    assert_eq!(nested.fraction(), Some(1.));
+    assert_eq!(nested.eta(), Some(Duration::ZERO));
+
+    let (state, _) = (0..3).progress().next().unwrap();
+    assert_eq!(state.parent_fraction(), None);
+}
+
+#[test]
+fn progress_retry() {
+    // An iterator which fails the first `fails` times it tries each item
+    fn flaky(items: Vec<(u8, usize)>) -> impl Iterator<Item = Result<u8, u8>> {
+        items
+            .into_iter()
+            .flat_map(|(item, fails)| (0..fails).map(move |_| Err(item)).chain(Some(Ok(item))))
+    }
+
+    let mut progressor = flaky(vec![(1, 0), (2, 2), (3, 0)]).progress_retry(2);
+    let handle = progressor.recorder.handle();
+    let (state, val) = progressor.next().unwrap();
+    assert_eq!((val, state.num_done(), state.num_retries()), (1, 1, 0));
+    // Retried twice, then worked
+    let (state, val) = progressor.next().unwrap();
+    assert_eq!((val, state.num_done(), state.num_retries()), (2, 2, 2));
+    assert_eq!(state.num_failed(), 0);
+    let (state, val) = progressor.next().unwrap();
+    assert_eq!((val, state.num_done(), state.num_retries()), (3, 3, 2));
+    assert!(!handle.is_finished());
+    assert!(progressor.next().is_none());
+    assert!(handle.is_finished());
+    assert_eq!(handle.num_done(), 3);
+
+    // Each `Err` is one try. After `max_retries` retries the item is dropped, & not counted
+    let results: Vec<Result<u8, ()>> = vec![
+        Err(()),
+        Err(()),
+        Ok(1),
+        Err(()),
+        Ok(2),
+        Err(()),
+        Err(()),
+        Err(()),
+        Ok(3),
+    ];
+    let progressor = results.into_iter().progress_retry(1);
+    assert_eq!(progressor.size_hint(), (0, Some(9)));
+    let results = progressor
+        .map(|(state, val)| {
+            (
+                val,
+                state.num_done(),
+                state.num_retries(),
+                state.num_failed(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(results, vec![(1, 1, 1, 1), (2, 2, 2, 1), (3, 3, 4, 2)]);
+
+    // Failed items count towards the fraction, so it gets to 1, even when the total is only
+    // known from the upper bound
+    let results: Vec<Result<u8, ()>> = vec![Ok(1), Err(()), Ok(2), Err(()), Ok(3)];
+    let mut progressor = results.into_iter().filter(|_| true).progress_retry(0);
+    let handle = progressor.recorder.handle();
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.fraction(), Some(0.2));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!((state.num_done(), state.num_failed()), (2, 1));
+    assert_eq!(state.fraction(), Some(0.6));
+    assert_eq!(state.remaining(), Some(2));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!((state.num_done(), state.num_failed()), (3, 2));
+    assert_eq!(state.fraction(), Some(1.));
+    assert_eq!(state.eta(), Some(Duration::ZERO));
+    assert!(progressor.next().is_none());
+    assert_eq!(handle.fraction(), Some(1.));
+    assert_eq!(progressor.finish().num_failed(), 2);
+
+    // Ending with a failure, the last record can't know about it, but the summary does
+    let results: Vec<Result<u8, ()>> = vec![Ok(1), Ok(2), Err(())];
+    let mut progressor = results.into_iter().filter(|_| true).progress_retry(0);
+    let (state, _) = progressor.by_ref().last().unwrap();
+    assert_eq!(state.fraction(), Some(2. / 3.));
+    let summary = progressor.finish();
+    assert_eq!((summary.num_done(), summary.num_failed()), (2, 1));
+    assert_eq!(summary.fraction(), Some(1.));
+
+    let progressor = vec![Err::<u8, _>(()), Err(()), Err(())]
+        .into_iter()
+        .progress_retry(0);
+    assert_eq!(progressor.count(), 0);
+}
+
+#[cfg(feature = "opentelemetry")]
+#[test]
+fn export_otel() {
+    use opentelemetry::metrics::{
+        Counter, Gauge, InstrumentBuilder, InstrumentProvider, Meter, SyncInstrument,
+    };
+    use opentelemetry::KeyValue;
+
+    type Measurements = Arc<Mutex<Vec<(String, f64)>>>;
+
+    /// Keeps every measurement, with the instrument's name
+    struct Recorder(String, Measurements);
+
+    impl SyncInstrument<u64> for Recorder {
+        fn measure(&self, measurement: u64, _attributes: &[KeyValue]) {
+            let measurement = (self.0.clone(), measurement as f64);
+            self.1.lock().unwrap().push(measurement);
+        }
+    }
+
+    impl SyncInstrument<f64> for Recorder {
+        fn measure(&self, measurement: f64, _attributes: &[KeyValue]) {
+            let measurement = (self.0.clone(), measurement);
+            self.1.lock().unwrap().push(measurement);
+        }
+    }
+
+    struct Provider(Measurements);
+
+    impl Provider {
+        fn recorder(&self, name: &str) -> Arc<Recorder> {
+            Arc::new(Recorder(name.to_string(), self.0.clone()))
+        }
+    }
+
+    impl InstrumentProvider for Provider {
+        fn u64_counter(&self, builder: InstrumentBuilder<'_, Counter<u64>>) -> Counter<u64> {
+            Counter::new(self.recorder(&builder.name))
+        }
+        fn f64_gauge(&self, builder: InstrumentBuilder<'_, Gauge<f64>>) -> Gauge<f64> {
+            Gauge::new(self.recorder(&builder.name))
+        }
+    }
+
+    let shared = Measurements::default();
+    let meter = Meter::new(Arc::new(Provider(shared.clone())));
+    let clock = MockClock::new();
+    let mut progressor = (0..100)
+        .progress()
+        .with_clock(clock.clone())
+        .export_otel(&meter, 10);
+    for _ in 0..95 {
+        clock.advance(Duration::from_millis(100));
+        progressor.next().unwrap();
+    }
+    let values = |name: &str| {
+        shared
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|&(_, value)| value)
+            .collect::<Vec<_>>()
+    };
+
+    // Only every 10 records, not every item
+    let items = values("iter_progress.items");
+    assert_eq!(items, vec![10.; 9]);
+    let percent = values("iter_progress.percent");
+    assert_eq!(percent.len(), 9);
+    assert_eq!(percent[0], 10.);
+    assert_eq!(percent[8], 90.);
+    let rate = values("iter_progress.rate");
+    assert_eq!(rate.len(), 9);
+    assert!(
+        rate.iter().all(|&rate| (rate - 10.).abs() < 1e-9),
+        "{:?}",
+        rate
+    );
+
+    // The last 5 items are recorded when finished, even though that's not every 10 records
+    progressor.finish();
+    let items = values("iter_progress.items");
+    assert_eq!(items.len(), 10);
+    assert_eq!(items.iter().sum::<f64>(), 95.);
+    assert_eq!(values("iter_progress.percent").last(), Some(&95.));
+}
+
+#[test]
+fn phase_rates() {
+    let mut progressor = (0..100).progress().with_phase_rates(vec![10., 1.]);
+    let (state, _) = progressor.nth(19).unwrap();
+    assert_eq!(state.num_done(), 20);
+    // 30 items at 10/sec, then 50 at 1/sec
+    assert_eq!(state.eta(), Some(Duration::from_secs(53)));
+
+    let (state, _) = progressor.nth(59).unwrap();
+    assert_eq!(state.num_done(), 80);
+    assert_eq!(state.eta(), Some(Duration::from_secs(20)));
+
+    // Total not known, so no ETA
+    let mut progressor = (0..).progress().with_phase_rates(vec![10., 1.]);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.eta(), None);
+
+    // A phase with no rate, falls back to the overall rate
+    let mut progressor = (0..100).progress().with_phase_rates(vec![10., 0.]);
+    let mut fake_now = progressor.started_iterating;
+    fake_now += Duration::from_secs(5);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.eta(), Some(Duration::from_secs(5 * 99)));
+
+    // A phase rate so slow the ETA is too big for a `Duration` doesn't panic, or fall back to
+    // the overall rate
+    let mut progressor = (0..100).progress().with_phase_rates(vec![1e-300]);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.phase_eta(), Some(Duration::MAX));
+
+    let mut progressor = (0..1_000).progress().with_phase_rates(vec![1e-17]);
+    let mut fake_now = progressor.started_iterating;
+    fake_now += Duration::from_secs(5);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.eta(), Some(Duration::MAX));
+    assert_eq!(state.estimated_total_time(), Some(Duration::MAX));
+    assert_eq!(state.eta_instant(), None);
+    assert_eq!(state.estimated_completion_time(), None);
+}
+
+#[cfg(feature = "ratatui")]
+#[test]
+fn progress_widget() {
+    use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+    let render = |widget: ProgressWidget| {
+        let area = Rect::new(0, 0, 50, 1);
+        let mut buf = Buffer::empty(area);
+        widget.render(area, &mut buf);
+        buf.content().iter().map(|c| c.symbol()).collect::<String>()
+    };
+
+    let mut progressor = (0..100).progress();
+    let mut fake_now = progressor.started_iterating;
+    fake_now += Duration::from_secs(10);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    let line = render(ProgressWidget::from(&state));
+    assert!(
+        line.contains("1.0%, ETA 16m 30s, 0.1 items/sec"),
+        "{}",
+        line
+    );
+
+    let (state, _) = (0..).progress().next().unwrap();
+    let line = render(ProgressWidget::from(&state));
+    assert!(line.contains("1 items, "), "{}", line);
+
+    let tree = ProgressTree::new("job");
+    tree.set_progress(1, 4);
+    let line = render(ProgressWidget::from(&tree));
+    assert!(line.contains("job: 25.0%"), "{}", line);
+
+    // Nothing to do is finished, rather than 0 / 0
+    tree.set_progress(0, 0);
+    let line = render(ProgressWidget::from(&tree));
+    assert!(line.contains("job: 100.0%"), "{}", line);
+
+    // A fraction which isn't a number draws an empty gauge, rather than panicking
+    let (mut state, _) = (0..10).progress().next().unwrap();
+    state.assume_fraction(f64::NAN);
+    render(ProgressWidget::from(&state));
+}
+
+#[test]
+fn fraction_with_wrong_assumed_size() {
+    // The lower bound of the size hint is always 0, so the assumed size is used
+    let mut progressor = (0..20).filter(|_| true).progress().assume_size(10);
+    let (state, _) = progressor.nth(9).unwrap();
+    assert_eq!(state.num_done(), 10);
+    assert_eq!(state.fraction(), Some(10. / 11.));
+    let (state, _) = progressor.nth(4).unwrap();
+    assert_eq!(state.fraction(), Some(15. / 16.));
+    let (state, _) = progressor.last().unwrap();
+    assert_eq!(state.num_done(), 20);
+    assert_eq!(state.fraction(), Some(1.));
+
+    // Exhausted before the assumed size, so the size hint is exact
+    let (state, _) = (0..5)
+        .filter(|_| true)
+        .progress()
+        .assume_size(10)
+        .last()
+        .unwrap();
+    assert_eq!(state.fraction(), Some(1.));
+
+    // Not known to be exhausted
+    let (state, _) = (0..)
+        .take_while(|&x| x < 10)
+        .progress()
+        .assume_size(10)
+        .last()
+        .unwrap();
+    assert_eq!(state.fraction(), Some(10. / 11.));
+}
+
+#[test]
+fn finish_into() {
+    let mut summary = None;
+    for _ in (0..5).progress().finish_into(&mut summary) {}
+    let summary = summary.unwrap();
+    assert_eq!(summary.num_done(), 5);
+    assert_eq!(summary.fraction(), Some(1.));
+
+    let mut summary = None;
+    for (_, num) in (0..5).progress().finish_into(&mut summary) {
+        if num == 2 {
+            break;
+        }
+    }
+    assert_eq!(summary.unwrap().num_done(), 3);
+
+    let mut summary = None;
+    for _ in (0..0).progress().finish_into(&mut summary) {}
+    assert!(summary.is_none());
+}
+
+#[test]
+fn eta_adaptive() {
+    let config = ProgressConfig::default()
+        .with_rolling_average(2)
+        .with_eta_strategy(EtaStrategy::Adaptive);
+    let mut progressor = (0..10).progress_with(config);
+    let mut fake_now = progressor.started_iterating;
+    // 4 items at 1 sec each, then 1 item at 0.25 sec
+    for _ in 0..4 {
+        fake_now += Duration::from_secs(1);
+        progressor.set_fake_now(fake_now);
+        progressor.next().unwrap();
+    }
+    fake_now += Duration::from_millis(250);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+
+    // 5 items in 4.25 sec. The last 2 took 1.25 sec
+    assert_eq!(state.fraction(), Some(0.5));
+    let rate = 0.5 * (5. / 4.25) + 0.5 * (2. / 1.25);
+    assert_eq!(
+        state.eta_adaptive(),
+        Some(Duration::from_secs_f64(5. / rate))
+    );
+    assert_eq!(state.eta(), state.eta_adaptive());
+
+    // Without a recent rate, it's the same as the naive ETA
+    let mut progressor = (0..10).progress();
+    let mut fake_now = progressor.started_iterating;
+    fake_now += Duration::from_secs(2);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.eta_adaptive(), Some(Duration::from_secs(18)));
+    assert_eq!(state.eta(), Some(Duration::from_secs(18)));
+
+    // No rate yet
+    let mut progressor = (0..10).progress();
+    let started_iterating = progressor.started_iterating;
+    progressor.set_fake_now(started_iterating);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.eta_adaptive(), None);
+}
+
+#[test]
+fn borrowing_iterators() {
+    let nums = [1, 2, 3];
+    let mut total = 0;
+    for (state, &num) in nums.iter().progress() {
+        total += num;
+        assert_eq!(state.num_done(), num);
+    }
+    assert_eq!(total, 6);
+    // Still ours
+    assert_eq!(nums.len(), 3);
+
+    // Part of an iterator, and carry on with the rest
+    let mut iter = nums.iter();
+    let firsts = iter
+        .by_ref()
+        .progress()
+        .take(2)
+        .map(|(_, &num)| num)
+        .collect::<Vec<_>>();
+    assert_eq!(firsts, vec![1, 2]);
+    assert_eq!(iter.next(), Some(&3));
+
+    // A custom iterator, borrowing from something else
+    struct Evens<'a> {
+        nums: &'a [u32],
+        pos: usize,
+    }
+    impl<'a> Iterator for Evens<'a> {
+        type Item = &'a u32;
+        fn next(&mut self) -> Option<&'a u32> {
+            let nums = self.nums;
+            let (offset, num) = nums[self.pos..]
+                .iter()
+                .enumerate()
+                .find(|(_, num)| *num % 2 == 0)?;
+            self.pos += offset + 1;
+            Some(num)
+        }
+    }
+    let nums = [1, 2, 3, 4, 5, 6];
+    let evens = Evens {
+        nums: &nums,
+        pos: 0,
+    };
+    let mut progressor = evens.progress_counting(|&&num| num as usize);
+    let (state, &num) = progressor.next().unwrap();
+    assert_eq!((state.num_done(), num), (2, 2));
+    let results = progressor
+        .map(|(state, &num)| (state.num_done(), num))
+        .collect::<Vec<_>>();
+    assert_eq!(results, vec![(6, 4), (12, 6)]);
+}
+
+#[test]
+fn progress_weighted() {
+    let weights = vec![6, 1, 3];
+    let mut progressor = weights
+        .into_iter()
+        .progress_weighted(|w| *w)
+        .assume_size(10);
+    let start = progressor.started_iterating;
+    progressor.set_fake_now(start + Duration::from_secs(2));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.num_done(), 6);
+    assert_eq!(state.fraction(), Some(0.6));
+    assert_eq!(state.rate(), 3.);
+    // The rest of the weight, 4, at 3 per second
+    assert!((state.eta().unwrap().as_secs_f64() - 4. / 3.).abs() < 1e-6);
+    let (state, _) = progressor.last().unwrap();
+    assert_eq!(state.num_done(), 10);
+    assert_eq!(state.fraction(), Some(1.));
+}
+
+#[test]
+fn progress_counting_borrows() {
+    // The counting function can borrow locals, and needn't be `Send`
+    let sizes = std::rc::Rc::new(vec![10, 30, 60]);
+    let results = (0..3)
+        .progress_counting(|&i| sizes[i])
+        .map(|(state, _)| state.num_done())
+        .collect::<Vec<_>>();
+    assert_eq!(results, vec![10, 40, 100]);
+
+    let mut seen = Vec::new();
+    let mut progressor = (0..3).rev().progress_accumulate(&mut seen, |seen, &i| {
+        seen.push(i);
+        sizes[i]
+    });
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.num_done(), 60);
+    assert_eq!(progressor.last().unwrap().0.num_done(), 100);
+    assert_eq!(seen, vec![2, 1, 0]);
+}
+
+#[test]
+fn every_n_items_with_weights() {
+    // A zero weight first (so `.num_done()` is 0), then ones which jump past multiples of 2
+    let weights = vec![0, 3, 5, 0, 2];
+    let every_2 = weights
+        .into_iter()
+        .progress_weighted(|w| *w)
+        .map(|(state, _)| state.should_do_every_n_items(2))
+        .collect::<Vec<_>>();
+    assert_eq!(every_2, vec![true, false, true, false, true]);
+
+    let every_3 = (0..7)
+        .progress_accumulate(0, |_, _| 1_000)
+        .map(|(state, _)| state.should_do_every_n_items(3))
+        .collect::<Vec<_>>();
+    assert_eq!(every_3, vec![true, false, false, true, false, false, true]);
+}
+
+#[test]
+fn progress_accumulate() {
+    // Offsets into a 1,000 byte file, which only move forward
+    let offsets = vec![100, 250, 250, 600, 1_000];
+    let mut progressor = offsets
+        .into_iter()
+        .progress_accumulate(0, |last_offset, offset| {
+            let delta = offset - *last_offset;
+            *last_offset = *offset;
+            delta
+        })
+        .assume_size(1_000);
+    let handle = progressor.handle();
+    let start = progressor.started_iterating;
+
+    progressor.set_fake_now(start + Duration::from_secs(1));
+    let (state, offset) = progressor.next().unwrap();
+    assert_eq!((offset, state.num_done()), (100, 100));
+    assert_eq!(state.rate(), 100.);
+
+    progressor.set_fake_now(start + Duration::from_secs(2));
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.num_done(), 250);
+    assert_eq!(state.fraction(), Some(0.25));
+    assert_eq!(state.rate(), 125.);
+    // The rest, 750, at 125 per second
+    assert_eq!(state.eta(), Some(Duration::from_secs(6)));
+
+    // No progress for this item
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.num_done(), 250);
+
+    let (state, _) = progressor.by_ref().last().unwrap();
+    assert_eq!(state.num_done(), 1_000);
+    assert_eq!(state.fraction(), Some(1.));
+    assert!(progressor.next().is_none());
+    assert!(handle.is_finished());
+}
+
+#[test]
+fn geomean_rate() {
+    let mut progressor = (0..).progress().with_history(None);
+    assert_eq!(progressor.geomean_rate(), None);
+    let mut fake_now = progressor.started_iterating;
+    // Rates of 10/sec, 1/sec & 0.1/sec, with a repeated time skipped
+    for gap in &[0, 100, 1_000, 0, 10_000] {
+        fake_now += Duration::from_millis(*gap);
+        progressor.set_fake_now(fake_now);
+        progressor.next().unwrap();
+    }
+    assert_eq!(progressor.samples().len(), 5);
+    assert!((progressor.geomean_rate().unwrap() - 1.).abs() < 1e-9);
+}
+
+/// Pins the rate & ETA calculations to exact values, for scripted item times
+mod rate_semantics {
+    use super::*;
+
+    /// Run `progressor` with items at these times (in seconds since starting), returning the
+    /// last record
+    fn run<I: Iterator>(mut progressor: ProgressRecorderIter<I>, times: &[f64]) -> ProgressRecord {
+        let started_iterating = progressor.started_iterating;
+        let mut last = None;
+        for &t in times {
+            progressor.set_fake_now(started_iterating + Duration::from_secs_f64(t));
+            last = Some(progressor.next().unwrap().0);
+        }
+        last.unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} is not close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn first_item() {
+        let state = run((0..10).progress(), &[0.]);
+        assert_eq!(state.num_done(), 1);
+        assert_eq!(state.rate(), 0.);
+        assert_eq!(state.rate_per(Duration::from_secs(60)), 0.);
+        assert_eq!(state.recent_rate(), None);
+        assert_eq!(state.eta(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn sub_second() {
+        let state = run((0..).progress(), &[0.001]);
+        assert_close(state.rate(), 1_000.);
+        let state = run((0..1_000).progress(), &[0.001, 0.002, 0.003, 0.004]);
+        assert_close(state.rate(), 1_000.);
+        assert_close(state.eta().unwrap().as_secs_f64(), 0.996);
+
+        // Every quarter second
+        let mut progressor = (0..).progress();
+        let started_iterating = progressor.started_iterating;
+        let mut done_at = vec![];
+        for ms in (100..=1_000).step_by(100) {
+            progressor.set_fake_now(started_iterating + Duration::from_millis(ms));
+            let (state, _) = progressor.next().unwrap();
+            if state.should_do_every_n_sec(0.25) {
+                done_at.push(ms);
+            }
+        }
+        assert_eq!(done_at, vec![300, 500, 800, 1_000]);
+    }
+
+    #[test]
+    fn print_at_most_every_n_sec() {
+        // 10,000 items in 3 seconds
+        let mut progressor = (0..).progress();
+        let started_iterating = progressor.started_iterating;
+        let mut printed_at = vec![];
+        for us in (300..=3_000_000).step_by(300) {
+            progressor.set_fake_now(started_iterating + Duration::from_micros(us));
+            let (state, _) = progressor.next().unwrap();
+            if state.should_print_every_n_sec(1.) {
+                printed_at.push(us);
+            }
+        }
+        // The first item at least a second after the start, then after that print
+        assert_eq!(printed_at, vec![1_000_200, 2_000_400]);
+    }
+
+    #[test]
+    fn print_every_n_sec_since_last_print() {
+        // Either side of the 2 second boundary, but not a second apart
+        let mut progressor = (0..).progress();
+        let started_iterating = progressor.started_iterating;
+        let mut printed_at = vec![];
+        for ms in [500, 1_500, 1_900, 2_100, 2_400, 2_600, 3_000] {
+            progressor.set_fake_now(started_iterating + Duration::from_millis(ms));
+            let (state, _) = progressor.next().unwrap();
+            if state.should_print_every_n_sec(1.) {
+                printed_at.push(ms);
+            }
+            // Asking again, for the same record, doesn't print twice
+            assert!(!state.should_print_every_n_sec(1.));
+        }
+        assert_eq!(printed_at, vec![1_500, 2_600]);
+    }
+
+    #[test]
+    fn print_every_n_sec_too_long() {
+        // Too long for a `Duration`, so never, rather than panicking
+        let mut progressor = (0..).progress();
+        let started_iterating = progressor.started_iterating;
+        progressor.set_fake_now(started_iterating + Duration::from_secs(1_000_000));
+        let (state, _) = progressor.next().unwrap();
+        assert!(!state.should_print_every_n_sec(f32::INFINITY));
+        assert!(!state.should_print_every_n_sec(1e20));
+        assert!(state.should_print_every_n_sec(1.));
+    }
+
+    #[test]
+    fn steady_known_size() {
+        let state = run(
+            (0..10).progress().with_rolling_average(2),
+            &[0., 1., 2., 3., 4.],
+        );
+        assert_eq!(state.num_done(), 5);
+        assert_eq!(state.rate(), 1.25);
+        assert_eq!(state.rate_per(Duration::from_secs(60)), 75.);
+        assert_eq!(state.recent_rate(), Some(1.));
+        assert_eq!(state.fraction(), Some(0.5));
+        assert_eq!(state.eta(), Some(Duration::from_secs(4)));
+        assert_eq!(state.estimated_total_time(), Some(Duration::from_secs(8)));
+    }
+
+    #[test]
+    fn steady_unknown_size() {
+        let state = run(
+            (0..).progress().with_rolling_average(2),
+            &[0., 1., 2., 3., 4.],
+        );
+        assert_eq!(state.rate(), 1.25);
+        assert_eq!(state.rate_per(Duration::from_secs(60)), 75.);
+        assert_eq!(state.recent_rate(), Some(1.));
+        assert_eq!(state.fraction(), None);
+        assert_eq!(state.eta(), None);
+    }
+
+    #[test]
+    fn uneven_known_size() {
+        let state = run((0..10).progress().with_rolling_average(2), &[0., 0.5, 0.7]);
+        assert_eq!(state.num_done(), 3);
+        assert_close(state.rate(), 3. / 0.7);
+        assert_close(state.rate_per(Duration::from_secs(60)), 180. / 0.7);
+        assert_close(state.recent_rate().unwrap(), 1. / 0.35);
+        assert_close(state.eta().unwrap().as_secs_f64(), 0.7 / 0.3 - 0.7);
+    }
+
+    #[test]
+    fn uneven_unknown_size() {
+        let state = run((0..).progress().with_rolling_average(2), &[0., 0.5, 0.7]);
+        assert_close(state.rate(), 3. / 0.7);
+        assert_close(state.recent_rate().unwrap(), 1. / 0.35);
+        assert_eq!(state.eta(), None);
+    }
+
+    #[test]
+    fn assumed_fractions() {
+        let mut state = run((0..).progress(), &[0., 2.]);
+        state.assume_fraction(0.);
+        assert_eq!(state.eta(), None);
+        state.assume_fraction(0.25);
+        assert_eq!(state.eta(), Some(Duration::from_secs(6)));
+        state.assume_fraction(1.5);
+        assert_eq!(state.eta(), Some(Duration::ZERO));
+        state.assume_fraction(f64::NAN);
+        assert_eq!(state.eta(), None);
+    }
+}
+
+#[test]
+fn recent_window_items_or_duration() {
+    let gaps = [10, 10, 1, 1, 1];
+    let rates = |progressor: ProgressRecorderIter<std::ops::RangeFrom<i32>>| {
+        let mut progressor = progressor;
+        let mut fake_now = progressor.started_iterating;
+        gaps.iter()
+            .map(|gap| {
+                fake_now += Duration::from_secs(*gap);
+                progressor.set_fake_now(fake_now);
+                progressor.next().unwrap().0.recent_rate()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        rates((0..).progress().recent_window_items(2)),
+        [None, Some(0.1), Some(2. / 11.), Some(1.), Some(1.)]
+    );
+    assert_eq!(
+        rates(
+            (0..)
+                .progress()
+                .recent_window_duration(Duration::from_secs(4))
+        ),
+        [None, Some(0.1), Some(1.), Some(1.), Some(1.)]
+    );
+}
+
+#[test]
+fn exp_average() {
+    let mut progressor = (0..).progress().with_exp_average(0.5);
+    let mut fake_now = progressor.started_iterating;
+    let rates = [1, 1, 3, 3]
+        .iter()
+        .map(|gap| {
+            fake_now += Duration::from_secs(*gap);
+            progressor.set_fake_now(fake_now);
+            let (state, _) = progressor.next().unwrap();
+            assert_eq!(state.rate_ewma(), state.exp_average_rate());
+            state.rate_ewma()
+        })
+        .collect::<Vec<_>>();
+    // Durations averaged: 1, then 1, then (3 + 1) / 2, then (3 + 2) / 2
+    assert_eq!(rates, [None, Some(1.), Some(0.5), Some(0.4)]);
+}
+
+#[test]
+fn assumed_size_for_unknown_size_hint() {
+    // Like a database cursor, after a `COUNT` query
+    let rows = (0..100).filter(|_| true);
+    assert_eq!(rows.size_hint(), (0, Some(100)));
+    let mut progressor = rows.progress().assume_size(100);
+    let mut fake_now = progressor.started_iterating;
+    fake_now += Duration::from_secs(5);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.fraction(), Some(0.01));
+    assert_eq!(state.percent(), Some(1.));
+    assert_eq!(state.eta(), Some(Duration::from_secs(5 * 99)));
+    assert_eq!(state.count_of_total(), "1 / 100");
+}
+
+#[test]
+fn inspect_progress() {
+    let mut seen = vec![];
+    let items = (10..15)
+        .inspect_progress(|state| seen.push(state.num_done()))
+        .collect::<Vec<_>>();
+    assert_eq!(items, vec![10, 11, 12, 13, 14]);
+    assert_eq!(seen, vec![1, 2, 3, 4, 5]);
+
+    let mut fractions = vec![];
+    let count = (0..)
+        .take_while(|&num| num < 4)
+        .progress()
+        .assume_size(4)
+        .inspect_progress(|state| fractions.push(state.fraction()))
+        .count();
+    assert_eq!(count, 4);
+    assert_eq!(fractions[0], Some(0.25));
+}
+
+#[test]
+fn progress_bar() {
+    let mut progressor = (0..1_000).progress().with_unicode(false);
+    let mut fake_now = progressor.started_iterating;
+    progressor.nth(248);
+    fake_now += Duration::from_secs(25);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(
+        state.bar_line(&BarStyle::default()),
+        "[#####               ]  25.0% 250 / 1,000, 10.0 items/sec, ETA 1m 15s"
+    );
+
+    let mut progressor = (0..).progress();
+    let mut fake_now = progressor.started_iterating;
+    fake_now += Duration::from_secs(3_725);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(
+        state.bar_line(&BarStyle::default()),
+        "1 items, 0.0 items/sec, 1h 02m 05s elapsed"
+    );
+
+    // A spinner when interactive
+    let mut progressor = (0..).progress().with_output_mode(OutputMode::Interactive);
+    let started_iterating = progressor.started_iterating;
+    let mut lines = vec![];
+    for ms in &[0, 100, 250] {
+        progressor.set_fake_now(started_iterating + Duration::from_millis(*ms));
+        lines.push(progressor.next().unwrap().0.bar_line(&BarStyle::default()));
+    }
+    assert!(lines[0].starts_with("⠋ 1 items, "), "{}", lines[0]);
+    assert!(lines[1].starts_with("⠙ 2 items, "), "{}", lines[1]);
+    assert!(lines[2].starts_with("⠹ 3 items, "), "{}", lines[2]);
+    let (state, _) = (0..)
+        .progress()
+        .with_output_mode(OutputMode::Interactive)
+        .with_unicode(false)
+        .next()
+        .unwrap();
+    assert!(state
+        .bar_line(&BarStyle::default())
+        .starts_with("| 1 items"));
+
+    let items = (0..5)
+        .progress()
+        .with_output_mode(OutputMode::Plain)
+        .progress_bar()
+        .collect::<Vec<_>>();
+    assert_eq!(items, vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn bar_style() {
+    let style = BarStyle::default()
+        .filled_color(Color::Green)
+        .unfilled_color(Color::Red)
+        .unfilled_char('-')
+        .bold_percent(true)
+        .brackets('|', '|')
+        .colors(true);
+    let mut progressor = (0..4)
+        .progress()
+        .with_unicode(false)
+        .with_output_mode(OutputMode::Interactive);
+    let (state, _) = progressor.next().unwrap();
+    assert!(state.bar_line(&style).starts_with(
+        "|\x1b[32m#####\x1b[0m\x1b[31m---------------\x1b[0m| \x1b[1m 25.0%\x1b[0m 1 / 4, "
+    ));
+
+    // No colours when not interactive, or turned off
+    let mut progressor = (0..4)
+        .progress()
+        .with_unicode(false)
+        .with_output_mode(OutputMode::Plain);
+    let (state, _) = progressor.next().unwrap();
+    assert!(state
+        .bar_line(&style)
+        .starts_with("|#####---------------|  25.0% 1 / 4, "));
+    let mut progressor = (0..4)
+        .progress()
+        .with_unicode(false)
+        .with_output_mode(OutputMode::Interactive);
+    let (state, _) = progressor.next().unwrap();
+    assert!(state
+        .bar_line(&style.colors(false))
+        .starts_with("|#####---------------|  25.0% 1 / 4, "));
+}
+
+#[test]
+fn format_bytes() {
+    use super::format_bytes;
+    assert_eq!(format_bytes(0., ByteUnits::Binary), "0 B");
+    assert_eq!(format_bytes(1_023., ByteUnits::Binary), "1023 B");
+    assert_eq!(format_bytes(1_024., ByteUnits::Binary), "1.0 KiB");
+    assert_eq!(format_bytes(1_000., ByteUnits::Decimal), "1.0 kB");
+    assert_eq!(format_bytes(999_999., ByteUnits::Decimal), "1.0 MB");
+    assert_eq!(
+        format_bytes(5. * 1024f64.powi(4), ByteUnits::Binary),
+        "5.0 TiB"
+    );
+    assert_eq!(format_byte_rate(2_500_000., ByteUnits::Decimal), "2.5 MB/s");
+
+    let mut progressor = (0..4)
+        .progress_weighted(|_| 1_024 * 1_024)
+        .assume_size(4 * 1_024 * 1_024)
+        .with_unicode(false);
+    let fake_now = progressor.started_iterating + Duration::from_secs(2);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.bytes_of_total(ByteUnits::Binary), "1.0 MiB / 4.0 MiB");
+    assert_eq!(state.byte_rate(ByteUnits::Binary), "512.0 KiB/s");
+    assert_eq!(
+        state.bar_line(&BarStyle::default().byte_units(ByteUnits::Binary)),
+        "[#####               ]  25.0% 1.0 MiB / 4.0 MiB, 512.0 KiB/s, ETA 6s"
+    );
+
+    let (state, _) = (0..).progress_weighted(|_| 2_000).next().unwrap();
+    assert!(state
+        .bar_line(&BarStyle::default().byte_units(ByteUnits::Decimal))
+        .starts_with("2.0 kB, "));
+}
+
+#[test]
+fn progress_bar_output_mode() {
+    // Tests don't run with stderr as a terminal
+    assert!(!(0..3).progress().progress_bar().interactive);
+    let bar = (0..3)
+        .progress()
+        .with_output_mode(OutputMode::Interactive)
+        .progress_bar();
+    assert!(bar.interactive);
+    let bar = (0..3)
+        .progress()
+        .with_output_mode(OutputMode::Plain)
+        .progress_bar();
+    assert!(!bar.interactive);
+
+    // Drawn on the output sink, and the final state is always drawn
+    let (sink, buf) = SharedBuf::sink();
+    let items = (0..5)
+        .progress()
+        .with_output_sink(sink)
+        .with_output_mode(OutputMode::Plain)
+        .with_unicode(false)
+        .progress_bar()
+        .collect::<Vec<_>>();
+    assert_eq!(items, vec![0, 1, 2, 3, 4]);
+    let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    assert!(
+        output.starts_with("[####################] 100.0% 5 / 5, "),
+        "{:?}",
+        output
+    );
+    assert_eq!(output.lines().count(), 1);
+
+    let (sink, buf) = SharedBuf::sink();
+    (0..5)
+        .progress()
+        .with_output_sink(sink)
+        .with_output_mode(OutputMode::Interactive)
+        .progress_bar()
+        .for_each(drop);
+    let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    assert!(
+        output.starts_with('\r') && output.ends_with('\n'),
+        "{:?}",
+        output
+    );
+    assert!(output.contains("100.0% 5 / 5"), "{:?}", output);
+}
+
+/// A writer to a shared buffer, to check output
+struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuf {
+    fn sink() -> (OutputSink, Arc<Mutex<Vec<u8>>>) {
+        let buf = Arc::new(Mutex::new(Vec::new()));
+        (OutputSink::writer(SharedBuf(buf.clone())), buf)
+    }
+}
+
+impl Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn output_sink_writer() {
+    let (sink, buf) = SharedBuf::sink();
+    let iter = (0..5).progress().with_output_sink(sink);
+    assert!(!iter.interactive);
+    for (state, _) in iter {
+        state.print_every_n_items(2, format!("{} ", state.num_done()));
+    }
+    assert_eq!(
+        String::from_utf8(buf.lock().unwrap().clone()).unwrap(),
+        "1 3 5 "
+    );
+}
+
+#[test]
+fn json_lines() {
+    let mut progressor = (0..4).progress();
+    let fake_now = progressor.started_iterating + Duration::from_secs(10);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(
+        state.json_line(),
+        r#"{"done":1,"total":4,"rate":0.1,"eta_secs":30}"#
+    );
+
+    let (state, _) = (0..).filter(|_| true).progress().next().unwrap();
+    assert!(state.json_line().contains(r#""total":null,"#));
+    assert!(state.json_line().ends_with(r#""eta_secs":null}"#));
+
+    let (sink, buf) = SharedBuf::sink();
+    let mut progressor = (0..4)
+        .progress()
+        .emit_json_lines(sink, Duration::from_secs(5));
+    let fake_now = progressor.started_iterating + Duration::from_secs(10);
+    progressor.set_fake_now(fake_now);
+    progressor.next().unwrap();
+    progressor.set_fake_now(fake_now + Duration::from_secs(1));
+    progressor.next().unwrap();
+    progressor.set_fake_now(fake_now + Duration::from_secs(6));
+    progressor.next().unwrap();
+    let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", output);
+    assert!(lines[0].starts_with(r#"{"done":1,"#), "{}", output);
+    assert!(lines[1].starts_with(r#"{"done":3,"#), "{}", output);
+}
+
+#[cfg(feature = "log")]
+#[test]
+fn log_every() {
+    use log::{Level, Log, Metadata, Record};
+
+    static LOGGED: Mutex<Vec<(Level, String)>> = Mutex::new(Vec::new());
+    struct TestLogger;
+    impl Log for TestLogger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.target() == "log_every_test"
+        }
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                let msg = record.args().to_string();
+                LOGGED.lock().unwrap().push((record.level(), msg));
+            }
+        }
+        fn flush(&self) {}
+    }
+    log::set_logger(&TestLogger).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    // Not enough time has passed
+    let mut progressor = (0..100)
+        .progress()
+        .log_every(Duration::from_secs(5), Level::Warn)
+        .with_log_target("log_every_test");
+    progressor.next().unwrap();
+    assert!(LOGGED.lock().unwrap().is_empty());
+
+    let mut progressor = (0..100)
+        .progress()
+        .with_log_target("log_every_test")
+        .log_every(Duration::from_secs(5), Level::Warn);
+    let fake_now = progressor.started_iterating + Duration::from_secs(10);
+    progressor.set_fake_now(fake_now);
+    progressor.next().unwrap();
+    let logged = LOGGED.lock().unwrap();
+    assert_eq!(logged.len(), 1);
+    assert_eq!(logged[0].0, Level::Warn);
+    assert!(
+        logged[0]
+            .1
+            .ends_with("1.0% 1 / 100 items, 0.1 items/sec, ETA 16m 30s"),
+        "{}",
+        logged[0].1
+    );
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn trace_every() {
+    use std::fmt::Debug;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata};
+
+    #[derive(Default)]
+    struct Seen {
+        spans: Vec<String>,
+        events: Vec<String>,
+        enters: usize,
+    }
+    struct Fields(String);
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.push_str(&format!("{}={:?} ", field.name(), value));
+        }
+    }
+    struct TestSubscriber(Arc<Mutex<Seen>>);
+    impl tracing::Subscriber for TestSubscriber {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes) -> Id {
+            let mut fields = Fields(format!("{}: ", span.metadata().name()));
+            span.record(&mut fields);
+            let mut seen = self.0.lock().unwrap();
+            seen.spans.push(fields.0);
+            Id::from_u64(seen.spans.len() as u64)
+        }
+        fn record(&self, _: &Id, _: &Record) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event) {
+            let mut fields = Fields(String::new());
+            event.record(&mut fields);
+            self.0.lock().unwrap().events.push(fields.0);
+        }
+        fn enter(&self, _: &Id) {
+            self.0.lock().unwrap().enters += 1;
+        }
+        fn exit(&self, _: &Id) {}
+    }
+
+    let seen = Arc::new(Mutex::new(Seen::default()));
+    tracing::subscriber::with_default(TestSubscriber(seen.clone()), || {
+        let mut progressor = (0..4).progress().trace_every(Duration::from_secs(5));
+        let fake_now = progressor.started_iterating + Duration::from_secs(10);
+        progressor.set_fake_now(fake_now);
+        progressor.next().unwrap();
+        progressor.for_each(|_| {});
+    });
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.spans, vec!["iter_progress: total=4 "]);
+    // Once for each item, and once for the end
+    assert_eq!(seen.enters, 5);
+    assert_eq!(
+        seen.events,
+        vec!["message=progress count=1 rate=0.1 percent=25.0 eta_secs=30.0 "]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_record() {
+    let mut progressor = (0..4).progress();
+    let fake_now = progressor.started_iterating + Duration::from_secs(10);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(
+        serde_json::to_string(&state).unwrap(),
+        r#"{"done":1,"total":4,"elapsed_secs":10.0,"rate":0.1,"fraction":0.25,"eta_secs":30.0}"#
+    );
+
+    let mut progressor = (0..).filter(|_| true).progress();
+    // Not any time, whose rate might not parse back to exactly the same float
+    let fake_now = progressor.started_iterating + Duration::from_secs(4);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    let json = serde_json::to_value(&state).unwrap();
+    assert_eq!(json["total"], serde_json::Value::Null);
+    assert_eq!(json["fraction"], serde_json::Value::Null);
+    assert_eq!(json["eta_secs"], serde_json::Value::Null);
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&state.to_json()).unwrap(),
+        json
+    );
+}
+
+#[cfg(feature = "prometheus")]
+#[test]
+fn export_prometheus() {
+    let registry = prometheus::Registry::new();
+    let mut progressor = (0..4).progress().export_prometheus(&registry, 2).unwrap();
+    let fake_now = progressor.started_iterating + Duration::from_secs(10);
+    progressor.set_fake_now(fake_now);
+    progressor.next().unwrap();
+    progressor.set_fake_now(fake_now + Duration::from_secs(10));
+    progressor.next().unwrap();
+
+    let value_in = |registry: &prometheus::Registry, name: &str| {
+        let family = registry
+            .gather()
+            .into_iter()
+            .find(|family| family.get_name() == name)
+            .unwrap();
+        let metric = &family.get_metric()[0];
+        if family.get_field_type() == prometheus::proto::MetricType::COUNTER {
+            metric.get_counter().get_value()
+        } else {
+            metric.get_gauge().get_value()
+        }
+    };
+    let value = |name: &str| value_in(&registry, name);
+    assert_eq!(value("iter_progress_items_total"), 2.);
+    assert_eq!(value("iter_progress_rate"), 0.1);
+    assert_eq!(value("iter_progress_fraction"), 0.5);
+    assert_eq!(value("iter_progress_elapsed_seconds"), 20.);
+
+    // Flushed when finished, even though the last item wasn't an update
+    let other = prometheus::Registry::new();
+    let progressor = (0..5).progress().export_prometheus(&other, 2).unwrap();
+    assert_eq!(progressor.last().unwrap().1, 4);
+    assert_eq!(value_in(&other, "iter_progress_items_total"), 5.);
+    assert_eq!(value_in(&other, "iter_progress_fraction"), 1.);
+
+    // Can't register the same metrics twice
+    assert!((0..4).progress().export_prometheus(&registry, 2).is_err());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_progress() {
+    use rayon::prelude::*;
+
+    let records = (0..100)
+        .into_par_iter()
+        .progress()
+        .map(|(state, num)| (state.num_done(), state.fraction(), num))
+        .collect::<Vec<_>>();
+    let mut done = records.iter().map(|r| r.0).collect::<Vec<_>>();
+    done.sort();
+    assert_eq!(done, (1..=100).collect::<Vec<_>>());
+    for (done, fraction, _) in records.iter() {
+        assert_eq!(*fraction, Some(*done as f64 / 100.));
+    }
+    let mut nums = records.iter().map(|r| r.2).collect::<Vec<_>>();
+    nums.sort();
+    assert_eq!(nums, (0..100).collect::<Vec<_>>());
+
+    // Finished once it has all been processed
+    let progressor = (0..10).into_par_iter().progress();
+    let handle = progressor.handle();
+    progressor.for_each(|_| assert!(!handle.is_finished()));
+    assert!(handle.is_finished());
+    assert_eq!(handle.num_done(), 10);
+
+    // Unknown size
+    let (state, _) = (0..100)
+        .into_par_iter()
+        .filter(|_| true)
+        .progress()
+        .max_by_key(|(state, _)| state.num_done())
+        .unwrap();
+    assert_eq!(state.num_done(), 100);
+    assert_eq!(state.fraction(), None);
+
+    let config = ProgressConfig::default().assume_size(200);
+    let (state, _) = (0..100)
+        .into_par_iter()
+        .filter(|_| true)
+        .progress_with(config)
+        .max_by_key(|(state, _)| state.num_done())
+        .unwrap();
+    assert_eq!(state.fraction(), Some(0.5));
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn optional_parallel_progress() {
+    use rayon::prelude::*;
+
+    let progressor = (0..1_000).into_par_iter().optional_progress(100);
+    let handle = progressor.handle();
+    let records = progressor
+        .filter_map(|(state, num)| state.map(|state| (state, num)))
+        .collect::<Vec<_>>();
+    // A record which lost the race to a later one is skipped
+    assert!(!records.is_empty() && records.len() <= 10, "{:?}", records);
+    for (state, _) in records.iter() {
+        assert_eq!(state.num_done() % 100, 0);
+        assert_eq!(state.num_items, state.num_done());
+        assert_eq!(state.fraction(), Some(state.num_done() as f64 / 1_000.));
+    }
+
+    // The last item gets a record, since no later one can beat it
+    assert!(handle.is_finished());
+    assert_eq!(handle.num_done(), 1_000);
+
+    let progressor = (0..1_050).into_par_iter().optional_progress(100);
+    let handle = progressor.handle();
+    progressor.for_each(|_| ());
+    assert!(handle.is_finished());
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn progress_stream() {
+    use futures::{stream, StreamExt};
+
+    let records = futures::executor::block_on(
+        stream::iter(0..4)
+            .progress()
+            .map(|(state, num)| (state.num_done(), state.fraction(), num))
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(
+        records,
+        vec![
+            (1, Some(0.25), 0),
+            (2, Some(0.5), 1),
+            (3, Some(0.75), 2),
+            (4, Some(1.), 3)
+        ]
+    );
+
+    let config = ProgressConfig::default().assume_size(8);
+    let records = futures::executor::block_on(
+        stream::iter(0..4)
+            .filter(|_| futures::future::ready(true))
+            .progress_with(config)
+            .map(|(state, _)| state.fraction())
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(
+        records,
+        vec![Some(0.125), Some(0.25), Some(0.375), Some(1.)]
+    );
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn progress_async_io() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let data = [1u8; 100];
+    let mut reader = ProgressAsyncReader::new(&data[..], None);
+    assert!(reader.record().is_none());
+    let mut buf = [0u8; 30];
+    runtime.block_on(reader.read_exact(&mut buf)).unwrap();
+    let record = reader.record().unwrap();
+    assert_eq!(record.num_done(), 30);
+    assert_eq!(record.fraction(), None);
+    let mut rest = Vec::new();
+    runtime.block_on(reader.read_to_end(&mut rest)).unwrap();
+    assert_eq!(rest.len(), 70);
+    assert_eq!(reader.record().unwrap().num_done(), 100);
+
+    let mut writer = ProgressAsyncWriter::new(Vec::new(), 200);
+    runtime.block_on(writer.write_all(&data)).unwrap();
+    runtime.block_on(writer.write_all(&data[..50])).unwrap();
+    runtime.block_on(writer.flush()).unwrap();
+    assert_eq!(writer.record().unwrap().num_done(), 150);
+    assert_eq!(writer.record().unwrap().fraction(), Some(0.75));
+    assert_eq!(writer.into_inner().len(), 150);
+}
+
+#[test]
+fn progress_reader() {
+    use std::io::{BufRead, Read};
+
+    let data = (0..100).collect::<Vec<u8>>();
+    let mut reader = ProgressReader::new(&data[..], data.len());
+    assert!(reader.record().is_none());
+
+    let mut buf = [0u8; 30];
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(reader.record().unwrap().num_done(), 30);
+    assert_eq!(reader.record().unwrap().fraction(), Some(0.3));
+
+    // BufRead counts what's consumed
+    assert_eq!(reader.fill_buf().unwrap()[0], 30);
+    assert_eq!(reader.record().unwrap().num_done(), 30);
+    reader.consume(20);
+    assert_eq!(reader.record().unwrap().num_done(), 50);
+
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest.len(), 50);
+    assert_eq!(reader.record().unwrap().fraction(), Some(1.));
+}
+
+#[test]
+fn progress_writer() {
+    let data = [1u8; 100];
+    let mut writer = ProgressWriter::new(Vec::new(), 200);
+    assert!(writer.record().is_none());
+
+    writer.write_all(&data).unwrap();
+    assert_eq!(writer.record().unwrap().num_done(), 100);
+    assert_eq!(writer.record().unwrap().fraction(), Some(0.5));
+
+    io::copy(&mut &data[..], &mut writer).unwrap();
+    writer.flush().unwrap();
+    assert_eq!(writer.record().unwrap().fraction(), Some(1.));
+    assert_eq!(writer.into_inner().len(), 200);
+
+    let mut writer = ProgressWriter::new(Vec::new(), None);
+    write!(writer, "hello").unwrap();
+    assert_eq!(writer.record().unwrap().num_done(), 5);
+    assert_eq!(writer.record().unwrap().fraction(), None);
+}
+
+#[test]
+fn progress_lines() {
+    let text = "one\r\ntwo\n\nfour";
+    let lines = ProgressLines::new(text.as_bytes(), text.len())
+        .map(|line| {
+            let (state, line) = line.unwrap();
+            (line, state.num_done(), state.fraction().unwrap())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            ("one".to_string(), 5, 5. / 14.),
+            ("two".to_string(), 9, 9. / 14.),
+            ("".to_string(), 10, 10. / 14.),
+            ("four".to_string(), 14, 1.),
+        ]
+    );
+
+    let path = std::env::temp_dir().join(format!(
+        "iter-progress-test-lines-{}.txt",
+        std::process::id()
+    ));
+    std::fs::write(&path, "a\nb\n").unwrap();
+    let lines = ProgressLines::open(&path)
+        .unwrap()
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1].1, "b");
+    assert_eq!(lines[1].0.fraction(), Some(1.));
+}
+
+#[test]
+fn format_count() {
+    use super::format_count;
+    assert_eq!(format_count(0., 1), "0");
+    assert_eq!(format_count(999., 1), "999");
+    assert_eq!(format_count(1_000., 1), "1.0k");
+    assert_eq!(format_count(999_949., 1), "999.9k");
+    assert_eq!(format_count(999_950., 1), "1.0M");
+    assert_eq!(format_count(999_500., 0), "1M");
+    assert_eq!(format_count(123_456_789., 2), "123.46M");
+    assert_eq!(format_count(3e9, 1), "3.0B");
+    assert_eq!(format_count(12.345, 2), "12.35");
+
+    let mut progressor = (0..200_000_000).progress().with_unicode(false);
+    progressor.nth(44_999);
+    let fake_now = progressor.started_iterating + Duration::from_secs(1);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    assert_eq!(state.short_count_of_total(1), "45.0k / 200.0M");
+    assert_eq!(state.short_rate(1), "45.0k/s");
+    assert!(state
+        .bar_line(&BarStyle::default().short_counts(1))
+        .contains(" 45.0k / 200.0M, 45.0k items/sec, "));
+}
+
+#[test]
+fn estimated_completion_time() {
+    let mut progressor = (0..4).progress().with_unicode(false);
+    let fake_now = progressor.started_iterating + Duration::from_secs(10);
+    progressor.set_fake_now(fake_now);
+    let (state, _) = progressor.next().unwrap();
+    // Finishes 40s after starting, i.e. about 40s from now
+    let expected = SystemTime::now() + Duration::from_secs(40);
+    let when = state.estimated_completion_time().unwrap();
+    let diff = match when.duration_since(expected) {
+        Ok(diff) => diff,
+        Err(e) => e.duration(),
+    };
+    assert!(diff < Duration::from_secs(1), "{:?}", diff);
+
+    let line = state.bar_line(&BarStyle::default().completion_time(true));
+    assert!(
+        line.starts_with("[#####               ]  25.0% 1 / 4, 0.1 items/sec, ETA 30s, done at "),
+        "{}",
+        line
+    );
+    assert!(line.ends_with(" UTC"), "{}", line);
+    assert!(!state.bar_line(&BarStyle::default()).contains("done at"));
+
+    // How long until the ETA is measured by the recorder's clock
+    let clock = MockClock::new();
+    let mut progressor = (0..4).progress().with_clock(clock.clone());
+    clock.advance(Duration::from_secs(10));
+    let (state, _) = progressor.next().unwrap();
+    clock.advance(Duration::from_secs(20));
+    let expected = SystemTime::now() + Duration::from_secs(10);
+    let when = state.estimated_completion_time().unwrap();
+    let diff = match when.duration_since(expected) {
+        Ok(diff) => diff,
+        Err(e) => e.duration(),
+    };
+    assert!(diff < Duration::from_secs(1), "{:?}", diff);
+
+    let (state, _) = (0..).progress().next().unwrap();
+    assert_eq!(state.estimated_completion_time(), None);
+
+    // Too far in the future for an `Instant`
+    let clock = MockClock::new();
+    let mut progressor = (0..)
+        .progress()
+        .assume_size(10_000_000_000_000_000_000)
+        .with_clock(clock.clone());
+    clock.advance(Duration::from_secs(1));
+    let (state, _) = progressor.next().unwrap();
+    assert!(state.eta().is_some());
+    assert_eq!(state.eta_instant(), None);
+    assert_eq!(state.estimated_completion_time(), None);
+}
+
+#[test]
+fn progress_counter() {
+    struct Timer(std::cell::Cell<u64>);
+
+    impl TickSource for Timer {
+        fn ticks_per_sec(&self) -> u64 {
+            4
+        }
+        fn now(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    let timer = Timer(std::cell::Cell::new(100));
+    let mut counter = ProgressCounter::new(&timer, 10);
+    assert_eq!(counter.fraction(), Some(0.));
+    assert_eq!(counter.rate(), 0.);
+    assert_eq!(counter.eta(), None);
+
+    timer.0.set(106);
+    counter.add(3);
+    assert_eq!(counter.duration_since_start(), Duration::from_millis(1_500));
+    assert_eq!(counter.fraction(), Some(0.3));
+    assert_eq!(counter.rate(), 2.);
+    assert_eq!(counter.eta(), Some(Duration::from_millis(3_500)));
+
+    counter.add(20);
+    assert_eq!(counter.fraction(), Some(1.));
+    assert_eq!(counter.eta(), Some(Duration::ZERO));
+
+    counter.set_total(None);
+    assert_eq!(counter.percent(), None);
+    assert_eq!(counter.eta(), None);
+
+    // Nothing to do
+    let counter = ProgressCounter::new(&timer, 0);
+    assert_eq!(counter.fraction(), Some(1.));
+
+    // An ETA too long for a `Duration`
+    let timer = Timer(std::cell::Cell::new(0));
+    let mut counter = ProgressCounter::new(&timer, usize::MAX);
+    counter.inc();
+    timer.0.set(40);
+    assert_eq!(counter.eta(), None);
+
+    // With a `Clock`
+    let clock = MockClock::new();
+    let mut counter = ProgressCounter::new(ClockTicks::new(clock.clone()), 4);
+    clock.advance(Duration::from_millis(2_500));
+    counter.inc();
+    assert_eq!(counter.duration_since_start(), Duration::from_millis(2_500));
+    assert_eq!(counter.percent(), Some(25.));
+    assert_eq!(counter.eta(), Some(Duration::from_millis(7_500)));
+}
@@ -0,0 +1,65 @@
+//! Report progress with the `tracing` crate.
+
+use super::{OptionalProgressRecorderIter, ProgressRecord, ProgressRecorderIter};
+use std::time::Duration;
+use tracing::{Level, Span};
+
+/// The span for the whole iteration, and how often progress events are emitted
+pub(crate) struct ProgressTracer {
+    pub(crate) span: Span,
+    every: Duration,
+}
+
+impl ProgressTracer {
+    pub(crate) fn update(&self, record: &ProgressRecord) {
+        if record.should_do_every_n_sec(self.every.as_secs_f32()) {
+            tracing::event!(
+                parent: &self.span,
+                Level::INFO,
+                count = record.num_done(),
+                rate = record.rate(),
+                percent = record.percent(),
+                eta_secs = record.eta().map(|eta| eta.as_secs_f64()),
+                "progress"
+            );
+        }
+    }
+}
+
+impl<I: Iterator> OptionalProgressRecorderIter<I> {
+    /// Report progress with the `tracing` crate. An `iter_progress` span (with a `total` field,
+    /// if known) is opened for the whole iteration, and closed when the iterator ends. The
+    /// underlying iterator's `.next()` is run inside it. At most every `every`, an `INFO` event
+    /// is emitted in it, with `count`, `rate`, `percent` & `eta_secs` fields.
+    ///
+    /// The span is a child of the current span when this is called.
+    ///
+    /// Only available with the `tracing` feature.
+    ///
+    /// ```
+    /// # use iter_progress::OptionalProgressableIter;
+    /// # use std::time::Duration;
+    /// let progressor = (0..1_000)
+    ///     .optional_progress(10)
+    ///     .trace_every(Duration::from_secs(5));
+    /// for (_state, _num) in progressor {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn trace_every(self, every: Duration) -> Self {
+        let mut res = self;
+        let span = tracing::info_span!("iter_progress", total = res.total());
+        res.tracer = Some(ProgressTracer { span, every });
+        res
+    }
+}
+
+impl<I: Iterator> ProgressRecorderIter<I> {
+    /// Report progress with the `tracing` crate. See
+    /// `OptionalProgressRecorderIter::trace_every`.
+    ///
+    /// Only available with the `tracing` feature.
+    pub fn trace_every(self, every: Duration) -> Self {
+        ProgressRecorderIter(self.0.trace_every(every))
+    }
+}
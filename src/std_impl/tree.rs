@@ -0,0 +1,231 @@
+//! A tree of progress, for jobs made of sub-tasks.
+
+use super::{ascii_bar, OptionalProgressRecorderIter, ProgressHandle, ProgressRecord};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A tree of progress, for a job with sub-tasks (which can have their own sub-tasks). Each node
+/// has a label, and either its own progress, or (if it has children) the weighted progress of its
+/// children. Children can be added & removed while the job runs.
+///
+/// A node's own progress can come from a recorder, live, with `.add_recorder(…)` or
+/// `.attach(…)`, or be set by hand, with `.update(…)` or `.set_progress(…)`. To draw it, add it
+/// to a `MultiProgress` with `MultiProgress::add_tree`, which redraws it (with each node's
+/// children indented under it) along with any other progress. Or call `.render()` when it's to
+/// be shown, or draw it with `ProgressWidget`.
+///
+/// `ProgressTree` is a cheap, cloneable, handle to a node, so it can be given to other threads.
+///
+/// ```
+/// # use iter_progress::{ProgressableIter, ProgressTree};
+/// let job = ProgressTree::new("build");
+/// let compile = job.add_child("compile", 3.);
+/// let link = job.add_child("link", 1.);
+///
+/// let files = vec!["a.rs", "b.rs"];
+/// let mut progressor = files.iter().progress();
+/// compile.attach(progressor.handle());
+/// for (_state, _file) in progressor {
+///     // ...
+/// }
+/// assert_eq!(job.fraction(), Some(0.75));
+///
+/// link.finish();
+/// assert_eq!(job.fraction(), Some(1.));
+/// println!("{}", job.render());
+/// ```
+#[derive(Clone, Debug)]
+pub struct ProgressTree {
+    node: Arc<Mutex<Node>>,
+}
+
+#[derive(Debug)]
+struct Node {
+    label: String,
+
+    /// How much this counts towards the parent's progress, relative to its siblings
+    weight: f64,
+
+    done: usize,
+    fraction: Option<f64>,
+    finished: bool,
+
+    /// If set, the recorder this node's progress comes from
+    handle: Option<ProgressHandle>,
+
+    children: Vec<ProgressTree>,
+}
+
+impl ProgressTree {
+    /// Create a new tree, with just the root node.
+    pub fn new(label: impl Into<String>) -> ProgressTree {
+        ProgressTree::with_weight(label, 1.)
+    }
+
+    fn with_weight(label: impl Into<String>, weight: f64) -> ProgressTree {
+        ProgressTree {
+            node: Arc::new(Mutex::new(Node {
+                label: label.into(),
+                weight,
+                done: 0,
+                fraction: None,
+                finished: false,
+                handle: None,
+                children: Vec::new(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Node> {
+        // A panic while holding the lock can't leave the node inconsistent, so carry on
+        self.node.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Add a child node. `weight` is how much it counts towards this node's progress, relative to
+    /// the other children (e.g. the expected time it will take).
+    ///
+    /// # Panics
+    ///
+    /// If `weight` is negative, or not finite.
+    pub fn add_child(&self, label: impl Into<String>, weight: impl Into<f64>) -> ProgressTree {
+        let weight = weight.into();
+        assert!(
+            weight.is_finite() && weight >= 0.,
+            "weight must be finite & not negative, not {}",
+            weight
+        );
+        let child = ProgressTree::with_weight(label, weight);
+        self.lock().children.push(child.clone());
+        child
+    }
+
+    /// Add a child node whose progress is `progressor`'s, live. It's labelled with its label
+    /// (`OptionalProgressRecorderIter::with_label`), or `label` if it has none. See
+    /// `.add_child(…)` for `weight`.
+    pub fn add_recorder<I: Iterator>(
+        &self,
+        label: impl Into<String>,
+        weight: impl Into<f64>,
+        progressor: &mut OptionalProgressRecorderIter<I>,
+    ) -> ProgressTree {
+        let label = match progressor.label {
+            Some(ref label) => label.to_string(),
+            None => label.into(),
+        };
+        let child = self.add_child(label, weight);
+        child.attach(progressor.handle());
+        child
+    }
+
+    /// Take this node's progress from the recorder of `handle`, live, from now on.
+    pub fn attach(&self, handle: ProgressHandle) {
+        self.lock().handle = Some(handle);
+    }
+
+    /// Remove a child node (and all its children). It no longer counts towards this node's
+    /// progress. Returns false if it isn't a child of this node.
+    pub fn remove_child(&self, child: &ProgressTree) -> bool {
+        let mut node = self.lock();
+        let len_before = node.children.len();
+        node.children.retain(|c| !Arc::ptr_eq(&c.node, &child.node));
+        node.children.len() != len_before
+    }
+
+    /// Set this node's progress from a `ProgressRecord`.
+    pub fn update(&self, record: &ProgressRecord) {
+        let mut node = self.lock();
+        node.done = record.num_done();
+        node.fraction = record.fraction();
+    }
+
+    /// Set this node's progress: `done` out of `total` (if known). A total of 0 means there's
+    /// nothing to do, so it's 100% done.
+    pub fn set_progress(&self, done: usize, total: impl Into<Option<usize>>) {
+        let mut node = self.lock();
+        node.done = done;
+        node.fraction = total.into().map(|total| {
+            if total == 0 {
+                1.
+            } else {
+                done as f64 / total as f64
+            }
+        });
+    }
+
+    /// Mark this node as finished, so it's 100% done. A node with a recorder is finished when
+    /// its iterator is exhausted.
+    pub fn finish(&self) {
+        self.lock().finished = true;
+    }
+
+    /// This node's label
+    pub fn label(&self) -> String {
+        self.lock().label.clone()
+    }
+
+    /// How far through this node is. For a node with children, this is the weighted mean of the
+    /// children's fractions, with children whose fraction isn't known counting as 0. None for a
+    /// node without children, whose total isn't known.
+    pub fn fraction(&self) -> Option<f64> {
+        let node = self.lock();
+        if node.finished {
+            return Some(1.);
+        }
+        if node.children.is_empty() {
+            return match node.handle {
+                Some(ref handle) if handle.is_finished() => Some(1.),
+                Some(ref handle) => handle.fraction(),
+                None => node.fraction,
+            };
+        }
+        let (done, total) = node
+            .children
+            .iter()
+            .map(|child| (child.fraction().unwrap_or(0.), child.lock().weight))
+            .fold((0., 0.), |(done, total), (f, w)| (done + f * w, total + w));
+        if total > 0. {
+            Some(done / total)
+        } else {
+            None
+        }
+    }
+
+    /// Render this node, and all its children (indented under it), one per line.
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        self.render_into(&mut output, 0);
+        output
+    }
+
+    fn render_into(&self, output: &mut String, depth: usize) {
+        let fraction = self.fraction();
+        let node = self.lock();
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        let _ = match fraction {
+            Some(f) => write!(
+                output,
+                "{:indent$}{} [{}] {:5.1}%",
+                "",
+                node.label,
+                ascii_bar(f, 20),
+                f * 100.,
+                indent = depth * 2
+            ),
+            None => write!(
+                output,
+                "{:indent$}{} {} items",
+                "",
+                node.label,
+                node.handle
+                    .as_ref()
+                    .map_or(node.done, |handle| handle.num_done()),
+                indent = depth * 2
+            ),
+        };
+        for child in node.children.iter() {
+            child.render_into(output, depth + 1);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! When to run a periodic callback.
+
+use std::time::Duration;
+
+use super::ProgressRecord;
+
+/// When `OptionalProgressRecorderIter::do_every` should call its callback: after a number of
+/// items, after some time, or whichever comes first, since it was last called.
+///
+/// Every `n` items is responsive for slow iterators, but floods for fast ones. Every `d` is
+/// throttled for fast iterators, but goes quiet if an item is slow. `.items_or_duration(…)` is
+/// both.
+///
+/// ```
+/// # use iter_progress::{ProgressableIter, Trigger};
+/// # use std::time::Duration;
+/// let progressor = (0..1_000).progress().do_every(
+///     Trigger::items_or_duration(100, Duration::from_secs(5)),
+///     |state| eprintln!("{} done", state.num_done()),
+/// );
+/// for (_state, _num) in progressor {
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trigger {
+    items: Option<usize>,
+    duration: Option<Duration>,
+}
+
+impl Trigger {
+    /// Fire when `n` items have been done since it last fired
+    pub fn items(n: usize) -> Trigger {
+        Trigger {
+            items: Some(n),
+            duration: None,
+        }
+    }
+
+    /// Fire when it's been `d` since it last fired
+    pub fn duration(d: Duration) -> Trigger {
+        Trigger {
+            items: None,
+            duration: Some(d),
+        }
+    }
+
+    /// Fire when either `n` items have been done, or it's been `d`, since it last fired
+    pub fn items_or_duration(n: usize, d: Duration) -> Trigger {
+        Trigger {
+            items: Some(n),
+            duration: Some(d),
+        }
+    }
+
+    /// Should it fire now, given the count & duration since the start, now & when it last fired
+    fn fires(&self, done: usize, elapsed: Duration, last: (usize, Duration)) -> bool {
+        self.items.is_some_and(|n| done.saturating_sub(last.0) >= n)
+            || self
+                .duration
+                .is_some_and(|d| elapsed.saturating_sub(last.1) >= d)
+    }
+}
+
+/// A callback for `.do_every(…)`
+pub(crate) struct Periodic {
+    trigger: Trigger,
+
+    /// `.num_done()` & `.duration_since_start()` when it was last called, or started
+    last_called: (usize, Duration),
+    f: Box<dyn FnMut(&ProgressRecord) + Send>,
+}
+
+impl Periodic {
+    pub(crate) fn new<F>(trigger: Trigger, done: usize, elapsed: Duration, f: F) -> Periodic
+    where
+        F: FnMut(&ProgressRecord) + Send + 'static,
+    {
+        Periodic {
+            trigger,
+            last_called: (done, elapsed),
+            f: Box::new(f),
+        }
+    }
+
+    /// Carry on from here, e.g. after resuming
+    pub(crate) fn reset_to(&mut self, done: usize, elapsed: Duration) {
+        self.last_called = (done, elapsed);
+    }
+
+    /// Call it, if the trigger fires
+    pub(crate) fn maybe_call(&mut self, record: &ProgressRecord) {
+        let done = record.num_done();
+        let elapsed = record.duration_since_start();
+        if self.trigger.fires(done, elapsed, self.last_called) {
+            self.last_called = (done, elapsed);
+            (self.f)(record);
+        }
+    }
+}
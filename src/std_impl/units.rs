@@ -0,0 +1,97 @@
+//! Human-readable formatting of counts, byte sizes & rates.
+
+/// Which multiples to use for byte sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteUnits {
+    /// Powers of 1024: KiB, MiB, GiB…
+    #[default]
+    Binary,
+
+    /// Powers of 1000: kB, MB, GB…
+    Decimal,
+}
+
+impl ByteUnits {
+    fn base(self) -> f64 {
+        match self {
+            ByteUnits::Binary => 1024.,
+            ByteUnits::Decimal => 1000.,
+        }
+    }
+
+    fn prefixes(self) -> &'static [&'static str] {
+        match self {
+            ByteUnits::Binary => &["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+            ByteUnits::Decimal => &["kB", "MB", "GB", "TB", "PB", "EB"],
+        }
+    }
+}
+
+/// Format a number of bytes for people, e.g. `"1.2 GiB"`, or `"512 B"`.
+///
+/// ```
+/// # use iter_progress::{format_bytes, ByteUnits};
+/// assert_eq!(format_bytes(512., ByteUnits::Binary), "512 B");
+/// assert_eq!(format_bytes(1_288_490_189., ByteUnits::Binary), "1.2 GiB");
+/// assert_eq!(format_bytes(1_500_000., ByteUnits::Decimal), "1.5 MB");
+/// ```
+pub fn format_bytes(bytes: f64, units: ByteUnits) -> String {
+    let base = units.base();
+    if !bytes.is_finite() || bytes.abs() < base {
+        return format!("{:.0} B", bytes);
+    }
+    let mut value = bytes;
+    let mut prefix = "";
+    for p in units.prefixes() {
+        value /= base;
+        prefix = p;
+        // Go up while it would still round to less than the next multiple
+        if value.abs() < base - 0.05 {
+            break;
+        }
+    }
+    format!("{:.1} {}", value, prefix)
+}
+
+/// Format a rate in bytes per second for people, e.g. `"12.4 MiB/s"`.
+///
+/// ```
+/// # use iter_progress::{format_byte_rate, ByteUnits};
+/// assert_eq!(format_byte_rate(13_002_342., ByteUnits::Binary), "12.4 MiB/s");
+/// ```
+pub fn format_byte_rate(bytes_per_sec: f64, units: ByteUnits) -> String {
+    format!("{}/s", format_bytes(bytes_per_sec, units))
+}
+
+/// Format a count for people, abbreviated with `k`, `M`, `B` or `T` when it's 1,000 or more,
+/// with `precision` decimal places, e.g. `"1.2M"`. Smaller whole numbers are shown as they are.
+///
+/// ```
+/// # use iter_progress::format_count;
+/// assert_eq!(format_count(1_234_567., 1), "1.2M");
+/// assert_eq!(format_count(45_300., 1), "45.3k");
+/// assert_eq!(format_count(45_300., 0), "45k");
+/// assert_eq!(format_count(999., 1), "999");
+/// assert_eq!(format_count(2.5, 1), "2.5");
+/// ```
+pub fn format_count(count: f64, precision: usize) -> String {
+    if !count.is_finite() || count.abs() < 1000. {
+        return if count.fract() == 0. {
+            format!("{:.0}", count)
+        } else {
+            format!("{:.*}", precision, count)
+        };
+    }
+    // The most it can be before it would be rounded up to the next multiple
+    let max = 1000. - 0.5 * 10f64.powi(-(precision as i32));
+    let mut value = count;
+    let mut suffix = "";
+    for s in &["k", "M", "B", "T"] {
+        value /= 1000.;
+        suffix = s;
+        if value.abs() < max {
+            break;
+        }
+    }
+    format!("{:.*}{}", precision, value, suffix)
+}
@@ -0,0 +1,110 @@
+//! Detect when the underlying iterator's `.next()` takes too long.
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::Instant;
+
+/// Runs a background thread which calls a callback if an item takes longer than the timeout
+pub(crate) struct Watchdog {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+struct State {
+    /// The index of the item we're waiting for, and since when
+    waiting: Option<(usize, Instant)>,
+
+    stop: bool,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Watchdog {
+    pub(crate) fn new<F>(timeout: Duration, callback: F) -> Watchdog
+    where
+        F: FnMut(usize, Duration) + Send + 'static,
+    {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                waiting: None,
+                stop: false,
+            }),
+            changed: Condvar::new(),
+        });
+        let thread_shared = shared.clone();
+        let thread = thread::spawn(move || watch(&thread_shared, timeout, callback));
+        Watchdog {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// We are now waiting for item `index`
+    pub(crate) fn start_item(&self, index: usize) {
+        self.shared.lock().waiting = Some((index, Instant::now()));
+        self.shared.changed.notify_one();
+    }
+
+    /// We have stopped waiting for an item
+    pub(crate) fn end_item(&self) {
+        self.shared.lock().waiting = None;
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shared.lock().stop = true;
+        self.shared.changed.notify_one();
+        if let Some(thread) = self.thread.take() {
+            // If the callback panicked, there's nothing to do about it now
+            let _ = thread.join();
+        }
+    }
+}
+
+fn watch<F>(shared: &Shared, timeout: Duration, mut callback: F)
+where
+    F: FnMut(usize, Duration),
+{
+    // When the item we last called the callback for started, so it's only called once per item
+    let mut fired_for = None;
+    let mut state = shared.lock();
+    loop {
+        if state.stop {
+            return;
+        }
+        state = match state.waiting {
+            Some((index, since)) if fired_for != Some(since) => {
+                let elapsed = since.elapsed();
+                if elapsed >= timeout {
+                    fired_for = Some(since);
+                    // Don't block the iterator while the callback runs
+                    drop(state);
+                    callback(index, elapsed);
+                    shared.lock()
+                } else {
+                    shared
+                        .changed
+                        .wait_timeout(state, timeout - elapsed)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+            }
+            _ => shared
+                .changed
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner()),
+        };
+    }
+}
@@ -0,0 +1,107 @@
+//! A `ratatui` widget showing progress.
+
+use super::{format_duration, ProgressRecord, ProgressTree};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Style;
+use ratatui::widgets::{Block, Gauge, Widget};
+
+/// A `ratatui` widget which draws a gauge of the progress, with a label of the percent, ETA &
+/// rate (when known).
+///
+/// Create it from a `ProgressRecord`, or from a `ProgressTree` (which can be shared with the
+/// threads doing the work).
+///
+/// ```
+/// # extern crate ratatui;
+/// # extern crate iter_progress;
+/// # use iter_progress::{ProgressableIter, ProgressWidget};
+/// # use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+/// let (state, _) = (0..100).progress().next().unwrap();
+/// let area = Rect::new(0, 0, 60, 1);
+/// let mut buf = Buffer::empty(area);
+/// ProgressWidget::from(&state).render(area, &mut buf);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ProgressWidget<'a> {
+    fraction: Option<f64>,
+    label: String,
+    block: Option<Block<'a>>,
+    style: Style,
+    gauge_style: Style,
+}
+
+impl<'a> ProgressWidget<'a> {
+    /// Surround the gauge with this block
+    pub fn block(self, block: Block<'a>) -> Self {
+        let mut res = self;
+        res.block = Some(block);
+        res
+    }
+
+    /// Style of the whole widget
+    pub fn style(self, style: Style) -> Self {
+        let mut res = self;
+        res.style = style;
+        res
+    }
+
+    /// Style of the filled part of the gauge
+    pub fn gauge_style(self, style: Style) -> Self {
+        let mut res = self;
+        res.gauge_style = style;
+        res
+    }
+}
+
+impl<'a, 'r> From<&'r ProgressRecord> for ProgressWidget<'a> {
+    fn from(record: &'r ProgressRecord) -> Self {
+        let mut label = match record.percent() {
+            Some(percent) => format!("{:.1}%", percent),
+            None => format!("{} items", record.num_done()),
+        };
+        if let Some(eta) = record.eta() {
+            label.push_str(&format!(", ETA {}", format_duration(eta)));
+        }
+        label.push_str(&format!(", {:.1} items/sec", record.rate()));
+        ProgressWidget {
+            fraction: record.fraction(),
+            label,
+            ..ProgressWidget::default()
+        }
+    }
+}
+
+impl<'a, 'r> From<&'r ProgressTree> for ProgressWidget<'a> {
+    fn from(tree: &'r ProgressTree) -> Self {
+        let fraction = tree.fraction();
+        let label = match fraction {
+            Some(fraction) => format!("{}: {:.1}%", tree.label(), fraction * 100.),
+            None => tree.label(),
+        };
+        ProgressWidget {
+            fraction,
+            label,
+            ..ProgressWidget::default()
+        }
+    }
+}
+
+impl<'a> Widget for ProgressWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut gauge = Gauge::default()
+            .ratio(
+                self.fraction
+                    .filter(|f| f.is_finite())
+                    .unwrap_or(0.)
+                    .clamp(0., 1.),
+            )
+            .label(self.label)
+            .style(self.style)
+            .gauge_style(self.gauge_style);
+        if let Some(block) = self.block {
+            gauge = gauge.block(block);
+        }
+        gauge.render(area, buf);
+    }
+}